@@ -0,0 +1,130 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use core_foundation::{base::CFType, dictionary::CFDictionary, propertylist::CFPropertyList, string::CFString};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use crate::format_description::{extensions_with_atom, find_extension_atom};
+
+pub const AV1_CONFIGURATION_ATOM: &str = "av1C";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Av1ConfigError {
+    UnexpectedEnd,
+    InvalidMarker,
+    UnsupportedVersion,
+    ValueOutOfRange,
+    MissingConfigurationAtom,
+}
+
+impl Display for Av1ConfigError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Av1ConfigError::UnexpectedEnd => write!(f, "unexpected end of configuration record"),
+            Av1ConfigError::InvalidMarker => write!(f, "marker bit is not set"),
+            Av1ConfigError::UnsupportedVersion => write!(f, "unsupported configuration version"),
+            Av1ConfigError::ValueOutOfRange => write!(f, "field value does not fit in the configuration record"),
+            Av1ConfigError::MissingConfigurationAtom => write!(f, "no av1C atom in the sample description extension atoms"),
+        }
+    }
+}
+
+impl Error for Av1ConfigError {}
+
+/// The AV1CodecConfigurationRecord carried in an av1C box, as defined in the AV1 Codec ISO Media File Format Binding
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Av1CodecConfigurationRecord {
+    pub seq_profile: u8,
+    pub seq_level_idx_0: u8,
+    pub seq_tier_0: bool,
+    pub high_bitdepth: bool,
+    pub twelve_bit: bool,
+    pub monochrome: bool,
+    pub chroma_subsampling_x: bool,
+    pub chroma_subsampling_y: bool,
+    pub chroma_sample_position: u8,
+    pub initial_presentation_delay_minus_one: Option<u8>,
+    // Zero or more OBUs, normally a sequence header followed by metadata OBUs
+    pub config_obus: Vec<u8>,
+}
+
+impl Av1CodecConfigurationRecord {
+    pub fn parse(data: &[u8]) -> Result<Self, Av1ConfigError> {
+        if data.len() < 4 {
+            return Err(Av1ConfigError::UnexpectedEnd);
+        }
+        if data[0] & 0x80 == 0 {
+            return Err(Av1ConfigError::InvalidMarker);
+        }
+        if data[0] & 0x7F != 1 {
+            return Err(Av1ConfigError::UnsupportedVersion);
+        }
+        Ok(Av1CodecConfigurationRecord {
+            seq_profile: data[1] >> 5,
+            seq_level_idx_0: data[1] & 0x1F,
+            seq_tier_0: data[2] & 0x80 != 0,
+            high_bitdepth: data[2] & 0x40 != 0,
+            twelve_bit: data[2] & 0x20 != 0,
+            monochrome: data[2] & 0x10 != 0,
+            chroma_subsampling_x: data[2] & 0x08 != 0,
+            chroma_subsampling_y: data[2] & 0x04 != 0,
+            chroma_sample_position: data[2] & 0x03,
+            initial_presentation_delay_minus_one: if data[3] & 0x10 != 0 { Some(data[3] & 0x0F) } else { None },
+            config_obus: data[4..].to_vec(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Av1ConfigError> {
+        if self.seq_profile > 7 || self.seq_level_idx_0 > 31 || self.chroma_sample_position > 3 {
+            return Err(Av1ConfigError::ValueOutOfRange);
+        }
+        let delay = match self.initial_presentation_delay_minus_one {
+            Some(delay) if delay > 15 => return Err(Av1ConfigError::ValueOutOfRange),
+            Some(delay) => 0x10 | delay,
+            None => 0,
+        };
+        let mut data = Vec::with_capacity(4 + self.config_obus.len());
+        data.push(0x81);
+        data.push(self.seq_profile << 5 | self.seq_level_idx_0);
+        data.push(
+            (self.seq_tier_0 as u8) << 7 |
+                (self.high_bitdepth as u8) << 6 |
+                (self.twelve_bit as u8) << 5 |
+                (self.monochrome as u8) << 4 |
+                (self.chroma_subsampling_x as u8) << 3 |
+                (self.chroma_subsampling_y as u8) << 2 |
+                self.chroma_sample_position,
+        );
+        data.push(delay);
+        data.extend_from_slice(&self.config_obus);
+        Ok(data)
+    }
+
+    // The bit depth signalled by high_bitdepth and twelve_bit
+    #[inline]
+    pub fn bit_depth(&self) -> u8 {
+        match (self.high_bitdepth, self.twelve_bit) {
+            (true, true) => 12,
+            (true, false) => 10,
+            _ => 8,
+        }
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl Av1CodecConfigurationRecord {
+    // The extensions dictionary carrying the record as an av1C sample description extension atom, for
+    // CMVideoFormatDescription::new with kCMVideoCodecType_AV1
+    pub fn to_extensions(&self) -> Result<CFDictionary<CFString, CFType>, Av1ConfigError> {
+        Ok(extensions_with_atom(AV1_CONFIGURATION_ATOM, &self.to_bytes()?))
+    }
+
+    // Reads the record from the value of the kCMFormatDescriptionExtension_SampleDescriptionExtensionAtoms extension
+    pub fn from_extension_atoms(atoms: &CFPropertyList) -> Result<Self, Av1ConfigError> {
+        let data = find_extension_atom(atoms, AV1_CONFIGURATION_ATOM).ok_or(Av1ConfigError::MissingConfigurationAtom)?;
+        Self::parse(&data)
+    }
+}
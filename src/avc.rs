@@ -0,0 +1,841 @@
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use core_foundation::{base::CFType, dictionary::CFDictionary, string::CFString};
+
+use crate::{
+    bitstream::{remove_emulation_prevention, BitReader, ByteReader},
+    color_description::ColorDescription,
+    time::CMTime,
+    video_format_extensions::{ChromaLocation, CleanAperture, PixelAspectRatio, VideoFormatExtensions},
+};
+
+pub const AVC_CONFIGURATION_ATOM: &str = "avcC";
+
+pub const AVC_NAL_UNIT_TYPE_SPS: u8 = 7;
+pub const AVC_NAL_UNIT_TYPE_PPS: u8 = 8;
+pub const AVC_NAL_UNIT_TYPE_SPS_EXT: u8 = 13;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AvcConfigError {
+    UnexpectedEnd,
+    UnsupportedVersion,
+    InvalidNalUnitHeaderLength,
+    EmptyParameterSet,
+    UnexpectedNalUnitType,
+    MissingSequenceParameterSet,
+    TooManyParameterSets,
+    ParameterSetTooLong,
+    InvalidChromaFormat,
+    InvalidBitDepth,
+    InvalidSequenceParameterSet,
+}
+
+impl Display for AvcConfigError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            AvcConfigError::UnexpectedEnd => write!(f, "unexpected end of configuration record"),
+            AvcConfigError::UnsupportedVersion => write!(f, "unsupported configuration version"),
+            AvcConfigError::InvalidNalUnitHeaderLength => write!(f, "NAL unit header length must be 1, 2 or 4"),
+            AvcConfigError::EmptyParameterSet => write!(f, "parameter set is empty"),
+            AvcConfigError::UnexpectedNalUnitType => write!(f, "parameter set has an unexpected NAL unit type"),
+            AvcConfigError::MissingSequenceParameterSet => write!(f, "no sequence parameter set"),
+            AvcConfigError::TooManyParameterSets => write!(f, "too many parameter sets"),
+            AvcConfigError::ParameterSetTooLong => write!(f, "parameter set is longer than 65535 bytes"),
+            AvcConfigError::InvalidChromaFormat => write!(f, "chroma format must be between 0 and 3"),
+            AvcConfigError::InvalidBitDepth => write!(f, "bit depth must be between 8 and 15"),
+            AvcConfigError::InvalidSequenceParameterSet => write!(f, "sequence parameter set is malformed"),
+        }
+    }
+}
+
+impl Error for AvcConfigError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AvcParameterSetError {
+    UnexpectedEnd,
+    UnexpectedNalUnitType,
+    InvalidValue,
+}
+
+impl Display for AvcParameterSetError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            AvcParameterSetError::UnexpectedEnd => write!(f, "unexpected end of parameter set"),
+            AvcParameterSetError::UnexpectedNalUnitType => write!(f, "NAL unit is not the expected parameter set"),
+            AvcParameterSetError::InvalidValue => write!(f, "parameter set has a value outside its allowed range"),
+        }
+    }
+}
+
+impl Error for AvcParameterSetError {}
+
+// Every profile but Baseline, Main and Extended carries the chroma format and bit depth extension in its
+// configuration record
+#[inline]
+fn has_high_profile_extension(profile_indication: u8) -> bool {
+    !matches!(profile_indication, 66 | 77 | 88)
+}
+
+#[inline]
+fn nal_unit_type(nal_unit: &[u8]) -> Option<u8> {
+    nal_unit.first().map(|header| header & 0x1F)
+}
+
+/// The chroma format and bit depth extension of a High profile AVCDecoderConfigurationRecord
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AvcHighProfileExtension {
+    pub chroma_format: u8,
+    pub bit_depth_luma: u8,
+    pub bit_depth_chroma: u8,
+    pub sequence_parameter_set_extensions: Vec<Vec<u8>>,
+}
+
+/// The AVCDecoderConfigurationRecord carried in an avcC box, as defined in ISO/IEC 14496-15
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AvcDecoderConfigurationRecord {
+    pub profile_indication: u8,
+    pub profile_compatibility: u8,
+    pub level_indication: u8,
+    pub nal_unit_header_length: u8,
+    pub sequence_parameter_sets: Vec<Vec<u8>>,
+    pub picture_parameter_sets: Vec<Vec<u8>>,
+    pub high_profile_extension: Option<AvcHighProfileExtension>,
+}
+
+#[inline]
+fn read_u8(reader: &mut ByteReader) -> Result<u8, AvcConfigError> {
+    reader.read_u8().ok_or(AvcConfigError::UnexpectedEnd)
+}
+
+fn read_parameter_sets(reader: &mut ByteReader, count: usize, nal_unit_type: u8) -> Result<Vec<Vec<u8>>, AvcConfigError> {
+    let mut parameter_sets = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = reader.read_u16().ok_or(AvcConfigError::UnexpectedEnd)? as usize;
+        let parameter_set = reader.read_bytes(len).ok_or(AvcConfigError::UnexpectedEnd)?;
+        check_parameter_set(parameter_set, nal_unit_type)?;
+        parameter_sets.push(parameter_set.to_vec());
+    }
+    Ok(parameter_sets)
+}
+
+fn check_parameter_set(parameter_set: &[u8], expected_nal_unit_type: u8) -> Result<(), AvcConfigError> {
+    match nal_unit_type(parameter_set) {
+        None => Err(AvcConfigError::EmptyParameterSet),
+        Some(nal_unit_type) if nal_unit_type != expected_nal_unit_type => Err(AvcConfigError::UnexpectedNalUnitType),
+        Some(_) => Ok(()),
+    }
+}
+
+fn write_parameter_sets(data: &mut Vec<u8>, parameter_sets: &[Vec<u8>], nal_unit_type: u8) -> Result<(), AvcConfigError> {
+    for parameter_set in parameter_sets {
+        check_parameter_set(parameter_set, nal_unit_type)?;
+        let len = u16::try_from(parameter_set.len()).map_err(|_| AvcConfigError::ParameterSetTooLong)?;
+        data.extend_from_slice(&len.to_be_bytes());
+        data.extend_from_slice(parameter_set);
+    }
+    Ok(())
+}
+
+#[inline]
+fn check_nal_unit_header_length(nal_unit_header_length: u8) -> Result<(), AvcConfigError> {
+    match nal_unit_header_length {
+        1 | 2 | 4 => Ok(()),
+        _ => Err(AvcConfigError::InvalidNalUnitHeaderLength),
+    }
+}
+
+impl AvcDecoderConfigurationRecord {
+    pub fn parse(data: &[u8]) -> Result<Self, AvcConfigError> {
+        let mut reader = ByteReader::new(data);
+        if read_u8(&mut reader)? != 1 {
+            return Err(AvcConfigError::UnsupportedVersion);
+        }
+        let profile_indication = read_u8(&mut reader)?;
+        let profile_compatibility = read_u8(&mut reader)?;
+        let level_indication = read_u8(&mut reader)?;
+        let nal_unit_header_length = (read_u8(&mut reader)? & 0x03) + 1;
+        check_nal_unit_header_length(nal_unit_header_length)?;
+        let count = (read_u8(&mut reader)? & 0x1F) as usize;
+        let sequence_parameter_sets = read_parameter_sets(&mut reader, count, AVC_NAL_UNIT_TYPE_SPS)?;
+        let count = read_u8(&mut reader)? as usize;
+        let picture_parameter_sets = read_parameter_sets(&mut reader, count, AVC_NAL_UNIT_TYPE_PPS)?;
+        // Many writers omit the extension, so it is only read when present
+        let high_profile_extension = if has_high_profile_extension(profile_indication) && !reader.is_empty() {
+            let chroma_format = read_u8(&mut reader)? & 0x03;
+            let bit_depth_luma = (read_u8(&mut reader)? & 0x07) + 8;
+            let bit_depth_chroma = (read_u8(&mut reader)? & 0x07) + 8;
+            let count = read_u8(&mut reader)? as usize;
+            let sequence_parameter_set_extensions = read_parameter_sets(&mut reader, count, AVC_NAL_UNIT_TYPE_SPS_EXT)?;
+            Some(AvcHighProfileExtension { chroma_format, bit_depth_luma, bit_depth_chroma, sequence_parameter_set_extensions })
+        } else {
+            None
+        };
+        Ok(AvcDecoderConfigurationRecord {
+            profile_indication,
+            profile_compatibility,
+            level_indication,
+            nal_unit_header_length,
+            sequence_parameter_sets,
+            picture_parameter_sets,
+            high_profile_extension,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, AvcConfigError> {
+        check_nal_unit_header_length(self.nal_unit_header_length)?;
+        if self.sequence_parameter_sets.len() > 31 || self.picture_parameter_sets.len() > 255 {
+            return Err(AvcConfigError::TooManyParameterSets);
+        }
+        let mut data = vec![
+            1,
+            self.profile_indication,
+            self.profile_compatibility,
+            self.level_indication,
+            0xFC | (self.nal_unit_header_length - 1),
+            0xE0 | self.sequence_parameter_sets.len() as u8,
+        ];
+        write_parameter_sets(&mut data, &self.sequence_parameter_sets, AVC_NAL_UNIT_TYPE_SPS)?;
+        data.push(self.picture_parameter_sets.len() as u8);
+        write_parameter_sets(&mut data, &self.picture_parameter_sets, AVC_NAL_UNIT_TYPE_PPS)?;
+        if let Some(extension) = &self.high_profile_extension {
+            if extension.chroma_format > 3 {
+                return Err(AvcConfigError::InvalidChromaFormat);
+            }
+            if !(8..=15).contains(&extension.bit_depth_luma) || !(8..=15).contains(&extension.bit_depth_chroma) {
+                return Err(AvcConfigError::InvalidBitDepth);
+            }
+            let count = u8::try_from(extension.sequence_parameter_set_extensions.len()).map_err(|_| AvcConfigError::TooManyParameterSets)?;
+            data.extend_from_slice(&[
+                0xFC | extension.chroma_format,
+                0xF8 | (extension.bit_depth_luma - 8),
+                0xF8 | (extension.bit_depth_chroma - 8),
+                count,
+            ]);
+            write_parameter_sets(&mut data, &extension.sequence_parameter_set_extensions, AVC_NAL_UNIT_TYPE_SPS_EXT)?;
+        }
+        Ok(data)
+    }
+
+    // Builds a record from parameter set NAL units in any order, as passed to
+    // CMVideoFormatDescription::from_h264_parameter_sets; the profile, level and High profile extension are taken from
+    // the first sequence parameter set
+    pub fn from_parameter_sets(parameter_sets: &[&[u8]], nal_unit_header_length: i32) -> Result<Self, AvcConfigError> {
+        let nal_unit_header_length = u8::try_from(nal_unit_header_length).map_err(|_| AvcConfigError::InvalidNalUnitHeaderLength)?;
+        check_nal_unit_header_length(nal_unit_header_length)?;
+        let mut record = AvcDecoderConfigurationRecord { nal_unit_header_length, ..Default::default() };
+        let mut sequence_parameter_set_extensions = Vec::new();
+        for parameter_set in parameter_sets {
+            match nal_unit_type(parameter_set) {
+                None => return Err(AvcConfigError::EmptyParameterSet),
+                Some(AVC_NAL_UNIT_TYPE_SPS) => record.sequence_parameter_sets.push(parameter_set.to_vec()),
+                Some(AVC_NAL_UNIT_TYPE_PPS) => record.picture_parameter_sets.push(parameter_set.to_vec()),
+                Some(AVC_NAL_UNIT_TYPE_SPS_EXT) => sequence_parameter_set_extensions.push(parameter_set.to_vec()),
+                Some(_) => return Err(AvcConfigError::UnexpectedNalUnitType),
+            }
+        }
+        let sps = record.sequence_parameter_sets.first().ok_or(AvcConfigError::MissingSequenceParameterSet)?;
+        if sps.len() < 4 {
+            return Err(AvcConfigError::InvalidSequenceParameterSet);
+        }
+        record.profile_indication = sps[1];
+        record.profile_compatibility = sps[2];
+        record.level_indication = sps[3];
+        if has_high_profile_extension(record.profile_indication) {
+            let sps = AvcSequenceParameterSet::parse(sps).map_err(|_| AvcConfigError::InvalidSequenceParameterSet)?;
+            record.high_profile_extension = Some(AvcHighProfileExtension {
+                chroma_format: sps.chroma_format_idc,
+                bit_depth_luma: sps.bit_depth_luma,
+                bit_depth_chroma: sps.bit_depth_chroma,
+                sequence_parameter_set_extensions,
+            });
+        }
+        Ok(record)
+    }
+
+    // The parameter sets in the order sequence parameter sets, picture parameter sets, sequence parameter set
+    // extensions, and the NAL unit header length, as taken by CMVideoFormatDescription::from_h264_parameter_sets
+    pub fn to_parameter_sets(&self) -> (Vec<&[u8]>, i32) {
+        let extensions = self.high_profile_extension.iter().flat_map(|extension| extension.sequence_parameter_set_extensions.iter());
+        let parameter_sets =
+            self.sequence_parameter_sets.iter().chain(self.picture_parameter_sets.iter()).chain(extensions).map(Vec::as_slice).collect();
+        (parameter_sets, self.nal_unit_header_length as i32)
+    }
+}
+
+#[inline]
+fn read_bits(reader: &mut BitReader, count: u32) -> Result<u32, AvcParameterSetError> {
+    reader.read_bits(count).ok_or(AvcParameterSetError::UnexpectedEnd)
+}
+
+#[inline]
+fn read_flag(reader: &mut BitReader) -> Result<bool, AvcParameterSetError> {
+    reader.read_bit().ok_or(AvcParameterSetError::UnexpectedEnd)
+}
+
+#[inline]
+fn read_se(reader: &mut BitReader) -> Result<i32, AvcParameterSetError> {
+    reader.read_se().ok_or(AvcParameterSetError::UnexpectedEnd)
+}
+
+// Reads ue(v) and fails unless the value is at most max
+#[inline]
+fn read_ue(reader: &mut BitReader, max: u32) -> Result<u32, AvcParameterSetError> {
+    match reader.read_ue() {
+        Some(value) if value <= max => Ok(value),
+        Some(_) => Err(AvcParameterSetError::InvalidValue),
+        None => Err(AvcParameterSetError::UnexpectedEnd),
+    }
+}
+
+fn skip_scaling_list(reader: &mut BitReader, size: usize) -> Result<(), AvcParameterSetError> {
+    let (mut last_scale, mut next_scale) = (8, 8);
+    for _ in 0..size {
+        if next_scale != 0 {
+            let delta_scale = read_se(reader)?;
+            next_scale = (last_scale + delta_scale + 256) % 256;
+        }
+        if next_scale != 0 {
+            last_scale = next_scale;
+        }
+    }
+    Ok(())
+}
+
+fn skip_scaling_lists(reader: &mut BitReader, count: usize) -> Result<(), AvcParameterSetError> {
+    for i in 0..count {
+        if read_flag(reader)? {
+            skip_scaling_list(reader, if i < 6 { 16 } else { 64 })?;
+        }
+    }
+    Ok(())
+}
+
+// Strips the NAL unit header after checking its type
+fn nal_unit_payload(nal_unit: &[u8], expected_nal_unit_type: u8) -> Result<Vec<u8>, AvcParameterSetError> {
+    match nal_unit_type(nal_unit) {
+        Some(actual) if actual == expected_nal_unit_type => Ok(remove_emulation_prevention(&nal_unit[1..])),
+        Some(_) => Err(AvcParameterSetError::UnexpectedNalUnitType),
+        None => Err(AvcParameterSetError::UnexpectedEnd),
+    }
+}
+
+// The sample aspect ratios of aspect_ratio_idc 1 to 16
+const SAMPLE_ASPECT_RATIOS: [(u16, u16); 16] = [
+    (1, 1),
+    (12, 11),
+    (10, 11),
+    (16, 11),
+    (40, 33),
+    (24, 11),
+    (20, 11),
+    (32, 11),
+    (80, 33),
+    (18, 11),
+    (15, 11),
+    (64, 33),
+    (160, 99),
+    (4, 3),
+    (3, 2),
+    (2, 1),
+];
+
+/// The video usability information of an H.264 sequence parameter set, up to the timing information
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AvcVuiParameters {
+    pub sample_aspect_ratio: Option<(u16, u16)>,
+    pub overscan_appropriate: Option<bool>,
+    pub video_format: u8,
+    pub video_full_range_flag: bool,
+    // colour_primaries, transfer_characteristics and matrix_coefficients as ITU-T H.273 code points
+    pub colour_description: Option<(u8, u8, u8)>,
+    pub chroma_sample_loc_type: Option<(u32, u32)>,
+    // num_units_in_tick, time_scale and fixed_frame_rate_flag
+    pub timing_info: Option<(u32, u32, bool)>,
+}
+
+impl AvcVuiParameters {
+    fn parse(reader: &mut BitReader) -> Result<Self, AvcParameterSetError> {
+        let mut vui = AvcVuiParameters { video_format: 5, ..Default::default() };
+        if read_flag(reader)? {
+            let aspect_ratio_idc = read_bits(reader, 8)?;
+            vui.sample_aspect_ratio = match aspect_ratio_idc {
+                1..=16 => Some(SAMPLE_ASPECT_RATIOS[aspect_ratio_idc as usize - 1]),
+                255 => Some((read_bits(reader, 16)? as u16, read_bits(reader, 16)? as u16)),
+                _ => None,
+            };
+        }
+        if read_flag(reader)? {
+            vui.overscan_appropriate = Some(read_flag(reader)?);
+        }
+        if read_flag(reader)? {
+            vui.video_format = read_bits(reader, 3)? as u8;
+            vui.video_full_range_flag = read_flag(reader)?;
+            if read_flag(reader)? {
+                vui.colour_description = Some((read_bits(reader, 8)? as u8, read_bits(reader, 8)? as u8, read_bits(reader, 8)? as u8));
+            }
+        }
+        if read_flag(reader)? {
+            vui.chroma_sample_loc_type = Some((read_ue(reader, 5)?, read_ue(reader, 5)?));
+        }
+        if read_flag(reader)? {
+            let num_units_in_tick = read_bits(reader, 32)?;
+            let time_scale = read_bits(reader, 32)?;
+            vui.timing_info = Some((num_units_in_tick, time_scale, read_flag(reader)?));
+        }
+        Ok(vui)
+    }
+}
+
+/// An H.264 sequence parameter set, as defined in ITU-T H.264 7.3.2.1.1
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AvcSequenceParameterSet {
+    pub profile_idc: u8,
+    // constraint_set0_flag in the most significant bit through constraint_set5_flag and the reserved bits
+    pub constraint_flags: u8,
+    pub level_idc: u8,
+    pub seq_parameter_set_id: u8,
+    pub chroma_format_idc: u8,
+    pub separate_colour_plane_flag: bool,
+    pub bit_depth_luma: u8,
+    pub bit_depth_chroma: u8,
+    pub log2_max_frame_num: u8,
+    pub pic_order_cnt_type: u8,
+    pub max_num_ref_frames: u32,
+    pub pic_width_in_mbs: u32,
+    pub pic_height_in_map_units: u32,
+    pub frame_mbs_only_flag: bool,
+    pub mb_adaptive_frame_field_flag: bool,
+    pub direct_8x8_inference_flag: bool,
+    // frame_crop_left_offset, frame_crop_right_offset, frame_crop_top_offset and frame_crop_bottom_offset
+    pub frame_cropping: Option<(u32, u32, u32, u32)>,
+    pub vui_parameters: Option<AvcVuiParameters>,
+}
+
+impl AvcSequenceParameterSet {
+    // Parses a sequence parameter set NAL unit, starting with its header
+    pub fn parse(nal_unit: &[u8]) -> Result<Self, AvcParameterSetError> {
+        let rbsp = nal_unit_payload(nal_unit, AVC_NAL_UNIT_TYPE_SPS)?;
+        let reader = &mut BitReader::new(&rbsp);
+        let mut sps = AvcSequenceParameterSet {
+            profile_idc: read_bits(reader, 8)? as u8,
+            constraint_flags: read_bits(reader, 8)? as u8,
+            level_idc: read_bits(reader, 8)? as u8,
+            seq_parameter_set_id: read_ue(reader, 31)? as u8,
+            chroma_format_idc: 1,
+            bit_depth_luma: 8,
+            bit_depth_chroma: 8,
+            ..Default::default()
+        };
+        if matches!(sps.profile_idc, 100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135) {
+            sps.chroma_format_idc = read_ue(reader, 3)? as u8;
+            if sps.chroma_format_idc == 3 {
+                sps.separate_colour_plane_flag = read_flag(reader)?;
+            }
+            sps.bit_depth_luma = read_ue(reader, 6)? as u8 + 8;
+            sps.bit_depth_chroma = read_ue(reader, 6)? as u8 + 8;
+            read_flag(reader)?;
+            if read_flag(reader)? {
+                skip_scaling_lists(reader, if sps.chroma_format_idc != 3 { 8 } else { 12 })?;
+            }
+        }
+        sps.log2_max_frame_num = read_ue(reader, 12)? as u8 + 4;
+        sps.pic_order_cnt_type = read_ue(reader, 2)? as u8;
+        match sps.pic_order_cnt_type {
+            0 => {
+                read_ue(reader, 12)?;
+            }
+            1 => {
+                read_flag(reader)?;
+                read_se(reader)?;
+                read_se(reader)?;
+                for _ in 0..read_ue(reader, 255)? {
+                    read_se(reader)?;
+                }
+            }
+            _ => {}
+        }
+        sps.max_num_ref_frames = read_ue(reader, u32::MAX)?;
+        read_flag(reader)?;
+        sps.pic_width_in_mbs = read_ue(reader, u32::MAX - 1)? + 1;
+        sps.pic_height_in_map_units = read_ue(reader, u32::MAX - 1)? + 1;
+        sps.frame_mbs_only_flag = read_flag(reader)?;
+        if !sps.frame_mbs_only_flag {
+            sps.mb_adaptive_frame_field_flag = read_flag(reader)?;
+        }
+        sps.direct_8x8_inference_flag = read_flag(reader)?;
+        if read_flag(reader)? {
+            let max = u32::MAX;
+            sps.frame_cropping = Some((read_ue(reader, max)?, read_ue(reader, max)?, read_ue(reader, max)?, read_ue(reader, max)?));
+        }
+        if read_flag(reader)? {
+            sps.vui_parameters = Some(AvcVuiParameters::parse(reader)?);
+        }
+        Ok(sps)
+    }
+
+    // The width and height of the decoded picture in luma samples, before cropping
+    pub fn coded_size(&self) -> (u64, u64) {
+        let frame_height_in_mbs = (2 - self.frame_mbs_only_flag as u64) * self.pic_height_in_map_units as u64;
+        (self.pic_width_in_mbs as u64 * 16, frame_height_in_mbs * 16)
+    }
+
+    // The left, right, top and bottom frame cropping in luma samples
+    pub fn cropping(&self) -> (u64, u64, u64, u64) {
+        let (left, right, top, bottom) = match self.frame_cropping {
+            Some(offsets) => offsets,
+            None => return (0, 0, 0, 0),
+        };
+        let chroma_array_type = if self.separate_colour_plane_flag { 0 } else { self.chroma_format_idc };
+        let (crop_unit_x, crop_unit_y) = match chroma_array_type {
+            1 => (2, 2),
+            2 => (2, 1),
+            _ => (1, 1),
+        };
+        let crop_unit_y = crop_unit_y * (2 - self.frame_mbs_only_flag as u64);
+        (left as u64 * crop_unit_x, right as u64 * crop_unit_x, top as u64 * crop_unit_y, bottom as u64 * crop_unit_y)
+    }
+
+    // The width and height of the cropped picture, as reported by CMVideoFormatDescription::get_dimensions for a
+    // description created from this parameter set
+    pub fn dimensions(&self) -> Result<(i32, i32), AvcParameterSetError> {
+        let (width, height) = self.coded_size();
+        let (left, right, top, bottom) = self.cropping();
+        let width = width.checked_sub(left + right).and_then(|width| i32::try_from(width).ok());
+        let height = height.checked_sub(top + bottom).and_then(|height| i32::try_from(height).ok());
+        match (width, height) {
+            (Some(width), Some(height)) if width > 0 && height > 0 => Ok((width, height)),
+            _ => Err(AvcParameterSetError::InvalidValue),
+        }
+    }
+
+    // The clean aperture of the cropping window within the coded picture, or None if the picture is not cropped
+    pub fn clean_aperture(&self) -> Option<CleanAperture> {
+        self.frame_cropping?;
+        let (width, height) = self.coded_size();
+        let (left, right, top, bottom) = self.cropping();
+        CleanAperture::from_cropping(width, height, left, right, top, bottom)
+    }
+
+    #[inline]
+    pub fn sample_aspect_ratio(&self) -> Option<(u16, u16)> {
+        self.vui_parameters.and_then(|vui| vui.sample_aspect_ratio).filter(|&(horizontal, vertical)| horizontal != 0 && vertical != 0)
+    }
+
+    // The duration of a frame from the VUI timing information, which counts fields, so a frame lasts two ticks
+    pub fn frame_duration(&self) -> Option<CMTime> {
+        let (num_units_in_tick, time_scale, _) = self.vui_parameters?.timing_info?;
+        if num_units_in_tick == 0 || time_scale == 0 {
+            return None;
+        }
+        let timescale = i32::try_from(time_scale).ok()?;
+        Some(CMTime::make(2 * num_units_in_tick as i64, timescale))
+    }
+}
+
+/// An H.264 picture parameter set, as defined in ITU-T H.264 7.3.2.2
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AvcPictureParameterSet {
+    pub pic_parameter_set_id: u8,
+    pub seq_parameter_set_id: u8,
+    pub entropy_coding_mode_flag: bool,
+    pub bottom_field_pic_order_in_frame_present_flag: bool,
+    pub num_slice_groups: u8,
+    pub num_ref_idx_l0_default_active: u8,
+    pub num_ref_idx_l1_default_active: u8,
+    pub weighted_pred_flag: bool,
+    pub weighted_bipred_idc: u8,
+    pub pic_init_qp: i32,
+    pub pic_init_qs: i32,
+    pub chroma_qp_index_offset: i32,
+    pub deblocking_filter_control_present_flag: bool,
+    pub constrained_intra_pred_flag: bool,
+    pub redundant_pic_cnt_present_flag: bool,
+    pub transform_8x8_mode_flag: bool,
+    pub second_chroma_qp_index_offset: i32,
+}
+
+impl AvcPictureParameterSet {
+    // Parses a picture parameter set NAL unit, starting with its header; the sequence parameter set it refers to is
+    // needed to size its scaling lists
+    pub fn parse(nal_unit: &[u8], sps: &AvcSequenceParameterSet) -> Result<Self, AvcParameterSetError> {
+        let rbsp = nal_unit_payload(nal_unit, AVC_NAL_UNIT_TYPE_PPS)?;
+        let reader = &mut BitReader::new(&rbsp);
+        let mut pps = AvcPictureParameterSet {
+            pic_parameter_set_id: read_ue(reader, 255)? as u8,
+            seq_parameter_set_id: read_ue(reader, 31)? as u8,
+            entropy_coding_mode_flag: read_flag(reader)?,
+            bottom_field_pic_order_in_frame_present_flag: read_flag(reader)?,
+            num_slice_groups: read_ue(reader, 7)? as u8 + 1,
+            ..Default::default()
+        };
+        if pps.num_slice_groups > 1 {
+            match read_ue(reader, 6)? {
+                0 => {
+                    for _ in 0..pps.num_slice_groups {
+                        read_ue(reader, u32::MAX)?;
+                    }
+                }
+                2 => {
+                    for _ in 1..pps.num_slice_groups {
+                        read_ue(reader, u32::MAX)?;
+                        read_ue(reader, u32::MAX)?;
+                    }
+                }
+                3..=5 => {
+                    read_flag(reader)?;
+                    read_ue(reader, u32::MAX)?;
+                }
+                6 => {
+                    let bits = 32 - (pps.num_slice_groups as u32 - 1).leading_zeros();
+                    for _ in 0..=read_ue(reader, u32::MAX - 1)? {
+                        read_bits(reader, bits)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        pps.num_ref_idx_l0_default_active = read_ue(reader, 31)? as u8 + 1;
+        pps.num_ref_idx_l1_default_active = read_ue(reader, 31)? as u8 + 1;
+        pps.weighted_pred_flag = read_flag(reader)?;
+        pps.weighted_bipred_idc = read_bits(reader, 2)? as u8;
+        pps.pic_init_qp = read_se(reader)? + 26;
+        pps.pic_init_qs = read_se(reader)? + 26;
+        pps.chroma_qp_index_offset = read_se(reader)?;
+        pps.deblocking_filter_control_present_flag = read_flag(reader)?;
+        pps.constrained_intra_pred_flag = read_flag(reader)?;
+        pps.redundant_pic_cnt_present_flag = read_flag(reader)?;
+        pps.second_chroma_qp_index_offset = pps.chroma_qp_index_offset;
+        if reader.more_rbsp_data() {
+            pps.transform_8x8_mode_flag = read_flag(reader)?;
+            if read_flag(reader)? {
+                let extra_lists = if !pps.transform_8x8_mode_flag {
+                    0
+                } else if sps.chroma_format_idc == 3 {
+                    6
+                } else {
+                    2
+                };
+                skip_scaling_lists(reader, 6 + extra_lists)?;
+            }
+            pps.second_chroma_qp_index_offset = read_se(reader)?;
+        }
+        Ok(pps)
+    }
+}
+
+impl AvcDecoderConfigurationRecord {
+    // The extensions CoreMedia derives from the first sequence parameter set, together with the record as an avcC
+    // sample description extension atom
+    pub fn to_video_format_extensions(&self) -> Result<VideoFormatExtensions, AvcConfigError> {
+        let sps = self.sequence_parameter_sets.first().ok_or(AvcConfigError::MissingSequenceParameterSet)?;
+        let sps = AvcSequenceParameterSet::parse(sps).map_err(|_| AvcConfigError::InvalidSequenceParameterSet)?;
+        let mut extensions = VideoFormatExtensions {
+            format_name: Some("H.264".to_string()),
+            depth: Some(24),
+            clean_aperture: sps.clean_aperture(),
+            field_count: Some(if sps.frame_mbs_only_flag { 1 } else { 2 }),
+            pixel_aspect_ratio: sps.sample_aspect_ratio().map(|(horizontal, vertical)| PixelAspectRatio::new(horizontal as u32, vertical as u32)),
+            ..Default::default()
+        };
+        extensions.set_atom(AVC_CONFIGURATION_ATOM, &self.to_bytes()?);
+        if let Some(vui) = sps.vui_parameters {
+            extensions.full_range_video = Some(vui.video_full_range_flag);
+            if let Some((colour_primaries, transfer_characteristics, matrix_coefficients)) = vui.colour_description {
+                let color =
+                    ColorDescription::from_code_points(colour_primaries, transfer_characteristics, matrix_coefficients, vui.video_full_range_flag);
+                extensions.set_color_description(&color);
+            }
+            if let Some((top_field, bottom_field)) = vui.chroma_sample_loc_type {
+                extensions.chroma_location_top_field = ChromaLocation::from_chroma_sample_loc_type(top_field);
+                extensions.chroma_location_bottom_field = ChromaLocation::from_chroma_sample_loc_type(bottom_field);
+            }
+        }
+        Ok(extensions)
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl AvcDecoderConfigurationRecord {
+    // The dictionary form of to_video_format_extensions, for CMVideoFormatDescription::new with
+    // kCMVideoCodecType_H264 and the sequence parameter set's dimensions
+    pub fn to_extensions(&self) -> Result<CFDictionary<CFString, CFType>, AvcConfigError> {
+        self.to_video_format_extensions()?.to_dictionary().map_err(|_| AvcConfigError::InvalidSequenceParameterSet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // x264 High profile 1920x1080, coded as 1920x1088 with frame_crop_bottom_offset 4
+    const HIGH_SPS: &[u8] = &[
+        0x67, 0x64, 0x00, 0x28, 0xAC, 0xD9, 0x40, 0x78, 0x02, 0x27, 0xE5, 0xC0, 0x44, 0x00, 0x00, 0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0xF0,
+        0x3C, 0x60, 0xC6, 0x58,
+    ];
+    const HIGH_PPS: &[u8] = &[0x68, 0xEB, 0xE3, 0xCB, 0x22, 0xC0];
+    // The same stream encoded in High 10 profile
+    const HIGH_10_SPS: &[u8] = &[
+        0x67, 0x6E, 0x00, 0x28, 0xA6, 0xCD, 0x94, 0x07, 0x80, 0x22, 0x7E, 0x5C, 0x04, 0x40, 0x00, 0x00, 0x03, 0x00, 0x40, 0x00, 0x00, 0x0F, 0x03,
+        0xC6, 0x0C, 0x65, 0x80,
+    ];
+    // Constrained Baseline 640x480 and 640x360, the latter coded as 640x368 with frame_crop_bottom_offset 4
+    const BASELINE_SPS: &[u8] = &[0x67, 0x42, 0xC0, 0x1F, 0x8C, 0x8D, 0x40, 0x50, 0x1E, 0xD0, 0x0F, 0x08, 0x84, 0x6A];
+    const BASELINE_360_SPS: &[u8] = &[0x67, 0x42, 0xC0, 0x1E, 0x8C, 0x8D, 0x40, 0x50, 0x17, 0xFC, 0xB0, 0x0F, 0x08, 0x84, 0x6A];
+    const BASELINE_PPS: &[u8] = &[0x68, 0xCE, 0x3C, 0x80];
+
+    fn avcc(header: &[u8], sps: &[u8], pps: &[u8], extension: &[u8]) -> Vec<u8> {
+        let mut data = header.to_vec();
+        data.push(0xE1);
+        data.extend_from_slice(&(sps.len() as u16).to_be_bytes());
+        data.extend_from_slice(sps);
+        data.push(1);
+        data.extend_from_slice(&(pps.len() as u16).to_be_bytes());
+        data.extend_from_slice(pps);
+        data.extend_from_slice(extension);
+        data
+    }
+
+    #[test]
+    fn high_profile_record_round_trip() {
+        let data = avcc(&[0x01, 0x64, 0x00, 0x28, 0xFF], HIGH_SPS, HIGH_PPS, &[0xFD, 0xF8, 0xF8, 0x00]);
+        let record = AvcDecoderConfigurationRecord::parse(&data).unwrap();
+        assert_eq!((record.profile_indication, record.profile_compatibility, record.level_indication), (100, 0, 40));
+        assert_eq!(record.nal_unit_header_length, 4);
+        assert_eq!(record.sequence_parameter_sets, vec![HIGH_SPS.to_vec()]);
+        assert_eq!(record.picture_parameter_sets, vec![HIGH_PPS.to_vec()]);
+        let extension =
+            AvcHighProfileExtension { chroma_format: 1, bit_depth_luma: 8, bit_depth_chroma: 8, sequence_parameter_set_extensions: vec![] };
+        assert_eq!(record.high_profile_extension, Some(extension));
+        assert_eq!(record.to_bytes().unwrap(), data);
+
+        let (parameter_sets, nal_unit_header_length) = record.to_parameter_sets();
+        assert_eq!(parameter_sets, vec![HIGH_SPS, HIGH_PPS]);
+        assert_eq!(AvcDecoderConfigurationRecord::from_parameter_sets(&parameter_sets, nal_unit_header_length).unwrap(), record);
+        assert_eq!(AvcDecoderConfigurationRecord::from_parameter_sets(&[HIGH_PPS, HIGH_SPS], 4).unwrap(), record);
+    }
+
+    #[test]
+    fn high_10_profile_record_round_trip() {
+        let data = avcc(&[0x01, 0x6E, 0x00, 0x28, 0xFF], HIGH_10_SPS, HIGH_PPS, &[0xFD, 0xFA, 0xFA, 0x00]);
+        let record = AvcDecoderConfigurationRecord::parse(&data).unwrap();
+        let extension = record.high_profile_extension.as_ref().unwrap();
+        assert_eq!((extension.chroma_format, extension.bit_depth_luma, extension.bit_depth_chroma), (1, 10, 10));
+        assert_eq!(record.to_bytes().unwrap(), data);
+        let (parameter_sets, nal_unit_header_length) = record.to_parameter_sets();
+        assert_eq!(AvcDecoderConfigurationRecord::from_parameter_sets(&parameter_sets, nal_unit_header_length).unwrap(), record);
+
+        // Writers that omit the extension still round-trip
+        let data = avcc(&[0x01, 0x6E, 0x00, 0x28, 0xFF], HIGH_10_SPS, HIGH_PPS, &[]);
+        let record = AvcDecoderConfigurationRecord::parse(&data).unwrap();
+        assert_eq!(record.high_profile_extension, None);
+        assert_eq!(record.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn baseline_profile_record_round_trip() {
+        let data = avcc(&[0x01, 0x42, 0xC0, 0x1F, 0xFF], BASELINE_SPS, BASELINE_PPS, &[]);
+        let record = AvcDecoderConfigurationRecord::parse(&data).unwrap();
+        assert_eq!((record.profile_indication, record.profile_compatibility, record.level_indication), (66, 0xC0, 31));
+        assert_eq!(record.high_profile_extension, None);
+        assert_eq!(record.to_bytes().unwrap(), data);
+        let (parameter_sets, nal_unit_header_length) = record.to_parameter_sets();
+        assert_eq!(parameter_sets, vec![BASELINE_SPS, BASELINE_PPS]);
+        assert_eq!(AvcDecoderConfigurationRecord::from_parameter_sets(&parameter_sets, nal_unit_header_length).unwrap(), record);
+
+        assert_eq!(AvcDecoderConfigurationRecord::from_parameter_sets(&[BASELINE_PPS], 4), Err(AvcConfigError::MissingSequenceParameterSet));
+        assert_eq!(AvcDecoderConfigurationRecord::from_parameter_sets(&[BASELINE_SPS], 3), Err(AvcConfigError::InvalidNalUnitHeaderLength));
+        assert_eq!(AvcDecoderConfigurationRecord::parse(&data[..data.len() - 1]), Err(AvcConfigError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn high_profile_parameter_sets() {
+        let sps = AvcSequenceParameterSet::parse(HIGH_SPS).unwrap();
+        assert_eq!((sps.profile_idc, sps.constraint_flags, sps.level_idc), (100, 0, 40));
+        assert_eq!((sps.chroma_format_idc, sps.bit_depth_luma, sps.bit_depth_chroma), (1, 8, 8));
+        assert_eq!((sps.log2_max_frame_num, sps.pic_order_cnt_type, sps.max_num_ref_frames), (4, 0, 4));
+        assert_eq!((sps.pic_width_in_mbs, sps.pic_height_in_map_units), (120, 68));
+        assert!(sps.frame_mbs_only_flag && sps.direct_8x8_inference_flag);
+        assert_eq!(sps.frame_cropping, Some((0, 0, 0, 4)));
+        assert_eq!(sps.coded_size(), (1920, 1088));
+        assert_eq!(sps.cropping(), (0, 0, 0, 8));
+        assert_eq!(sps.dimensions(), Ok((1920, 1080)));
+        let vui = sps.vui_parameters.unwrap();
+        assert_eq!(vui.sample_aspect_ratio, Some((1, 1)));
+        assert_eq!((vui.video_format, vui.video_full_range_flag, vui.colour_description), (5, false, None));
+        assert_eq!(vui.timing_info, Some((1, 60, false)));
+        assert_eq!(sps.frame_duration(), Some(CMTime::make(2, 60)));
+
+        let pps = AvcPictureParameterSet::parse(HIGH_PPS, &sps).unwrap();
+        assert!(pps.entropy_coding_mode_flag);
+        assert_eq!((pps.num_ref_idx_l0_default_active, pps.num_ref_idx_l1_default_active), (3, 1));
+        assert_eq!((pps.weighted_pred_flag, pps.weighted_bipred_idc), (true, 2));
+        assert_eq!((pps.pic_init_qp, pps.pic_init_qs, pps.chroma_qp_index_offset), (23, 26, -2));
+        assert!(pps.deblocking_filter_control_present_flag);
+        assert!(pps.transform_8x8_mode_flag);
+        assert_eq!(pps.second_chroma_qp_index_offset, -2);
+
+        let sps = AvcSequenceParameterSet::parse(HIGH_10_SPS).unwrap();
+        assert_eq!((sps.profile_idc, sps.bit_depth_luma, sps.bit_depth_chroma), (110, 10, 10));
+        assert_eq!(sps.dimensions(), Ok((1920, 1080)));
+        assert_eq!(sps.vui_parameters.unwrap().timing_info, Some((1, 60, false)));
+    }
+
+    #[test]
+    fn baseline_profile_parameter_sets() {
+        let sps = AvcSequenceParameterSet::parse(BASELINE_SPS).unwrap();
+        assert_eq!((sps.profile_idc, sps.constraint_flags, sps.level_idc), (66, 0xC0, 31));
+        assert_eq!((sps.chroma_format_idc, sps.bit_depth_luma, sps.bit_depth_chroma), (1, 8, 8));
+        assert_eq!((sps.log2_max_frame_num, sps.max_num_ref_frames), (15, 1));
+        assert_eq!(sps.frame_cropping, None);
+        assert_eq!(sps.dimensions(), Ok((640, 480)));
+        assert_eq!(sps.clean_aperture(), None);
+        let vui = sps.vui_parameters.unwrap();
+        assert_eq!((vui.sample_aspect_ratio, vui.timing_info), (None, None));
+
+        let pps = AvcPictureParameterSet::parse(BASELINE_PPS, &sps).unwrap();
+        assert!(!pps.entropy_coding_mode_flag);
+        assert_eq!((pps.pic_init_qp, pps.chroma_qp_index_offset), (26, 0));
+        assert!(!pps.transform_8x8_mode_flag);
+
+        let sps = AvcSequenceParameterSet::parse(BASELINE_360_SPS).unwrap();
+        assert_eq!(sps.frame_cropping, Some((0, 0, 0, 4)));
+        assert_eq!(sps.coded_size(), (640, 368));
+        assert_eq!(sps.dimensions(), Ok((640, 360)));
+        let aperture = sps.clean_aperture().unwrap();
+        assert_eq!((aperture.width, aperture.height, aperture.horizontal_offset, aperture.vertical_offset), (640.0, 360.0, 0.0, -4.0));
+
+        assert_eq!(AvcSequenceParameterSet::parse(BASELINE_PPS), Err(AvcParameterSetError::UnexpectedNalUnitType));
+        assert_eq!(AvcSequenceParameterSet::parse(&BASELINE_SPS[..6]), Err(AvcParameterSetError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn video_format_extensions() {
+        let data = avcc(&[0x01, 0x64, 0x00, 0x28, 0xFF], HIGH_SPS, HIGH_PPS, &[0xFD, 0xF8, 0xF8, 0x00]);
+        let record = AvcDecoderConfigurationRecord::parse(&data).unwrap();
+        let sps = AvcSequenceParameterSet::parse(HIGH_SPS).unwrap();
+        assert_eq!(sps.coded_size(), (1920, 1088));
+        assert_eq!(sps.dimensions(), Ok((1920, 1080)));
+        let extensions = record.to_video_format_extensions().unwrap();
+        assert_eq!(extensions.format_name.as_deref(), Some("H.264"));
+        // frame_crop_bottom_offset 4 removes 8 luma rows from the bottom of the coded picture
+        let aperture = extensions.clean_aperture.unwrap();
+        assert_eq!((aperture.width, aperture.height, aperture.horizontal_offset, aperture.vertical_offset), (1920.0, 1080.0, 0.0, -4.0));
+        assert!(aperture.fits(1920, 1088));
+        assert_eq!(extensions.field_count, Some(1));
+        assert_eq!(extensions.pixel_aspect_ratio, Some(PixelAspectRatio::new(1, 1)));
+        assert_eq!(extensions.full_range_video, Some(false));
+        assert_eq!(extensions.color_primaries, None);
+        assert_eq!(extensions.atom("avcC"), Some(data.as_slice()));
+
+        // An uncropped picture has no clean aperture
+        let data = avcc(&[0x01, 0x42, 0xC0, 0x1F, 0xFF], BASELINE_SPS, BASELINE_PPS, &[]);
+        let extensions = AvcDecoderConfigurationRecord::parse(&data).unwrap().to_video_format_extensions().unwrap();
+        assert_eq!(extensions.clean_aperture, None);
+        assert_eq!(extensions.pixel_aspect_ratio, None);
+    }
+}
@@ -0,0 +1,137 @@
+// Helpers for reading the raw byte sequence payloads of H.264 and HEVC NAL units.
+
+use std::convert::TryFrom;
+
+// Strips the emulation prevention bytes (0x03 following two zero bytes) from a NAL unit payload
+pub(crate) fn remove_emulation_prevention(data: &[u8]) -> Vec<u8> {
+    let mut rbsp = Vec::with_capacity(data.len());
+    let mut zeros = 0;
+    for &byte in data {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        rbsp.push(byte);
+    }
+    rbsp
+}
+
+// Reads bits most significant first; every read returns None once the data is exhausted
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    #[inline]
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        BitReader { data, position: 0 }
+    }
+
+    #[inline]
+    pub(crate) fn read_bit(&mut self) -> Option<bool> {
+        let byte = *self.data.get(self.position / 8)?;
+        let bit = byte >> (7 - self.position % 8) & 1;
+        self.position += 1;
+        Some(bit != 0)
+    }
+
+    // Reads up to 32 bits as an unsigned integer
+    pub(crate) fn read_bits(&mut self, count: u32) -> Option<u32> {
+        debug_assert!(count <= 32);
+        let mut value = 0u64;
+        for _ in 0..count {
+            value = value << 1 | self.read_bit()? as u64;
+        }
+        Some(value as u32)
+    }
+
+    #[inline]
+    pub(crate) fn skip_bits(&mut self, count: usize) -> Option<()> {
+        if self.position + count > self.data.len() * 8 {
+            return None;
+        }
+        self.position += count;
+        Some(())
+    }
+
+    // Reads an unsigned Exp-Golomb code, ue(v)
+    pub(crate) fn read_ue(&mut self) -> Option<u32> {
+        let mut leading_zeros = 0;
+        while !self.read_bit()? {
+            leading_zeros += 1;
+            if leading_zeros > 31 {
+                return None;
+            }
+        }
+        let suffix = self.read_bits(leading_zeros)? as u64;
+        u32::try_from((1u64 << leading_zeros) - 1 + suffix).ok()
+    }
+
+    // Reads a signed Exp-Golomb code, se(v)
+    pub(crate) fn read_se(&mut self) -> Option<i32> {
+        let code = self.read_ue()? as i64;
+        let value = if code % 2 == 1 { (code + 1) / 2 } else { -(code / 2) };
+        i32::try_from(value).ok()
+    }
+
+    // Whether any syntax elements remain before the rbsp_stop_one_bit
+    pub(crate) fn more_rbsp_data(&self) -> bool {
+        let last = match self.data.iter().rposition(|&byte| byte != 0) {
+            Some(last) => last,
+            None => return false,
+        };
+        let stop_bit = last * 8 + 7 - self.data[last].trailing_zeros() as usize;
+        self.position < stop_bit
+    }
+}
+
+// Reads big-endian fields from a byte slice; every read returns None once the data is exhausted
+pub(crate) struct ByteReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    #[inline]
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        ByteReader { data }
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    #[inline]
+    pub(crate) fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    pub(crate) fn read_u8(&mut self) -> Option<u8> {
+        let (&byte, rest) = self.data.split_first()?;
+        self.data = rest;
+        Some(byte)
+    }
+
+    #[inline]
+    pub(crate) fn read_u16(&mut self) -> Option<u16> {
+        self.read_bytes(2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    // Reads an unsigned big-endian integer of up to 8 bytes
+    pub(crate) fn read_uint(&mut self, len: usize) -> Option<u64> {
+        debug_assert!(len <= 8);
+        self.read_bytes(len).map(|bytes| bytes.iter().fold(0, |value, &byte| value << 8 | byte as u64))
+    }
+
+    pub(crate) fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+}
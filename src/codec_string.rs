@@ -0,0 +1,931 @@
+// RFC 6381 codecs parameter values, as used by the HLS CODECS and DASH @codecs attributes, generated from codec
+// configuration records and parsed back for checking stream compatibility.
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use crate::{
+    av1::{Av1CodecConfigurationRecord, AV1_CONFIGURATION_ATOM},
+    avc::{AvcDecoderConfigurationRecord, AVC_CONFIGURATION_ATOM},
+    bitstream::{BitReader, ByteReader},
+    color_description::ColorDescription,
+    format_description::{
+        fourcc, kCMVideoCodecType_AV1, kCMVideoCodecType_DolbyVisionHEVC, kCMVideoCodecType_H264, kCMVideoCodecType_HEVC, kCMVideoCodecType_VP9,
+        CMAudioCodecType, CMVideoCodecType,
+    },
+    hevc::{HevcDecoderConfigurationRecord, HEVC_CONFIGURATION_ATOM},
+    video_format_extensions::VideoFormatExtensions,
+    vp9::{VpCodecConfigurationRecord, VP_CONFIGURATION_ATOM},
+};
+
+// The Dolby Vision configuration boxes, for profiles up to 7, 8 to 10 and above 10
+pub const DOLBY_VISION_CONFIGURATION_ATOMS: [&str; 3] = ["dvcC", "dvvC", "dvwC"];
+
+// The sample description extension atoms a codecs parameter can be derived from
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub(crate) const CODEC_CONFIGURATION_ATOMS: [&str; 7] = [
+    AVC_CONFIGURATION_ATOM,
+    HEVC_CONFIGURATION_ATOM,
+    AV1_CONFIGURATION_ATOM,
+    VP_CONFIGURATION_ATOM,
+    DOLBY_VISION_CONFIGURATION_ATOMS[0],
+    DOLBY_VISION_CONFIGURATION_ATOMS[1],
+    DOLBY_VISION_CONFIGURATION_ATOMS[2],
+];
+
+const AVC1: u32 = fourcc(b"avc1");
+const AVC3: u32 = fourcc(b"avc3");
+const HVC1: u32 = fourcc(b"hvc1");
+const HEV1: u32 = fourcc(b"hev1");
+const DVH1: u32 = fourcc(b"dvh1");
+const DVHE: u32 = fourcc(b"dvhe");
+const DVA1: u32 = fourcc(b"dva1");
+const DVAV: u32 = fourcc(b"dvav");
+const DAV1: u32 = fourcc(b"dav1");
+const AV01: u32 = fourcc(b"av01");
+const VP08: u32 = fourcc(b"vp08");
+const VP09: u32 = fourcc(b"vp09");
+const MP4A: u32 = fourcc(b"mp4a");
+
+// The Core Audio format IDs of the audio codecs with a codecs parameter
+const kAudioFormatMPEG4AAC: CMAudioCodecType = fourcc(b"aac ");
+const kAudioFormatMPEG4AAC_HE: CMAudioCodecType = fourcc(b"aach");
+const kAudioFormatMPEG4AAC_HE_V2: CMAudioCodecType = fourcc(b"aacp");
+const kAudioFormatMPEG4AAC_LD: CMAudioCodecType = fourcc(b"aacl");
+const kAudioFormatMPEG4AAC_ELD: CMAudioCodecType = fourcc(b"aace");
+const kAudioFormatMPEGD_USAC: CMAudioCodecType = fourcc(b"usac");
+const kAudioFormatMPEGLayer3: CMAudioCodecType = fourcc(b".mp3");
+const kAudioFormatAC3: CMAudioCodecType = fourcc(b"ac-3");
+const kAudioFormatEnhancedAC3: CMAudioCodecType = fourcc(b"ec-3");
+const kAudioFormatAppleLossless: CMAudioCodecType = fourcc(b"alac");
+const kAudioFormatFLAC: CMAudioCodecType = fourcc(b"flac");
+const kAudioFormatOpus: CMAudioCodecType = fourcc(b"opus");
+
+// The object type indications of ISO/IEC 14496-1 for MPEG-4 audio and MPEG-1 audio
+const OBJECT_TYPE_MPEG4_AUDIO: u8 = 0x40;
+const OBJECT_TYPE_MPEG1_AUDIO: u8 = 0x6B;
+
+// The MPEG-4 audio object types of the AAC variants and of MPEG-1 Layer 3
+const AUDIO_OBJECT_TYPE_AAC_LC: u8 = 2;
+const AUDIO_OBJECT_TYPE_SBR: u8 = 5;
+const AUDIO_OBJECT_TYPE_ER_AAC_LD: u8 = 23;
+const AUDIO_OBJECT_TYPE_PS: u8 = 29;
+const AUDIO_OBJECT_TYPE_LAYER_3: u8 = 34;
+const AUDIO_OBJECT_TYPE_ER_AAC_ELD: u8 = 39;
+const AUDIO_OBJECT_TYPE_USAC: u8 = 42;
+
+const ES_DESCRIPTOR_TAG: u8 = 0x03;
+const DECODER_CONFIG_DESCRIPTOR_TAG: u8 = 0x04;
+const DECODER_SPECIFIC_INFO_TAG: u8 = 0x05;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CodecStringError {
+    InvalidFormat,
+    ValueOutOfRange,
+    UnsupportedCodec,
+    MissingConfiguration,
+    InvalidConfiguration,
+}
+
+impl Display for CodecStringError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CodecStringError::InvalidFormat => write!(f, "invalid codecs parameter syntax"),
+            CodecStringError::ValueOutOfRange => write!(f, "codecs parameter field is out of range"),
+            CodecStringError::UnsupportedCodec => write!(f, "codec has no codecs parameter"),
+            CodecStringError::MissingConfiguration => write!(f, "no codec configuration to derive the codecs parameter from"),
+            CodecStringError::InvalidConfiguration => write!(f, "codec configuration cannot be parsed"),
+        }
+    }
+}
+
+impl Error for CodecStringError {}
+
+#[inline]
+fn fourcc_string(code: u32) -> String {
+    code.to_be_bytes().iter().map(|&byte| char::from(byte)).collect()
+}
+
+// Splits a codecs parameter into its sample entry type, which must be one of the given ones, and its fields
+fn split_fields<'a>(s: &'a str, sample_entry_types: &[u32]) -> Result<(u32, Vec<&'a str>), CodecStringError> {
+    let mut fields = s.split('.');
+    let sample_entry_type = match fields.next().map(str::as_bytes) {
+        Some(&[a, b, c, d]) => fourcc(&[a, b, c, d]),
+        _ => return Err(CodecStringError::InvalidFormat),
+    };
+    if !sample_entry_types.contains(&sample_entry_type) {
+        return Err(CodecStringError::InvalidFormat);
+    }
+    Ok((sample_entry_type, fields.collect()))
+}
+
+// Parses a decimal field, of exactly len digits unless len is zero
+fn decimal(field: &str, len: usize) -> Result<u8, CodecStringError> {
+    if field.is_empty() || (len != 0 && field.len() != len) || !field.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(CodecStringError::InvalidFormat);
+    }
+    field.parse().map_err(|_| CodecStringError::ValueOutOfRange)
+}
+
+// Parses a hexadecimal field of at most len digits
+fn hexadecimal(field: &str, len: usize) -> Result<u32, CodecStringError> {
+    if field.is_empty() || field.len() > len || !field.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(CodecStringError::InvalidFormat);
+    }
+    u32::from_str_radix(field, 16).map_err(|_| CodecStringError::ValueOutOfRange)
+}
+
+#[inline]
+fn flag(field: &str) -> Result<bool, CodecStringError> {
+    match field {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(CodecStringError::InvalidFormat),
+    }
+}
+
+/// The avc1 and avc3 codecs parameter of ISO/IEC 14496-15, such as avc1.64001F
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AvcCodecString {
+    pub sample_entry_type: u32,
+    pub profile_indication: u8,
+    pub profile_compatibility: u8,
+    pub level_indication: u8,
+}
+
+impl From<&AvcDecoderConfigurationRecord> for AvcCodecString {
+    fn from(record: &AvcDecoderConfigurationRecord) -> Self {
+        AvcCodecString {
+            sample_entry_type: AVC1,
+            profile_indication: record.profile_indication,
+            profile_compatibility: record.profile_compatibility,
+            level_indication: record.level_indication,
+        }
+    }
+}
+
+impl Display for AvcCodecString {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}.{:02X}{:02X}{:02X}",
+            fourcc_string(self.sample_entry_type),
+            self.profile_indication,
+            self.profile_compatibility,
+            self.level_indication
+        )
+    }
+}
+
+impl FromStr for AvcCodecString {
+    type Err = CodecStringError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (sample_entry_type, fields) = split_fields(s, &[AVC1, AVC3])?;
+        let value = match fields.as_slice() {
+            [field] if field.len() == 6 => hexadecimal(field, 6)?,
+            _ => return Err(CodecStringError::InvalidFormat),
+        };
+        let [_, profile_indication, profile_compatibility, level_indication] = value.to_be_bytes();
+        Ok(AvcCodecString { sample_entry_type, profile_indication, profile_compatibility, level_indication })
+    }
+}
+
+/// The hvc1 and hev1 codecs parameter of ISO/IEC 14496-15, such as hvc1.2.4.L153.B0
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HevcCodecString {
+    pub sample_entry_type: u32,
+    pub general_profile_space: u8,
+    pub general_profile_idc: u8,
+    pub general_profile_compatibility_flags: u32,
+    pub general_tier_flag: bool,
+    pub general_level_idc: u8,
+    // The 48 bits of general_progressive_source_flag through general_inbld_flag and the reserved bits
+    pub general_constraint_indicator_flags: u64,
+}
+
+impl From<&HevcDecoderConfigurationRecord> for HevcCodecString {
+    fn from(record: &HevcDecoderConfigurationRecord) -> Self {
+        HevcCodecString {
+            sample_entry_type: HVC1,
+            general_profile_space: record.general_profile_space,
+            general_profile_idc: record.general_profile_idc,
+            general_profile_compatibility_flags: record.general_profile_compatibility_flags,
+            general_tier_flag: record.general_tier_flag,
+            general_level_idc: record.general_level_idc,
+            general_constraint_indicator_flags: record.general_constraint_indicator_flags,
+        }
+    }
+}
+
+impl Display for HevcCodecString {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let profile_space = ["", "A", "B", "C"][(self.general_profile_space & 3) as usize];
+        let tier = if self.general_tier_flag { 'H' } else { 'L' };
+        write!(
+            f,
+            "{}.{}{}.{:X}.{}{}",
+            fourcc_string(self.sample_entry_type),
+            profile_space,
+            self.general_profile_idc,
+            self.general_profile_compatibility_flags.reverse_bits(),
+            tier,
+            self.general_level_idc
+        )?;
+        // Trailing zero bytes of the constraint flags are omitted
+        let constraints = &self.general_constraint_indicator_flags.to_be_bytes()[2..];
+        let len = constraints.iter().rposition(|&byte| byte != 0).map_or(0, |last| last + 1);
+        for byte in &constraints[..len] {
+            write!(f, ".{:X}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for HevcCodecString {
+    type Err = CodecStringError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (sample_entry_type, fields) = split_fields(s, &[HVC1, HEV1])?;
+        if fields.len() < 3 || fields.len() > 9 {
+            return Err(CodecStringError::InvalidFormat);
+        }
+        let (general_profile_space, profile) = match fields[0].as_bytes().first() {
+            Some(&space @ b'A'..=b'C') => (space - b'A' + 1, &fields[0][1..]),
+            _ => (0, fields[0]),
+        };
+        let general_profile_idc = decimal(profile, 0)?;
+        let general_profile_compatibility_flags = hexadecimal(fields[1], 8)?.reverse_bits();
+        let general_tier_flag = match fields[2].as_bytes().first() {
+            Some(b'L') => false,
+            Some(b'H') => true,
+            _ => return Err(CodecStringError::InvalidFormat),
+        };
+        let general_level_idc = decimal(&fields[2][1..], 0)?;
+        let mut general_constraint_indicator_flags = 0;
+        for (index, field) in fields[3..].iter().enumerate() {
+            general_constraint_indicator_flags |= (hexadecimal(field, 2)? as u64) << (40 - 8 * index);
+        }
+        if general_profile_idc > 31 {
+            return Err(CodecStringError::ValueOutOfRange);
+        }
+        Ok(HevcCodecString {
+            sample_entry_type,
+            general_profile_space,
+            general_profile_idc,
+            general_profile_compatibility_flags,
+            general_tier_flag,
+            general_level_idc,
+            general_constraint_indicator_flags,
+        })
+    }
+}
+
+/// The Dolby Vision codecs parameter, such as dvh1.08.06
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DolbyVisionCodecString {
+    pub sample_entry_type: u32,
+    pub profile: u8,
+    pub level: u8,
+}
+
+impl DolbyVisionCodecString {
+    // Reads the profile and level from the body of a dvcC, dvvC or dvwC box
+    pub fn from_configuration_record(sample_entry_type: u32, data: &[u8]) -> Result<Self, CodecStringError> {
+        if data.len() < 4 {
+            return Err(CodecStringError::InvalidConfiguration);
+        }
+        Ok(DolbyVisionCodecString { sample_entry_type, profile: data[2] >> 1, level: (data[2] & 1) << 5 | data[3] >> 3 })
+    }
+}
+
+impl Display for DolbyVisionCodecString {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}.{:02}.{:02}", fourcc_string(self.sample_entry_type), self.profile, self.level)
+    }
+}
+
+impl FromStr for DolbyVisionCodecString {
+    type Err = CodecStringError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (sample_entry_type, fields) = split_fields(s, &[DVH1, DVHE, DVA1, DVAV, DAV1])?;
+        match fields.as_slice() {
+            [profile, level] => Ok(DolbyVisionCodecString { sample_entry_type, profile: decimal(profile, 2)?, level: decimal(level, 2)? }),
+            _ => Err(CodecStringError::InvalidFormat),
+        }
+    }
+}
+
+/// The av01 codecs parameter of the AV1 Codec ISO Media File Format Binding, such as av01.0.08M.10.0.110.09.16.09.0
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Av1CodecString {
+    pub seq_profile: u8,
+    pub seq_level_idx: u8,
+    pub seq_tier: bool,
+    pub bit_depth: u8,
+    pub monochrome: bool,
+    pub chroma_subsampling_x: bool,
+    pub chroma_subsampling_y: bool,
+    pub chroma_sample_position: u8,
+    // None for the short form, which leaves the colour description at its default of BT.709 video range
+    pub color: Option<ColorDescription>,
+}
+
+impl From<&Av1CodecConfigurationRecord> for Av1CodecString {
+    fn from(record: &Av1CodecConfigurationRecord) -> Self {
+        Av1CodecString {
+            seq_profile: record.seq_profile,
+            seq_level_idx: record.seq_level_idx_0,
+            seq_tier: record.seq_tier_0,
+            bit_depth: record.bit_depth(),
+            monochrome: record.monochrome,
+            chroma_subsampling_x: record.chroma_subsampling_x,
+            chroma_subsampling_y: record.chroma_subsampling_y,
+            chroma_sample_position: record.chroma_sample_position,
+            color: None,
+        }
+    }
+}
+
+impl Display for Av1CodecString {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let tier = if self.seq_tier { 'H' } else { 'M' };
+        write!(f, "av01.{}.{:02}{}.{:02}", self.seq_profile, self.seq_level_idx, tier, self.bit_depth)?;
+        let default_chroma = !self.monochrome && self.chroma_subsampling_x && self.chroma_subsampling_y && self.chroma_sample_position == 0;
+        if self.color.is_none() && default_chroma {
+            return Ok(());
+        }
+        let color = self.color.unwrap_or_else(|| ColorDescription::from_code_points(1, 1, 1, false));
+        let (colour_primaries, transfer_characteristics, matrix_coefficients) = color.code_points();
+        write!(
+            f,
+            ".{}.{}{}{}.{:02}.{:02}.{:02}.{}",
+            self.monochrome as u8,
+            self.chroma_subsampling_x as u8,
+            self.chroma_subsampling_y as u8,
+            self.chroma_sample_position,
+            colour_primaries,
+            transfer_characteristics,
+            matrix_coefficients,
+            color.full_range as u8
+        )
+    }
+}
+
+impl FromStr for Av1CodecString {
+    type Err = CodecStringError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (_, fields) = split_fields(s, &[AV01])?;
+        if fields.len() != 3 && fields.len() != 9 {
+            return Err(CodecStringError::InvalidFormat);
+        }
+        let seq_tier = match fields[1].as_bytes().get(2) {
+            Some(b'M') if fields[1].len() == 3 => false,
+            Some(b'H') if fields[1].len() == 3 => true,
+            _ => return Err(CodecStringError::InvalidFormat),
+        };
+        let mut codec_string = Av1CodecString {
+            seq_profile: decimal(fields[0], 1)?,
+            seq_level_idx: decimal(&fields[1][..2], 2)?,
+            seq_tier,
+            bit_depth: decimal(fields[2], 2)?,
+            monochrome: false,
+            chroma_subsampling_x: true,
+            chroma_subsampling_y: true,
+            chroma_sample_position: 0,
+            color: None,
+        };
+        if let [monochrome, chroma, colour_primaries, transfer_characteristics, matrix_coefficients, full_range] = fields[3..] {
+            let [subsampling_x, subsampling_y, sample_position] = match chroma.as_bytes() {
+                &[x, y, position] if chroma.bytes().all(|byte| byte.is_ascii_digit()) => [x - b'0', y - b'0', position - b'0'],
+                _ => return Err(CodecStringError::InvalidFormat),
+            };
+            if subsampling_x > 1 || subsampling_y > 1 {
+                return Err(CodecStringError::InvalidFormat);
+            }
+            codec_string.monochrome = flag(monochrome)?;
+            codec_string.chroma_subsampling_x = subsampling_x == 1;
+            codec_string.chroma_subsampling_y = subsampling_y == 1;
+            codec_string.chroma_sample_position = sample_position;
+            codec_string.color = Some(ColorDescription::from_code_points(
+                decimal(colour_primaries, 2)?,
+                decimal(transfer_characteristics, 2)?,
+                decimal(matrix_coefficients, 2)?,
+                flag(full_range)?,
+            ));
+        }
+        if codec_string.seq_profile > 7 || codec_string.seq_level_idx > 31 || codec_string.chroma_sample_position > 3 {
+            return Err(CodecStringError::ValueOutOfRange);
+        }
+        Ok(codec_string)
+    }
+}
+
+/// The vp08 and vp09 codecs parameter of the VP Codec ISO Media File Format Binding, such as
+/// vp09.02.10.10.01.09.16.09.01
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VpCodecString {
+    pub sample_entry_type: u32,
+    pub profile: u8,
+    pub level: u8,
+    pub bit_depth: u8,
+    pub chroma_subsampling: u8,
+    pub color: ColorDescription,
+}
+
+impl From<&VpCodecConfigurationRecord> for VpCodecString {
+    fn from(record: &VpCodecConfigurationRecord) -> Self {
+        VpCodecString {
+            sample_entry_type: VP09,
+            profile: record.profile,
+            level: record.level,
+            bit_depth: record.bit_depth,
+            chroma_subsampling: record.chroma_subsampling,
+            color: ColorDescription::from_code_points(
+                record.colour_primaries,
+                record.transfer_characteristics,
+                record.matrix_coefficients,
+                record.video_full_range_flag,
+            ),
+        }
+    }
+}
+
+impl Display for VpCodecString {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let (colour_primaries, transfer_characteristics, matrix_coefficients) = self.color.code_points();
+        write!(
+            f,
+            "{}.{:02}.{:02}.{:02}.{:02}.{:02}.{:02}.{:02}.{:02}",
+            fourcc_string(self.sample_entry_type),
+            self.profile,
+            self.level,
+            self.bit_depth,
+            self.chroma_subsampling,
+            colour_primaries,
+            transfer_characteristics,
+            matrix_coefficients,
+            self.color.full_range as u8
+        )
+    }
+}
+
+impl FromStr for VpCodecString {
+    type Err = CodecStringError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (sample_entry_type, fields) = split_fields(s, &[VP08, VP09])?;
+        if fields.len() < 3 || fields.len() > 8 {
+            return Err(CodecStringError::InvalidFormat);
+        }
+        // Trailing optional fields may be omitted, leaving 4:2:0 colocated BT.709 video range
+        let mut values = [0, 0, 0, 1, 1, 1, 1, 0];
+        for (value, field) in values.iter_mut().zip(&fields) {
+            *value = decimal(field, 2)?;
+        }
+        let [profile, level, bit_depth, chroma_subsampling, colour_primaries, transfer_characteristics, matrix_coefficients, full_range] = values;
+        if full_range > 1 {
+            return Err(CodecStringError::ValueOutOfRange);
+        }
+        Ok(VpCodecString {
+            sample_entry_type,
+            profile,
+            level,
+            bit_depth,
+            chroma_subsampling,
+            color: ColorDescription::from_code_points(colour_primaries, transfer_characteristics, matrix_coefficients, full_range == 1),
+        })
+    }
+}
+
+/// The mp4a codecs parameter of RFC 6381, such as mp4a.40.2
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Mpeg4AudioCodecString {
+    pub object_type_indication: u8,
+    // The audio object type, present when the object type indication is MPEG-4 audio (0x40)
+    pub audio_object_type: Option<u8>,
+}
+
+impl Mpeg4AudioCodecString {
+    // Reads the audio object type from an AudioSpecificConfig of ISO/IEC 14496-3
+    pub fn from_audio_specific_config(data: &[u8]) -> Result<Self, CodecStringError> {
+        let mut reader = BitReader::new(data);
+        let audio_object_type = match reader.read_bits(5).ok_or(CodecStringError::InvalidConfiguration)? {
+            0 => return Err(CodecStringError::InvalidConfiguration),
+            31 => 32 + reader.read_bits(6).ok_or(CodecStringError::InvalidConfiguration)?,
+            audio_object_type => audio_object_type,
+        };
+        Ok(Mpeg4AudioCodecString { object_type_indication: OBJECT_TYPE_MPEG4_AUDIO, audio_object_type: Some(audio_object_type as u8) })
+    }
+
+    // Reads an AAC magic cookie, which is either the ES_Descriptor of an esds box, with or without the box's version
+    // and flags, or a bare AudioSpecificConfig
+    pub fn from_magic_cookie(data: &[u8]) -> Result<Self, CodecStringError> {
+        let data = match data {
+            [0, 0, 0, 0, ES_DESCRIPTOR_TAG, ..] => &data[4..],
+            _ => data,
+        };
+        if data.first() != Some(&ES_DESCRIPTOR_TAG) {
+            return Self::from_audio_specific_config(data);
+        }
+        let (object_type_indication, decoder_specific_info) = read_es_descriptor(data).ok_or(CodecStringError::InvalidConfiguration)?;
+        if object_type_indication != OBJECT_TYPE_MPEG4_AUDIO {
+            return Ok(Mpeg4AudioCodecString { object_type_indication, audio_object_type: None });
+        }
+        Self::from_audio_specific_config(decoder_specific_info.ok_or(CodecStringError::InvalidConfiguration)?)
+    }
+
+    // The codecs parameter of a Core Audio format ID, for when there is no magic cookie to read the audio object type
+    // from
+    fn from_audio_format(format_id: CMAudioCodecType) -> Option<Self> {
+        let audio_object_type = match format_id {
+            kAudioFormatMPEG4AAC => AUDIO_OBJECT_TYPE_AAC_LC,
+            kAudioFormatMPEG4AAC_HE => AUDIO_OBJECT_TYPE_SBR,
+            kAudioFormatMPEG4AAC_HE_V2 => AUDIO_OBJECT_TYPE_PS,
+            kAudioFormatMPEG4AAC_LD => AUDIO_OBJECT_TYPE_ER_AAC_LD,
+            kAudioFormatMPEG4AAC_ELD => AUDIO_OBJECT_TYPE_ER_AAC_ELD,
+            kAudioFormatMPEGD_USAC => AUDIO_OBJECT_TYPE_USAC,
+            kAudioFormatMPEGLayer3 => AUDIO_OBJECT_TYPE_LAYER_3,
+            _ => return None,
+        };
+        Some(Mpeg4AudioCodecString { object_type_indication: OBJECT_TYPE_MPEG4_AUDIO, audio_object_type: Some(audio_object_type) })
+    }
+
+    // The Core Audio format ID of the audio object type
+    fn audio_format(&self) -> Option<CMAudioCodecType> {
+        if self.object_type_indication == OBJECT_TYPE_MPEG1_AUDIO {
+            return Some(kAudioFormatMPEGLayer3);
+        }
+        if self.object_type_indication != OBJECT_TYPE_MPEG4_AUDIO {
+            return None;
+        }
+        match self.audio_object_type? {
+            AUDIO_OBJECT_TYPE_AAC_LC => Some(kAudioFormatMPEG4AAC),
+            AUDIO_OBJECT_TYPE_SBR => Some(kAudioFormatMPEG4AAC_HE),
+            AUDIO_OBJECT_TYPE_PS => Some(kAudioFormatMPEG4AAC_HE_V2),
+            AUDIO_OBJECT_TYPE_ER_AAC_LD => Some(kAudioFormatMPEG4AAC_LD),
+            AUDIO_OBJECT_TYPE_ER_AAC_ELD => Some(kAudioFormatMPEG4AAC_ELD),
+            AUDIO_OBJECT_TYPE_USAC => Some(kAudioFormatMPEGD_USAC),
+            AUDIO_OBJECT_TYPE_LAYER_3 => Some(kAudioFormatMPEGLayer3),
+            _ => None,
+        }
+    }
+}
+
+// Reads a descriptor of ISO/IEC 14496-1, returning its tag and body
+fn read_descriptor<'a>(reader: &mut ByteReader<'a>) -> Option<(u8, &'a [u8])> {
+    let tag = reader.read_u8()?;
+    let mut size = 0;
+    for _ in 0..4 {
+        let byte = reader.read_u8()?;
+        size = size << 7 | (byte & 0x7F) as usize;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    Some((tag, reader.read_bytes(size)?))
+}
+
+// Reads the object type indication and decoder specific info of an ES_Descriptor
+fn read_es_descriptor(data: &[u8]) -> Option<(u8, Option<&[u8]>)> {
+    let (tag, body) = read_descriptor(&mut ByteReader::new(data))?;
+    if tag != ES_DESCRIPTOR_TAG {
+        return None;
+    }
+    let mut reader = ByteReader::new(body);
+    reader.read_u16()?;
+    let flags = reader.read_u8()?;
+    if flags & 0x80 != 0 {
+        reader.read_u16()?;
+    }
+    if flags & 0x40 != 0 {
+        let len = reader.read_u8()?;
+        reader.read_bytes(len as usize)?;
+    }
+    if flags & 0x20 != 0 {
+        reader.read_u16()?;
+    }
+    let (tag, body) = read_descriptor(&mut reader)?;
+    if tag != DECODER_CONFIG_DESCRIPTOR_TAG {
+        return None;
+    }
+    let mut reader = ByteReader::new(body);
+    let object_type_indication = reader.read_u8()?;
+    // streamType, bufferSizeDB, maxBitrate and avgBitrate
+    reader.read_bytes(12)?;
+    let decoder_specific_info = match read_descriptor(&mut reader) {
+        Some((DECODER_SPECIFIC_INFO_TAG, info)) => Some(info),
+        _ => None,
+    };
+    Some((object_type_indication, decoder_specific_info))
+}
+
+impl Display for Mpeg4AudioCodecString {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "mp4a.{:02X}", self.object_type_indication)?;
+        if let Some(audio_object_type) = self.audio_object_type {
+            write!(f, ".{}", audio_object_type)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Mpeg4AudioCodecString {
+    type Err = CodecStringError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (_, fields) = split_fields(s, &[MP4A])?;
+        let (object_type_indication, audio_object_type) = match fields.as_slice() {
+            [object_type_indication] => (object_type_indication, None),
+            [object_type_indication, audio_object_type] => (object_type_indication, Some(decimal(audio_object_type, 0)?)),
+            _ => return Err(CodecStringError::InvalidFormat),
+        };
+        Ok(Mpeg4AudioCodecString { object_type_indication: hexadecimal(object_type_indication, 2)? as u8, audio_object_type })
+    }
+}
+
+/// An RFC 6381 codecs parameter, typed for the codecs whose parameters are understood
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CodecString {
+    Avc(AvcCodecString),
+    Hevc(HevcCodecString),
+    DolbyVision(DolbyVisionCodecString),
+    Av1(Av1CodecString),
+    Vp(VpCodecString),
+    Mpeg4Audio(Mpeg4AudioCodecString),
+    // A codec identified by its sample entry type alone, such as ac-3, ec-3, Opus or fLaC, or a codecs parameter
+    // whose fields are not interpreted
+    Other(String),
+}
+
+impl CodecString {
+    // The codecs parameter of a video format from its codec type and the configuration record among its sample
+    // description extension atoms; the colour extensions complete the AV1 codecs parameter
+    pub fn from_video_format_extensions(codec_type: CMVideoCodecType, extensions: &VideoFormatExtensions) -> Result<Self, CodecStringError> {
+        let atom = |name| extensions.atom(name).ok_or(CodecStringError::MissingConfiguration);
+        match codec_type {
+            kCMVideoCodecType_H264 => {
+                let record =
+                    AvcDecoderConfigurationRecord::parse(atom(AVC_CONFIGURATION_ATOM)?).map_err(|_| CodecStringError::InvalidConfiguration)?;
+                Ok(CodecString::Avc(AvcCodecString::from(&record)))
+            }
+            kCMVideoCodecType_HEVC => {
+                let record =
+                    HevcDecoderConfigurationRecord::parse(atom(HEVC_CONFIGURATION_ATOM)?).map_err(|_| CodecStringError::InvalidConfiguration)?;
+                Ok(CodecString::Hevc(HevcCodecString::from(&record)))
+            }
+            kCMVideoCodecType_DolbyVisionHEVC => {
+                let data = DOLBY_VISION_CONFIGURATION_ATOMS.iter().find_map(|&name| extensions.atom(name));
+                let data = data.ok_or(CodecStringError::MissingConfiguration)?;
+                Ok(CodecString::DolbyVision(DolbyVisionCodecString::from_configuration_record(DVH1, data)?))
+            }
+            kCMVideoCodecType_AV1 => {
+                let record = Av1CodecConfigurationRecord::parse(atom(AV1_CONFIGURATION_ATOM)?).map_err(|_| CodecStringError::InvalidConfiguration)?;
+                let mut codec_string = Av1CodecString::from(&record);
+                if extensions.color_primaries.is_some() || extensions.transfer_function.is_some() || extensions.ycbcr_matrix.is_some() {
+                    codec_string.color = Some(extensions.color_description());
+                }
+                Ok(CodecString::Av1(codec_string))
+            }
+            kCMVideoCodecType_VP9 => {
+                let record = VpCodecConfigurationRecord::parse(atom(VP_CONFIGURATION_ATOM)?).map_err(|_| CodecStringError::InvalidConfiguration)?;
+                Ok(CodecString::Vp(VpCodecString::from(&record)))
+            }
+            _ => Self::from_sample_entry_type(codec_type),
+        }
+    }
+
+    // The codecs parameter of an audio format from its Core Audio format ID and magic cookie
+    pub fn from_audio_format(format_id: CMAudioCodecType, magic_cookie: Option<&[u8]>) -> Result<Self, CodecStringError> {
+        match format_id {
+            kAudioFormatAC3 | kAudioFormatEnhancedAC3 | kAudioFormatAppleLossless => Self::from_sample_entry_type(format_id),
+            kAudioFormatFLAC => Ok(CodecString::Other("fLaC".to_string())),
+            kAudioFormatOpus => Ok(CodecString::Other("Opus".to_string())),
+            _ => {
+                let mut codec_string = Mpeg4AudioCodecString::from_audio_format(format_id).ok_or(CodecStringError::UnsupportedCodec)?;
+                // The magic cookie of AAC signals the audio object type precisely, such as HE-AAC in kAudioFormatMPEG4AAC
+                if let Some(cookie) = magic_cookie.filter(|cookie| !cookie.is_empty() && format_id != kAudioFormatMPEGLayer3) {
+                    codec_string = Mpeg4AudioCodecString::from_magic_cookie(cookie)?;
+                }
+                Ok(CodecString::Mpeg4Audio(codec_string))
+            }
+        }
+    }
+
+    fn from_sample_entry_type(sample_entry_type: u32) -> Result<Self, CodecStringError> {
+        if !sample_entry_type.to_be_bytes().iter().all(|byte| byte.is_ascii_graphic() || *byte == b' ') {
+            return Err(CodecStringError::UnsupportedCodec);
+        }
+        Ok(CodecString::Other(fourcc_string(sample_entry_type)))
+    }
+
+    // The CoreMedia codec type of the format descriptions the codecs parameter describes, for checking whether a
+    // stream can be decoded before fetching it
+    pub fn codec_type(&self) -> Option<u32> {
+        match self {
+            CodecString::Avc(_) => Some(kCMVideoCodecType_H264),
+            CodecString::Hevc(_) => Some(kCMVideoCodecType_HEVC),
+            CodecString::DolbyVision(codec_string) => match codec_string.sample_entry_type {
+                DVH1 | DVHE => Some(kCMVideoCodecType_DolbyVisionHEVC),
+                _ => None,
+            },
+            CodecString::Av1(_) => Some(kCMVideoCodecType_AV1),
+            CodecString::Vp(codec_string) if codec_string.sample_entry_type == VP09 => Some(kCMVideoCodecType_VP9),
+            CodecString::Vp(_) => None,
+            CodecString::Mpeg4Audio(codec_string) => codec_string.audio_format(),
+            CodecString::Other(codec_string) => match codec_string.split('.').next()?.as_bytes() {
+                b"fLaC" => Some(kAudioFormatFLAC),
+                b"Opus" => Some(kAudioFormatOpus),
+                b"ac-3" => Some(kAudioFormatAC3),
+                b"ec-3" => Some(kAudioFormatEnhancedAC3),
+                b"alac" => Some(kAudioFormatAppleLossless),
+                // Other sample entries, such as the stpp and wvtt subtitle formats, have no audio or video codec type
+                _ => None,
+            },
+        }
+    }
+}
+
+// Parses the comma separated list of codecs parameters of an HLS CODECS or DASH @codecs attribute
+pub fn parse_codecs(value: &str) -> Result<Vec<CodecString>, CodecStringError> {
+    value.split(',').map(|codec_string| codec_string.trim().parse()).collect()
+}
+
+impl Display for CodecString {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CodecString::Avc(codec_string) => codec_string.fmt(f),
+            CodecString::Hevc(codec_string) => codec_string.fmt(f),
+            CodecString::DolbyVision(codec_string) => codec_string.fmt(f),
+            CodecString::Av1(codec_string) => codec_string.fmt(f),
+            CodecString::Vp(codec_string) => codec_string.fmt(f),
+            CodecString::Mpeg4Audio(codec_string) => codec_string.fmt(f),
+            CodecString::Other(codec_string) => f.write_str(codec_string),
+        }
+    }
+}
+
+impl FromStr for CodecString {
+    type Err = CodecStringError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let sample_entry_type = s.split('.').next().unwrap_or_default();
+        match sample_entry_type {
+            "avc1" | "avc3" => s.parse().map(CodecString::Avc),
+            "hvc1" | "hev1" => s.parse().map(CodecString::Hevc),
+            "dvh1" | "dvhe" | "dva1" | "dvav" | "dav1" => s.parse().map(CodecString::DolbyVision),
+            "av01" => s.parse().map(CodecString::Av1),
+            "vp08" | "vp09" => s.parse().map(CodecString::Vp),
+            "mp4a" => s.parse().map(CodecString::Mpeg4Audio),
+            _ if sample_entry_type.is_empty() || !s.bytes().all(|byte| byte.is_ascii_graphic()) => Err(CodecStringError::InvalidFormat),
+            _ => Ok(CodecString::Other(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(s: &str) -> CodecString {
+        let codec_string: CodecString = s.parse().unwrap();
+        assert_eq!(codec_string.to_string(), s);
+        codec_string
+    }
+
+    #[test]
+    fn avc() {
+        let expected = AvcCodecString { sample_entry_type: AVC1, profile_indication: 0x64, profile_compatibility: 0, level_indication: 0x1F };
+        let codec_string = round_trip("avc1.64001F");
+        assert_eq!(codec_string, CodecString::Avc(expected));
+        assert_eq!(codec_string.codec_type(), Some(kCMVideoCodecType_H264));
+        assert_eq!("avc3.64001f".parse::<AvcCodecString>().map(|codec_string| codec_string.sample_entry_type), Ok(AVC3));
+        assert_eq!("avc1.64001".parse::<CodecString>(), Err(CodecStringError::InvalidFormat));
+        assert_eq!("avc1.64001G".parse::<CodecString>(), Err(CodecStringError::InvalidFormat));
+    }
+
+    #[test]
+    fn hevc() {
+        let expected = HevcCodecString {
+            sample_entry_type: HVC1,
+            general_profile_space: 0,
+            general_profile_idc: 2,
+            general_profile_compatibility_flags: 0x2000_0000,
+            general_tier_flag: false,
+            general_level_idc: 153,
+            general_constraint_indicator_flags: 0xB0 << 40,
+        };
+        let codec_string = round_trip("hvc1.2.4.L153.B0");
+        assert_eq!(codec_string, CodecString::Hevc(expected));
+        assert_eq!(codec_string.codec_type(), Some(kCMVideoCodecType_HEVC));
+        let codec_string = round_trip("hev1.A1.6.H120.90.0.0.0.0.1");
+        match codec_string {
+            CodecString::Hevc(codec_string) => {
+                assert_eq!((codec_string.general_profile_space, codec_string.general_tier_flag), (1, true));
+                assert_eq!(codec_string.general_constraint_indicator_flags, 0x9000_0000_0001);
+            }
+            _ => panic!("{:?}", codec_string),
+        }
+        assert_eq!("hvc1.2.4".parse::<CodecString>(), Err(CodecStringError::InvalidFormat));
+        assert_eq!("hvc1.2.4.M153".parse::<CodecString>(), Err(CodecStringError::InvalidFormat));
+        assert_eq!("hvc1.32.4.L153".parse::<CodecString>(), Err(CodecStringError::ValueOutOfRange));
+    }
+
+    #[test]
+    fn av1() {
+        let expected = Av1CodecString {
+            seq_profile: 0,
+            seq_level_idx: 8,
+            seq_tier: false,
+            bit_depth: 10,
+            monochrome: false,
+            chroma_subsampling_x: true,
+            chroma_subsampling_y: true,
+            chroma_sample_position: 0,
+            color: Some(ColorDescription::from_code_points(9, 16, 9, false)),
+        };
+        let codec_string = round_trip("av01.0.08M.10.0.110.09.16.09.0");
+        assert_eq!(codec_string, CodecString::Av1(expected));
+        assert_eq!(codec_string.codec_type(), Some(kCMVideoCodecType_AV1));
+        assert_eq!(round_trip("av01.0.04M.08"), CodecString::Av1(Av1CodecString { seq_level_idx: 4, bit_depth: 8, color: None, ..expected }));
+        assert_eq!("av01.0.08X.10".parse::<CodecString>(), Err(CodecStringError::InvalidFormat));
+        assert_eq!("av01.0.08M.10.0.210.09.16.09.0".parse::<CodecString>(), Err(CodecStringError::InvalidFormat));
+        assert_eq!("av01.8.08M.10".parse::<CodecString>(), Err(CodecStringError::ValueOutOfRange));
+    }
+
+    #[test]
+    fn vp() {
+        let expected = VpCodecString {
+            sample_entry_type: VP09,
+            profile: 2,
+            level: 10,
+            bit_depth: 10,
+            chroma_subsampling: 1,
+            color: ColorDescription::from_code_points(9, 16, 9, true),
+        };
+        let codec_string = round_trip("vp09.02.10.10.01.09.16.09.01");
+        assert_eq!(codec_string, CodecString::Vp(expected));
+        assert_eq!(codec_string.codec_type(), Some(kCMVideoCodecType_VP9));
+        // Omitted trailing fields take their defaults, which are always written out
+        let short: CodecString = "vp09.00.10.08".parse().unwrap();
+        assert_eq!(short.to_string(), "vp09.00.10.08.01.01.01.01.00");
+        assert_eq!("vp08.00.10.08".parse::<CodecString>().map(|codec_string| codec_string.codec_type()), Ok(None));
+        assert_eq!("vp09.02.10.10.01.09.16.09.02".parse::<CodecString>(), Err(CodecStringError::ValueOutOfRange));
+        assert_eq!("vp09.2.10.10".parse::<CodecString>(), Err(CodecStringError::InvalidFormat));
+    }
+
+    #[test]
+    fn audio() {
+        let codec_string = round_trip("mp4a.40.2");
+        assert_eq!(codec_string, CodecString::Mpeg4Audio(Mpeg4AudioCodecString { object_type_indication: 0x40, audio_object_type: Some(2) }));
+        assert_eq!(codec_string.codec_type(), Some(kAudioFormatMPEG4AAC));
+        assert_eq!(round_trip("mp4a.6B").codec_type(), Some(kAudioFormatMPEGLayer3));
+        assert_eq!(round_trip("mp4a.40.5").codec_type(), Some(kAudioFormatMPEG4AAC_HE));
+        let codec_string = round_trip("ec-3");
+        assert_eq!(codec_string, CodecString::Other("ec-3".to_string()));
+        assert_eq!(codec_string.codec_type(), Some(kAudioFormatEnhancedAC3));
+        assert_eq!(round_trip("ac-3").codec_type(), Some(kAudioFormatAC3));
+        assert_eq!(round_trip("fLaC").codec_type(), Some(kAudioFormatFLAC));
+        assert_eq!(round_trip("Opus").codec_type(), Some(kAudioFormatOpus));
+
+        assert_eq!(CodecString::from_audio_format(kAudioFormatEnhancedAC3, None), Ok(codec_string));
+        assert_eq!(
+            CodecString::from_audio_format(kAudioFormatMPEG4AAC, None).map(|codec_string| codec_string.to_string()),
+            Ok("mp4a.40.2".to_string())
+        );
+        // An AudioSpecificConfig signalling SBR overrides the format ID
+        let he_aac = CodecString::from_audio_format(kAudioFormatMPEG4AAC, Some(&[0x2B, 0x92, 0x08, 0x00]));
+        assert_eq!(he_aac.map(|codec_string| codec_string.to_string()), Ok("mp4a.40.5".to_string()));
+        assert_eq!(CodecString::from_audio_format(fourcc(b"lpcm"), None), Err(CodecStringError::UnsupportedCodec));
+    }
+
+    #[test]
+    fn dolby_vision() {
+        let codec_string = round_trip("dvh1.08.06");
+        assert_eq!(codec_string, CodecString::DolbyVision(DolbyVisionCodecString { sample_entry_type: DVH1, profile: 8, level: 6 }));
+        assert_eq!(codec_string.codec_type(), Some(kCMVideoCodecType_DolbyVisionHEVC));
+        assert_eq!(round_trip("dav1.10.09").codec_type(), None);
+        // The version, profile and level bytes of a dvvC box for profile 8 at level 6
+        let record = DolbyVisionCodecString::from_configuration_record(DVH1, &[1, 0, 0x10, 0x35]);
+        assert_eq!(record.map(|codec_string| codec_string.to_string()), Ok("dvh1.08.06".to_string()));
+        assert_eq!("dvh1.8.06".parse::<CodecString>(), Err(CodecStringError::InvalidFormat));
+    }
+
+    #[test]
+    fn other() {
+        // Subtitle and unknown sample entries parse, but have no audio or video codec type
+        for s in ["stpp.ttml.im1t", "wvtt", "mp4v.20.9", "abcd"].iter() {
+            assert_eq!(round_trip(s).codec_type(), None, "{}", s);
+        }
+        assert_eq!("".parse::<CodecString>(), Err(CodecStringError::InvalidFormat));
+        assert_eq!("av c1".parse::<CodecString>(), Err(CodecStringError::InvalidFormat));
+        let codecs = parse_codecs("avc1.64001F, mp4a.40.2,ec-3").unwrap();
+        assert_eq!(codecs.iter().map(ToString::to_string).collect::<Vec<_>>(), ["avc1.64001F", "mp4a.40.2", "ec-3"]);
+        assert_eq!(parse_codecs("avc1.64001F,,mp4a.40.2"), Err(CodecStringError::InvalidFormat));
+    }
+}
@@ -0,0 +1,334 @@
+// The colour description of a video format, as ITU-T H.273 (ISO/IEC 23091-2) code points and as the values of the
+// kCMFormatDescriptionExtension_ColorPrimaries, TransferFunction and YCbCrMatrix extensions.
+
+/// ColourPrimaries code points of ITU-T H.273
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColorPrimaries {
+    Bt709,
+    #[default]
+    Unspecified,
+    Bt470M,
+    // BT.470 System B, G and BT.601 625-line, named EBU_3213 by CoreMedia
+    Ebu3213,
+    // SMPTE 170M and BT.601 525-line
+    SmpteC,
+    Smpte240M,
+    GenericFilm,
+    Bt2020,
+    Smpte428,
+    DciP3,
+    P3D65,
+    // EBU Tech 3213-E, named P22 by CoreMedia
+    P22,
+    // A code point without a variant; never holds one of the values above
+    Other(u8),
+}
+
+impl ColorPrimaries {
+    // The value of the kCMFormatDescriptionExtension_ColorPrimaries extension, if CoreMedia has one
+    pub fn core_media_name(self) -> Option<&'static str> {
+        match self {
+            ColorPrimaries::Bt709 => Some("ITU_R_709_2"),
+            ColorPrimaries::Ebu3213 => Some("EBU_3213"),
+            ColorPrimaries::SmpteC => Some("SMPTE_C"),
+            ColorPrimaries::Bt2020 => Some("ITU_R_2020"),
+            ColorPrimaries::DciP3 => Some("DCI_P3"),
+            ColorPrimaries::P3D65 => Some("P3_D65"),
+            ColorPrimaries::P22 => Some("P22"),
+            _ => None,
+        }
+    }
+
+    pub fn from_core_media_name(name: &str) -> Option<Self> {
+        match name {
+            "ITU_R_709_2" => Some(ColorPrimaries::Bt709),
+            "EBU_3213" => Some(ColorPrimaries::Ebu3213),
+            "SMPTE_C" => Some(ColorPrimaries::SmpteC),
+            "ITU_R_2020" => Some(ColorPrimaries::Bt2020),
+            "DCI_P3" => Some(ColorPrimaries::DciP3),
+            "P3_D65" => Some(ColorPrimaries::P3D65),
+            "P22" => Some(ColorPrimaries::P22),
+            _ => None,
+        }
+    }
+}
+
+impl From<u8> for ColorPrimaries {
+    fn from(code_point: u8) -> Self {
+        match code_point {
+            1 => ColorPrimaries::Bt709,
+            2 => ColorPrimaries::Unspecified,
+            4 => ColorPrimaries::Bt470M,
+            5 => ColorPrimaries::Ebu3213,
+            6 => ColorPrimaries::SmpteC,
+            7 => ColorPrimaries::Smpte240M,
+            8 => ColorPrimaries::GenericFilm,
+            9 => ColorPrimaries::Bt2020,
+            10 => ColorPrimaries::Smpte428,
+            11 => ColorPrimaries::DciP3,
+            12 => ColorPrimaries::P3D65,
+            22 => ColorPrimaries::P22,
+            _ => ColorPrimaries::Other(code_point),
+        }
+    }
+}
+
+impl From<ColorPrimaries> for u8 {
+    fn from(primaries: ColorPrimaries) -> Self {
+        match primaries {
+            ColorPrimaries::Bt709 => 1,
+            ColorPrimaries::Unspecified => 2,
+            ColorPrimaries::Bt470M => 4,
+            ColorPrimaries::Ebu3213 => 5,
+            ColorPrimaries::SmpteC => 6,
+            ColorPrimaries::Smpte240M => 7,
+            ColorPrimaries::GenericFilm => 8,
+            ColorPrimaries::Bt2020 => 9,
+            ColorPrimaries::Smpte428 => 10,
+            ColorPrimaries::DciP3 => 11,
+            ColorPrimaries::P3D65 => 12,
+            ColorPrimaries::P22 => 22,
+            ColorPrimaries::Other(code_point) => code_point,
+        }
+    }
+}
+
+/// TransferCharacteristics code points of ITU-T H.273
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TransferFunction {
+    Bt709,
+    #[default]
+    Unspecified,
+    // Gamma 2.2
+    Bt470M,
+    // Gamma 2.8
+    Bt470BG,
+    // SMPTE 170M and BT.601, the same curve as BT.709
+    Bt601,
+    Smpte240M,
+    Linear,
+    Log100,
+    Log316,
+    Iec61966_2_4,
+    Bt1361,
+    Srgb,
+    Bt2020TenBit,
+    Bt2020TwelveBit,
+    SmpteSt2084Pq,
+    SmpteSt428,
+    Bt2100Hlg,
+    // A code point without a variant; never holds one of the values above
+    Other(u8),
+}
+
+impl TransferFunction {
+    // The value of the kCMFormatDescriptionExtension_TransferFunction extension, if CoreMedia has one; the gamma
+    // curves are UseGamma together with the gamma_level
+    pub fn core_media_name(self) -> Option<&'static str> {
+        match self {
+            TransferFunction::Bt709 | TransferFunction::Bt601 => Some("ITU_R_709_2"),
+            TransferFunction::Bt470M | TransferFunction::Bt470BG => Some("UseGamma"),
+            TransferFunction::Smpte240M => Some("SMPTE_240M_1995"),
+            TransferFunction::Linear => Some("Linear"),
+            TransferFunction::Srgb => Some("IEC_sRGB"),
+            TransferFunction::Bt2020TenBit | TransferFunction::Bt2020TwelveBit => Some("ITU_R_2020"),
+            TransferFunction::SmpteSt2084Pq => Some("SMPTE_ST_2084_PQ"),
+            TransferFunction::SmpteSt428 => Some("SMPTE_ST_428_1"),
+            TransferFunction::Bt2100Hlg => Some("ITU_R_2100_HLG"),
+            _ => None,
+        }
+    }
+
+    // The transfer function named by a kCMFormatDescriptionExtension_TransferFunction value; a name shared by several
+    // code points maps to the first of them, and UseGamma needs from_gamma_level instead
+    pub fn from_core_media_name(name: &str) -> Option<Self> {
+        match name {
+            "ITU_R_709_2" => Some(TransferFunction::Bt709),
+            "SMPTE_240M_1995" => Some(TransferFunction::Smpte240M),
+            "Linear" => Some(TransferFunction::Linear),
+            "IEC_sRGB" => Some(TransferFunction::Srgb),
+            "ITU_R_2020" => Some(TransferFunction::Bt2020TenBit),
+            "SMPTE_ST_2084_PQ" => Some(TransferFunction::SmpteSt2084Pq),
+            "SMPTE_ST_428_1" => Some(TransferFunction::SmpteSt428),
+            "ITU_R_2100_HLG" => Some(TransferFunction::Bt2100Hlg),
+            _ => None,
+        }
+    }
+
+    // The value of the kCMFormatDescriptionExtension_GammaLevel extension that goes with UseGamma
+    #[inline]
+    pub fn gamma_level(self) -> Option<f64> {
+        match self {
+            TransferFunction::Bt470M => Some(2.2),
+            TransferFunction::Bt470BG => Some(2.8),
+            _ => None,
+        }
+    }
+
+    pub fn from_gamma_level(gamma_level: f64) -> Option<Self> {
+        if (gamma_level - 2.2).abs() < 0.01 {
+            Some(TransferFunction::Bt470M)
+        } else if (gamma_level - 2.8).abs() < 0.01 {
+            Some(TransferFunction::Bt470BG)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<u8> for TransferFunction {
+    fn from(code_point: u8) -> Self {
+        match code_point {
+            1 => TransferFunction::Bt709,
+            2 => TransferFunction::Unspecified,
+            4 => TransferFunction::Bt470M,
+            5 => TransferFunction::Bt470BG,
+            6 => TransferFunction::Bt601,
+            7 => TransferFunction::Smpte240M,
+            8 => TransferFunction::Linear,
+            9 => TransferFunction::Log100,
+            10 => TransferFunction::Log316,
+            11 => TransferFunction::Iec61966_2_4,
+            12 => TransferFunction::Bt1361,
+            13 => TransferFunction::Srgb,
+            14 => TransferFunction::Bt2020TenBit,
+            15 => TransferFunction::Bt2020TwelveBit,
+            16 => TransferFunction::SmpteSt2084Pq,
+            17 => TransferFunction::SmpteSt428,
+            18 => TransferFunction::Bt2100Hlg,
+            _ => TransferFunction::Other(code_point),
+        }
+    }
+}
+
+impl From<TransferFunction> for u8 {
+    fn from(transfer_function: TransferFunction) -> Self {
+        match transfer_function {
+            TransferFunction::Bt709 => 1,
+            TransferFunction::Unspecified => 2,
+            TransferFunction::Bt470M => 4,
+            TransferFunction::Bt470BG => 5,
+            TransferFunction::Bt601 => 6,
+            TransferFunction::Smpte240M => 7,
+            TransferFunction::Linear => 8,
+            TransferFunction::Log100 => 9,
+            TransferFunction::Log316 => 10,
+            TransferFunction::Iec61966_2_4 => 11,
+            TransferFunction::Bt1361 => 12,
+            TransferFunction::Srgb => 13,
+            TransferFunction::Bt2020TenBit => 14,
+            TransferFunction::Bt2020TwelveBit => 15,
+            TransferFunction::SmpteSt2084Pq => 16,
+            TransferFunction::SmpteSt428 => 17,
+            TransferFunction::Bt2100Hlg => 18,
+            TransferFunction::Other(code_point) => code_point,
+        }
+    }
+}
+
+/// MatrixCoefficients code points of ITU-T H.273
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum YCbCrMatrix {
+    Identity,
+    Bt709,
+    #[default]
+    Unspecified,
+    Fcc,
+    // BT.470 System B, G and BT.601 625-line
+    Bt470BG,
+    // SMPTE 170M and BT.601 525-line, the same matrix as Bt470BG
+    Bt601,
+    Smpte240M,
+    YCgCo,
+    Bt2020NonConstantLuminance,
+    Bt2020ConstantLuminance,
+    // A code point without a variant; never holds one of the values above
+    Other(u8),
+}
+
+impl YCbCrMatrix {
+    // The value of the kCMFormatDescriptionExtension_YCbCrMatrix extension, if CoreMedia has one
+    pub fn core_media_name(self) -> Option<&'static str> {
+        match self {
+            YCbCrMatrix::Bt709 => Some("ITU_R_709_2"),
+            YCbCrMatrix::Bt470BG | YCbCrMatrix::Bt601 => Some("ITU_R_601_4"),
+            YCbCrMatrix::Smpte240M => Some("SMPTE_240M_1995"),
+            YCbCrMatrix::Bt2020NonConstantLuminance => Some("ITU_R_2020"),
+            _ => None,
+        }
+    }
+
+    // The matrix named by a kCMFormatDescriptionExtension_YCbCrMatrix value; ITU_R_601_4 maps to Bt601
+    pub fn from_core_media_name(name: &str) -> Option<Self> {
+        match name {
+            "ITU_R_709_2" => Some(YCbCrMatrix::Bt709),
+            "ITU_R_601_4" => Some(YCbCrMatrix::Bt601),
+            "SMPTE_240M_1995" => Some(YCbCrMatrix::Smpte240M),
+            "ITU_R_2020" => Some(YCbCrMatrix::Bt2020NonConstantLuminance),
+            _ => None,
+        }
+    }
+}
+
+impl From<u8> for YCbCrMatrix {
+    fn from(code_point: u8) -> Self {
+        match code_point {
+            0 => YCbCrMatrix::Identity,
+            1 => YCbCrMatrix::Bt709,
+            2 => YCbCrMatrix::Unspecified,
+            4 => YCbCrMatrix::Fcc,
+            5 => YCbCrMatrix::Bt470BG,
+            6 => YCbCrMatrix::Bt601,
+            7 => YCbCrMatrix::Smpte240M,
+            8 => YCbCrMatrix::YCgCo,
+            9 => YCbCrMatrix::Bt2020NonConstantLuminance,
+            10 => YCbCrMatrix::Bt2020ConstantLuminance,
+            _ => YCbCrMatrix::Other(code_point),
+        }
+    }
+}
+
+impl From<YCbCrMatrix> for u8 {
+    fn from(matrix: YCbCrMatrix) -> Self {
+        match matrix {
+            YCbCrMatrix::Identity => 0,
+            YCbCrMatrix::Bt709 => 1,
+            YCbCrMatrix::Unspecified => 2,
+            YCbCrMatrix::Fcc => 4,
+            YCbCrMatrix::Bt470BG => 5,
+            YCbCrMatrix::Bt601 => 6,
+            YCbCrMatrix::Smpte240M => 7,
+            YCbCrMatrix::YCgCo => 8,
+            YCbCrMatrix::Bt2020NonConstantLuminance => 9,
+            YCbCrMatrix::Bt2020ConstantLuminance => 10,
+            YCbCrMatrix::Other(code_point) => code_point,
+        }
+    }
+}
+
+/// The colour description of a colr box of type nclx or of H.264 and HEVC video usability information
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ColorDescription {
+    pub primaries: ColorPrimaries,
+    pub transfer_function: TransferFunction,
+    pub ycbcr_matrix: YCbCrMatrix,
+    pub full_range: bool,
+}
+
+impl ColorDescription {
+    #[inline]
+    pub fn from_code_points(colour_primaries: u8, transfer_characteristics: u8, matrix_coefficients: u8, full_range: bool) -> Self {
+        ColorDescription {
+            primaries: colour_primaries.into(),
+            transfer_function: transfer_characteristics.into(),
+            ycbcr_matrix: matrix_coefficients.into(),
+            full_range,
+        }
+    }
+
+    // The colour_primaries, transfer_characteristics and matrix_coefficients code points
+    #[inline]
+    pub fn code_points(&self) -> (u8, u8, u8) {
+        (self.primaries.into(), self.transfer_function.into(), self.ycbcr_matrix.into())
+    }
+}
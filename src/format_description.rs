@@ -1,30 +1,51 @@
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use std::{
+    convert::TryFrom,
     mem::{forget, size_of_val},
     ptr::{null, null_mut},
     slice::from_raw_parts,
 };
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use core_audio_types::base_types::{AudioChannelLayout, AudioFormatListItem, AudioStreamBasicDescription};
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use core_foundation::{
     array::{CFArray, CFArrayRef},
     base::{kCFAllocatorDefault, Boolean, CFAllocatorRef, CFType, CFTypeID, CFTypeRef, OSStatus, TCFType, TCFTypeRef},
+    boolean::CFBoolean,
+    data::CFData,
     dictionary::{CFDictionary, CFDictionaryRef},
+    number::CFNumber,
     propertylist::{CFPropertyList, CFPropertyListRef},
     string::{CFString, CFStringRef},
 };
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use core_graphics::{
     base::CGFloat,
     geometry::{CGRect, CGSize},
 };
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use core_video::image_buffer::{CVImageBuffer, CVImageBufferRef};
-use libc::{c_int, c_void, size_t};
-#[cfg(feature = "objc")]
+use libc::c_void;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use libc::{c_int, size_t};
+#[cfg(all(feature = "objc", any(target_os = "macos", target_os = "ios")))]
 use objc2::encode::{Encoding, RefEncode};
 
-use crate::{time::CMTime, OSType};
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use crate::{
+    codec_string::{CodecString, CodecStringError, CODEC_CONFIGURATION_ATOMS},
+    color_description::{ColorDescription, ColorPrimaries, TransferFunction, YCbCrMatrix},
+    time::CMTime,
+    video_format_extensions::{VideoFormatExtensions, VideoFormatExtensionsError},
+    OSType,
+};
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub const kCMFormatDescriptionError_InvalidParameter: OSStatus = -12710;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub const kCMFormatDescriptionError_AllocationFailed: OSStatus = -12711;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub const kCMFormatDescriptionError_ValueNotAvailable: OSStatus = -12718;
 
 type FourCharCode = u32;
@@ -32,8 +53,8 @@ type FourCharCode = u32;
 pub type CMMediaType = FourCharCode;
 
 #[inline]
-const fn fourcc(code: &[u8; 4]) -> u32 {
-    ((code[0] as u32) << 24) | ((code[1] as u32) << 16) | ((code[2] as u32) << 8) | ((code[3] as u32) << 0)
+pub(crate) const fn fourcc(code: &[u8; 4]) -> u32 {
+    ((code[0] as u32) << 24) | ((code[1] as u32) << 16) | ((code[2] as u32) << 8) | (code[3] as u32)
 }
 
 pub const kCMMediaType_Video: CMMediaType = fourcc(b"vide");
@@ -51,6 +72,7 @@ pub struct opaqueCMFormatDescription(c_void);
 
 pub type CMFormatDescriptionRef = *mut opaqueCMFormatDescription;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     pub fn CMFormatDescriptionCreate(
         allocator: CFAllocatorRef,
@@ -86,6 +108,7 @@ pub const kCMAudioCodecType_AAC_AudibleProtected: CMAudioCodecType = fourcc(b"aa
 
 pub type CMAudioFormatDescriptionRef = CMFormatDescriptionRef;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     pub fn CMAudioFormatDescriptionCreate(
         allocator: CFAllocatorRef,
@@ -122,6 +145,7 @@ pub const kCMAudioFormatDescriptionMask_All: CMAudioFormatDescriptionMask = kCMA
     kCMAudioFormatDescriptionMask_ChannelLayout |
     kCMAudioFormatDescriptionMask_Extensions;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     pub fn CMAudioFormatDescriptionEqual(
         formatDescription: CMAudioFormatDescriptionRef,
@@ -200,6 +224,7 @@ pub struct CMVideoDimensions {
     pub height: i32,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     pub static kCMFormatDescriptionExtension_FormatName: CFStringRef;
     pub static kCMFormatDescriptionExtension_Depth: CFStringRef;
@@ -213,6 +238,7 @@ extern "C" {
     pub static kCMFormatDescriptionKey_CleanApertureHorizontalOffsetRational: CFStringRef;
     pub static kCMFormatDescriptionKey_CleanApertureVerticalOffsetRational: CFStringRef;
     pub static kCMFormatDescriptionExtension_FieldCount: CFStringRef;
+    pub static kCMFormatDescriptionExtension_FieldDetail: CFStringRef;
     pub static kCMFormatDescriptionFieldDetail_TemporalTopFirst: CFStringRef;
     pub static kCMFormatDescriptionFieldDetail_TemporalBottomFirst: CFStringRef;
     pub static kCMFormatDescriptionFieldDetail_SpatialFirstLineEarly: CFStringRef;
@@ -299,6 +325,7 @@ pub const kCMMPEG2VideoProfile_XDCAM_HD422_720p25_CBR50: i32 = fourcc(b"xd55") a
 pub const kCMMPEG2VideoProfile_XDCAM_HD422_720p30_CBR50: i32 = fourcc(b"xd51") as i32;
 pub const kCMMPEG2VideoProfile_XF: i32 = fourcc(b"xfz1") as i32;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     pub static kCMFormatDescriptionExtension_TemporalQuality: CFStringRef;
     pub static kCMFormatDescriptionExtension_SpatialQuality: CFStringRef;
@@ -401,6 +428,7 @@ pub const kCMMuxedStreamType_MPEG2Program: CMMuxedStreamType = fourcc(b"mp2p");
 pub const kCMMuxedStreamType_DV: CMMuxedStreamType = fourcc(b"dv  ");
 pub const kCMMuxedStreamType_EmbeddedDeviceScreenRecording: CMMuxedStreamType = fourcc(b"isr ");
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     pub fn CMMuxedFormatDescriptionCreate(
         allocator: CFAllocatorRef,
@@ -447,6 +475,7 @@ pub const kCMTextJustification_left_top: CMTextJustificationValue = 0;
 pub const kCMTextJustification_centered: CMTextJustificationValue = 1;
 pub const kCMTextJustification_bottom_right: CMTextJustificationValue = -1;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     pub static kCMTextFormatDescriptionExtension_DisplayFlags: CFStringRef;
     pub static kCMTextFormatDescriptionExtension_BackgroundColor: CFStringRef;
@@ -517,6 +546,7 @@ pub const kCMTimeCodeFlag_DropFrame: u32 = 1 << 0;
 pub const kCMTimeCodeFlag_24HourMax: u32 = 1 << 1;
 pub const kCMTimeCodeFlag_NegTimesOK: u32 = 1 << 2;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     pub fn CMTimeCodeFormatDescriptionCreate(
         allocator: CFAllocatorRef,
@@ -545,6 +575,7 @@ pub const kCMMetadataFormatType_ID3: CMMetadataFormatType = fourcc(b"id3 ");
 pub const kCMMetadataFormatType_Boxed: CMMetadataFormatType = fourcc(b"mebx");
 pub const kCMMetadataFormatType_EMSG: CMMetadataFormatType = fourcc(b"emsg");
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     pub static kCMFormatDescriptionExtensionKey_MetadataKeyTable: CFStringRef;
     pub static kCMMetadataFormatDescriptionKey_Namespace: CFStringRef;
@@ -591,11 +622,12 @@ extern "C" {
     pub fn CMMetadataFormatDescriptionGetIdentifiers(desc: CMMetadataFormatDescriptionRef) -> CFArrayRef;
 }
 
-#[cfg(feature = "objc")]
+#[cfg(all(feature = "objc", any(target_os = "macos", target_os = "ios")))]
 unsafe impl RefEncode for opaqueCMFormatDescription {
     const ENCODING_REF: Encoding = Encoding::Pointer(&Encoding::Struct("opaqueCMFormatDescription", &[]));
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub trait TCMFormatDescription: TCFType {
     #[inline]
     fn as_buffer(&self) -> CMFormatDescription {
@@ -613,6 +645,7 @@ pub trait TCMFormatDescription: TCFType {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CMFormatDescription {
     #[inline]
     pub fn downcast<T: TCMFormatDescription>(&self) -> Option<T> {
@@ -637,12 +670,16 @@ impl CMFormatDescription {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 declare_TCFType! {
     CMFormatDescription, CMFormatDescriptionRef
 }
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_TCFType!(CMFormatDescription, CMFormatDescriptionRef, CMFormatDescriptionGetTypeID);
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_CFTypeDescription!(CMFormatDescription);
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CMFormatDescription {
     #[inline]
     pub fn new(media_type: CMMediaType, media_subtype: FourCharCode, extensions: Option<&CFDictionary<CFString, CFType>>) -> Result<Self, OSStatus> {
@@ -720,14 +757,19 @@ impl CMFormatDescription {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl TCMFormatDescription for CMAudioFormatDescription {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 declare_TCFType! {
     CMAudioFormatDescription, CMAudioFormatDescriptionRef
 }
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_TCFType!(CMAudioFormatDescription, CMAudioFormatDescriptionRef, CMFormatDescriptionGetTypeID);
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_CFTypeDescription!(CMAudioFormatDescription);
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CMAudioFormatDescription {
     #[inline]
     pub fn new(
@@ -786,6 +828,12 @@ impl CMAudioFormatDescription {
         }
     }
 
+    // The RFC 6381 codecs parameter, from the codec type and the magic cookie
+    #[inline]
+    pub fn get_codec_string(&self) -> Result<CodecString, CodecStringError> {
+        CodecString::from_audio_format(self.get_codec_type(), self.get_magic_cookie())
+    }
+
     #[inline]
     pub fn get_channel_layout(&self) -> Option<(&AudioChannelLayout, usize)> {
         unsafe {
@@ -857,14 +905,106 @@ impl CMAudioFormatDescription {
     }
 }
 
+// Collects the extensions of a video format description derived from a bitstream, for CMVideoFormatDescription::new
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+#[derive(Default)]
+pub(crate) struct ExtensionsBuilder {
+    extensions: Vec<(CFString, CFType)>,
+    atoms: Vec<(CFString, CFType)>,
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl ExtensionsBuilder {
+    #[inline]
+    pub(crate) fn set(&mut self, key: CFStringRef, value: CFType) {
+        self.extensions.push((unsafe { CFString::wrap_under_get_rule(key) }, value));
+    }
+
+    #[inline]
+    pub(crate) fn set_name(&mut self, key: CFStringRef, name: &'static str) {
+        self.set(key, CFString::from_static_string(name).as_CFType());
+    }
+
+    // Sets a CFNumber extension, which CoreMedia reads as a signed 32-bit integer
+    #[inline]
+    pub(crate) fn set_number(&mut self, key: CFStringRef, value: u32) -> Result<(), VideoFormatExtensionsError> {
+        let value = i32::try_from(value).map_err(|_| VideoFormatExtensionsError::InvalidValue)?;
+        self.set(key, CFNumber::from(value).as_CFType());
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) fn set_data(&mut self, key: CFStringRef, data: &[u8]) {
+        self.set(key, CFData::from_buffer(data).as_CFType());
+    }
+
+    pub(crate) fn atom(&mut self, atom: &str, data: &[u8]) {
+        self.atoms.push((CFString::new(atom), CFData::from_buffer(data).as_CFType()));
+    }
+
+    pub(crate) fn build(mut self) -> CFDictionary<CFString, CFType> {
+        if !self.atoms.is_empty() {
+            // An atom that occurs more than once is stored as an array
+            let mut grouped: Vec<(CFString, Vec<CFType>)> = Vec::new();
+            for (name, data) in self.atoms.drain(..) {
+                match grouped.iter_mut().find(|(existing, _)| *existing == name) {
+                    Some((_, values)) => values.push(data),
+                    None => grouped.push((name, vec![data])),
+                }
+            }
+            let atoms: Vec<(CFString, CFType)> = grouped
+                .into_iter()
+                .map(|(name, mut values)| {
+                    let value = if values.len() == 1 { values.remove(0) } else { CFArray::from_CFTypes(&values).as_CFType() };
+                    (name, value)
+                })
+                .collect();
+            let atoms = CFDictionary::from_CFType_pairs(&atoms);
+            self.set(unsafe { kCMFormatDescriptionExtension_SampleDescriptionExtensionAtoms }, atoms.as_CFType());
+        }
+        CFDictionary::from_CFType_pairs(&self.extensions)
+    }
+}
+
+// Wraps a sample description extension atom, such as av1C, in the extensions dictionary taken by
+// CMVideoFormatDescription::new
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub(crate) fn extensions_with_atom(atom: &str, data: &[u8]) -> CFDictionary<CFString, CFType> {
+    let mut builder = ExtensionsBuilder::default();
+    builder.atom(atom, data);
+    builder.build()
+}
+
+// Finds an atom in the value of the SampleDescriptionExtensionAtoms extension; an atom that occurs more than once is
+// stored as an array, in which case the first one is returned
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub(crate) fn find_extension_atom(atoms: &CFPropertyList, atom: &str) -> Option<Vec<u8>> {
+    let atoms = atoms.downcast::<CFDictionary>()?;
+    let key = CFString::new(atom);
+    let value = atoms.find(key.as_concrete_TypeRef() as *const c_void)?;
+    let value = unsafe { CFType::wrap_under_get_rule(*value as CFTypeRef) };
+    if let Some(data) = value.downcast::<CFData>() {
+        return Some(data.bytes().to_vec());
+    }
+    let array = value.downcast::<CFArray>()?;
+    let first = array.get(0)?;
+    let first = unsafe { CFType::wrap_under_get_rule(*first as CFTypeRef) };
+    first.downcast::<CFData>().map(|data| data.bytes().to_vec())
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl TCMFormatDescription for CMVideoFormatDescription {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 declare_TCFType! {
     CMVideoFormatDescription, CMVideoFormatDescriptionRef
 }
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_TCFType!(CMVideoFormatDescription, CMVideoFormatDescriptionRef, CMFormatDescriptionGetTypeID);
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_CFTypeDescription!(CMVideoFormatDescription);
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CMVideoFormatDescription {
     #[inline]
     pub fn new(codec_type: CMVideoCodecType, width: i32, height: i32, extensions: Option<&CFDictionary<CFString, CFType>>) -> Result<Self, OSStatus> {
@@ -1018,16 +1158,116 @@ impl CMVideoFormatDescription {
     pub fn matches_image_buffer(&self, image_buffer: &CVImageBuffer) -> bool {
         unsafe { CMVideoFormatDescriptionMatchesImageBuffer(self.as_concrete_TypeRef(), image_buffer.as_concrete_TypeRef()) != 0 }
     }
+
+    #[inline]
+    fn get_extension_by_key(&self, extension_key: CFStringRef) -> Option<CFPropertyList> {
+        self.as_buffer().get_extension(&unsafe { CFString::wrap_under_get_rule(extension_key) })
+    }
+
+    #[inline]
+    fn get_string_extension(&self, extension_key: CFStringRef) -> Option<String> {
+        self.get_extension_by_key(extension_key)?.downcast::<CFString>().map(|value| value.to_string())
+    }
+
+    #[inline]
+    pub fn get_color_primaries(&self) -> Option<ColorPrimaries> {
+        ColorPrimaries::from_core_media_name(&self.get_string_extension(unsafe { kCMFormatDescriptionExtension_ColorPrimaries })?)
+    }
+
+    // The transfer function, reading kCMFormatDescriptionExtension_GammaLevel when it is UseGamma
+    pub fn get_transfer_function(&self) -> Option<TransferFunction> {
+        let name = self.get_string_extension(unsafe { kCMFormatDescriptionExtension_TransferFunction })?;
+        if name == "UseGamma" {
+            let gamma_level = self.get_extension_by_key(unsafe { kCMFormatDescriptionExtension_GammaLevel })?.downcast::<CFNumber>()?;
+            return TransferFunction::from_gamma_level(gamma_level.to_f64()?);
+        }
+        TransferFunction::from_core_media_name(&name)
+    }
+
+    #[inline]
+    pub fn get_ycbcr_matrix(&self) -> Option<YCbCrMatrix> {
+        YCbCrMatrix::from_core_media_name(&self.get_string_extension(unsafe { kCMFormatDescriptionExtension_YCbCrMatrix })?)
+    }
+
+    #[inline]
+    pub fn get_full_range_video(&self) -> Option<bool> {
+        self.get_extension_by_key(unsafe { kCMFormatDescriptionExtension_FullRangeVideo })?.downcast::<CFBoolean>().map(bool::from)
+    }
+
+    // The colour extensions, with missing or unrecognized values reported as unspecified and video range
+    pub fn get_color_description(&self) -> ColorDescription {
+        ColorDescription {
+            primaries: self.get_color_primaries().unwrap_or_default(),
+            transfer_function: self.get_transfer_function().unwrap_or_default(),
+            ycbcr_matrix: self.get_ycbcr_matrix().unwrap_or_default(),
+            full_range: self.get_full_range_video().unwrap_or(false),
+        }
+    }
+
+    // The RFC 6381 codecs parameter, from the codec type, the configuration record among the sample description
+    // extension atoms and, for AV1, the colour extensions
+    pub fn get_codec_string(&self) -> Result<CodecString, CodecStringError> {
+        let mut extensions = VideoFormatExtensions {
+            color_primaries: self.get_color_primaries(),
+            transfer_function: self.get_transfer_function(),
+            ycbcr_matrix: self.get_ycbcr_matrix(),
+            full_range_video: self.get_full_range_video(),
+            ..Default::default()
+        };
+        if let Some(atoms) = self.get_extension_by_key(unsafe { kCMFormatDescriptionExtension_SampleDescriptionExtensionAtoms }) {
+            for name in CODEC_CONFIGURATION_ATOMS {
+                if let Some(data) = find_extension_atom(&atoms, name) {
+                    extensions.set_atom(name, &data);
+                }
+            }
+        }
+        CodecString::from_video_format_extensions(self.get_codec_type(), &extensions)
+    }
+
+    // Creates a copy of the format description with its colour extensions replaced; format descriptions are
+    // immutable, so this is how the colour description is set
+    pub fn with_color_description(&self, color: &ColorDescription) -> Result<Self, OSStatus> {
+        let color_keys = unsafe {
+            [
+                kCMFormatDescriptionExtension_ColorPrimaries,
+                kCMFormatDescriptionExtension_TransferFunction,
+                kCMFormatDescriptionExtension_GammaLevel,
+                kCMFormatDescriptionExtension_YCbCrMatrix,
+                kCMFormatDescriptionExtension_FullRangeVideo,
+            ]
+            .map(|key| CFString::wrap_under_get_rule(key))
+        };
+        let mut builder = ExtensionsBuilder::default();
+        if let Some(extensions) = self.as_buffer().get_extensions() {
+            let (keys, values) = extensions.get_keys_and_values();
+            for (key, value) in keys.into_iter().zip(values) {
+                let key = key as CFStringRef;
+                if !color_keys.contains(&unsafe { CFString::wrap_under_get_rule(key) }) {
+                    builder.set(key, unsafe { CFType::wrap_under_get_rule(value as CFTypeRef) });
+                }
+            }
+        }
+        let mut color_extensions = VideoFormatExtensions::default();
+        color_extensions.set_color_description(color);
+        color_extensions.write_to(&mut builder).map_err(|_| kCMFormatDescriptionError_InvalidParameter)?;
+        let dimensions = self.get_dimensions();
+        Self::new(self.get_codec_type(), dimensions.width, dimensions.height, Some(&builder.build()))
+    }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl TCMFormatDescription for CMMuxedFormatDescription {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 declare_TCFType! {
     CMMuxedFormatDescription, CMMuxedFormatDescriptionRef
 }
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_TCFType!(CMMuxedFormatDescription, CMMuxedFormatDescriptionRef, CMFormatDescriptionGetTypeID);
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_CFTypeDescription!(CMMuxedFormatDescription);
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CMMuxedFormatDescription {
     #[inline]
     pub fn new(mux_type: CMMuxedStreamType, extensions: &CFDictionary<CFString, CFType>) -> Result<Self, OSStatus> {
@@ -1047,14 +1287,19 @@ impl CMMuxedFormatDescription {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl TCMFormatDescription for CMClosedCaptionFormatDescription {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 declare_TCFType! {
     CMClosedCaptionFormatDescription, CMClosedCaptionFormatDescriptionRef
 }
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_TCFType!(CMClosedCaptionFormatDescription, CMClosedCaptionFormatDescriptionRef, CMFormatDescriptionGetTypeID);
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_CFTypeDescription!(CMClosedCaptionFormatDescription);
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CMClosedCaptionFormatDescription {
     #[inline]
     pub fn new(format_type: CMClosedCaptionFormatType, extensions: Option<&CFDictionary<CFString, CFType>>) -> Result<Self, OSStatus> {
@@ -1081,14 +1326,19 @@ impl CMClosedCaptionFormatDescription {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl TCMFormatDescription for CMTextFormatDescription {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 declare_TCFType! {
     CMTextFormatDescription, CMTextFormatDescriptionRef
 }
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_TCFType!(CMTextFormatDescription, CMTextFormatDescriptionRef, CMFormatDescriptionGetTypeID);
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_CFTypeDescription!(CMTextFormatDescription);
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CMTextFormatDescription {
     #[inline]
     pub fn new(format_type: CMTextFormatType, extensions: Option<&CFDictionary<CFString, CFType>>) -> Result<Self, OSStatus> {
@@ -1195,14 +1445,19 @@ impl CMTextFormatDescription {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl TCMFormatDescription for CMTimeCodeFormatDescription {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 declare_TCFType! {
     CMTimeCodeFormatDescription, CMTimeCodeFormatDescriptionRef
 }
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_TCFType!(CMTimeCodeFormatDescription, CMTimeCodeFormatDescriptionRef, CMFormatDescriptionGetTypeID);
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_CFTypeDescription!(CMTimeCodeFormatDescription);
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CMTimeCodeFormatDescription {
     #[inline]
     pub fn new(
@@ -1252,14 +1507,19 @@ impl CMTimeCodeFormatDescription {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl TCMFormatDescription for CMMetadataFormatDescription {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 declare_TCFType! {
     CMMetadataFormatDescription, CMMetadataFormatDescriptionRef
 }
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_TCFType!(CMMetadataFormatDescription, CMMetadataFormatDescriptionRef, CMFormatDescriptionGetTypeID);
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl_CFTypeDescription!(CMMetadataFormatDescription);
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CMMetadataFormatDescription {
     #[inline]
     pub fn new_with_keys(metadata_type: CMMetadataFormatType, keys: Option<&CFArray<CFString>>) -> Result<Self, OSStatus> {
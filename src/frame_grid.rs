@@ -0,0 +1,205 @@
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    iter::FusedIterator,
+};
+
+use crate::{
+    time::{
+        div_round, gcd, kCMTimeFlags_Valid, kCMTimeMaxTimescale, kCMTimeRoundingMethod_RoundHalfAwayFromZero,
+        kCMTimeRoundingMethod_RoundTowardNegativeInfinity, kCMTimeRoundingMethod_RoundTowardPositiveInfinity, resolve_rounding_method, CMTime,
+        CMTimeRoundingMethod, CMTimeScale, CMTimeValue,
+    },
+    time_range::CMTimeRange,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameGridError {
+    InvalidFrameDuration,
+    InvalidOrigin,
+    NonNumericTime,
+    Overflow,
+}
+
+impl Display for FrameGridError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            FrameGridError::InvalidFrameDuration => write!(f, "frame duration must be a positive numeric time"),
+            FrameGridError::InvalidOrigin => write!(f, "origin must be a numeric time"),
+            FrameGridError::NonNumericTime => write!(f, "time is not numeric"),
+            FrameGridError::Overflow => write!(f, "frame index is out of range"),
+        }
+    }
+}
+
+impl Error for FrameGridError {}
+
+#[inline]
+fn is_computable(time: &CMTime) -> bool {
+    time.is_numeric() && time.timescale > 0
+}
+
+/// The frame times origin + n * frame_duration for every integer n
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FrameGrid {
+    frame_duration: CMTime,
+    origin: CMTime,
+    // Grid points are origin_value + n * step in this timescale
+    timescale: CMTimeScale,
+    origin_value: i128,
+    step: i128,
+}
+
+impl FrameGrid {
+    // Grid points are exact in the least common multiple of the timescales; if it is too large, the origin is rounded
+    // to the frame duration's timescale
+    pub fn new(frame_duration: CMTime, origin: CMTime) -> Result<Self, FrameGridError> {
+        if !is_computable(&frame_duration) || frame_duration.value <= 0 {
+            return Err(FrameGridError::InvalidFrameDuration);
+        }
+        if !is_computable(&origin) {
+            return Err(FrameGridError::InvalidOrigin);
+        }
+        let origin_timescale = origin.timescale as u128 / gcd(origin.value.unsigned_abs() as u128, origin.timescale as u128);
+        let lcm = origin_timescale / gcd(origin_timescale, frame_duration.timescale as u128) * frame_duration.timescale as u128;
+        let timescale = if lcm <= kCMTimeMaxTimescale as u128 { lcm as CMTimeScale } else { frame_duration.timescale };
+        let (origin_value, _) =
+            div_round(origin.value as i128 * timescale as i128, origin.timescale as i128, kCMTimeRoundingMethod_RoundHalfAwayFromZero);
+        let step = frame_duration.value as i128 * (timescale / frame_duration.timescale) as i128;
+        Ok(FrameGrid { frame_duration, origin, timescale, origin_value, step })
+    }
+
+    #[inline]
+    pub fn with_frame_duration(frame_duration: CMTime) -> Result<Self, FrameGridError> {
+        Self::new(frame_duration, CMTime::ZERO)
+    }
+
+    #[inline]
+    pub fn frame_duration(&self) -> CMTime {
+        self.frame_duration
+    }
+
+    #[inline]
+    pub fn origin(&self) -> CMTime {
+        self.origin
+    }
+
+    // The frame index of the time, rounded to a whole frame with the rounding method
+    pub fn frame_index(&self, time: CMTime, method: CMTimeRoundingMethod) -> Result<i64, FrameGridError> {
+        if !is_computable(&time) {
+            return Err(FrameGridError::NonNumericTime);
+        }
+        let num = time.value as i128 * self.timescale as i128 - self.origin_value * time.timescale as i128;
+        let den = self.step * time.timescale as i128;
+        let method = resolve_rounding_method(method, time.timescale, self.timescale);
+        let (index, _) = div_round(num, den, method);
+        i64::try_from(index).map_err(|_| FrameGridError::Overflow)
+    }
+
+    pub fn time_at(&self, index: i64) -> Result<CMTime, FrameGridError> {
+        let value = CMTimeValue::try_from(self.origin_value + index as i128 * self.step).map_err(|_| FrameGridError::Overflow)?;
+        Ok(CMTime { value, timescale: self.timescale, flags: kCMTimeFlags_Valid, epoch: 0 })
+    }
+
+    #[inline]
+    pub fn snap(&self, time: CMTime, method: CMTimeRoundingMethod) -> Result<CMTime, FrameGridError> {
+        self.time_at(self.frame_index(time, method)?)
+    }
+
+    #[inline]
+    pub fn snap_to_nearest(&self, time: CMTime) -> Result<CMTime, FrameGridError> {
+        self.snap(time, kCMTimeRoundingMethod_RoundHalfAwayFromZero)
+    }
+
+    // The latest grid point at or before the time
+    #[inline]
+    pub fn snap_to_previous(&self, time: CMTime) -> Result<CMTime, FrameGridError> {
+        self.snap(time, kCMTimeRoundingMethod_RoundTowardNegativeInfinity)
+    }
+
+    // The earliest grid point at or after the time
+    #[inline]
+    pub fn snap_to_next(&self, time: CMTime) -> Result<CMTime, FrameGridError> {
+        self.snap(time, kCMTimeRoundingMethod_RoundTowardPositiveInfinity)
+    }
+
+    #[inline]
+    pub fn is_on_grid(&self, time: CMTime) -> bool {
+        self.snap_to_previous(time).map(|snapped| snapped == time).unwrap_or(false)
+    }
+
+    // Indices of the first grid point at or after the start and the first at or after the end
+    fn index_bounds(&self, range: &CMTimeRange) -> Result<(i64, i64), FrameGridError> {
+        if !range.is_valid() || !is_computable(&range.start) || !is_computable(&range.duration) {
+            return Err(FrameGridError::NonNumericTime);
+        }
+        let first = self.frame_index(range.start, kCMTimeRoundingMethod_RoundTowardPositiveInfinity)?;
+        let end = self.frame_index(range.start + range.duration, kCMTimeRoundingMethod_RoundTowardPositiveInfinity)?;
+        Ok((first, end.max(first)))
+    }
+
+    // The number of grid points in the range, with the start included and the end excluded
+    pub fn frame_count(&self, range: CMTimeRange) -> Result<u64, FrameGridError> {
+        let (first, end) = self.index_bounds(&range)?;
+        Ok((end - first) as u64)
+    }
+
+    pub fn frames(&self, range: CMTimeRange) -> Result<FrameTimes, FrameGridError> {
+        let (first, end) = self.index_bounds(&range)?;
+        self.time_at(first)?;
+        if end > first {
+            self.time_at(end - 1)?;
+        }
+        Ok(FrameTimes { grid: *self, next: first, end })
+    }
+}
+
+/// Iterator over the grid points of a range
+#[derive(Clone, Debug)]
+pub struct FrameTimes {
+    grid: FrameGrid,
+    next: i64,
+    end: i64,
+}
+
+impl FrameTimes {
+    // Index of the next frame the iterator will return
+    #[inline]
+    pub fn next_index(&self) -> i64 {
+        self.next
+    }
+}
+
+impl Iterator for FrameTimes {
+    type Item = CMTime;
+
+    fn next(&mut self) -> Option<CMTime> {
+        if self.next >= self.end {
+            return None;
+        }
+        let time = self.grid.time_at(self.next).ok()?;
+        self.next += 1;
+        Some(time)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.end - self.next) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl DoubleEndedIterator for FrameTimes {
+    fn next_back(&mut self) -> Option<CMTime> {
+        if self.next >= self.end {
+            return None;
+        }
+        self.end -= 1;
+        self.grid.time_at(self.end).ok()
+    }
+}
+
+impl ExactSizeIterator for FrameTimes {}
+
+impl FusedIterator for FrameTimes {}
@@ -0,0 +1,386 @@
+// The static HDR metadata carried by the kCMFormatDescriptionExtension_MasteringDisplayColorVolume,
+// ContentLightLevelInfo, ContentColorVolume and AmbientViewingEnvironment extensions. Each is stored in a CFData as
+// the big-endian payload of the corresponding ITU-T H.265 SEI message, which is also the body of the mdcv (SMPTE ST
+// 2086) and clli (CTA-861.3) boxes.
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    iter::once,
+};
+
+use crate::bitstream::ByteReader;
+
+// Chromaticity coordinates are coded in increments of 0.00002
+const CHROMATICITY_SCALE: f64 = 50_000.0;
+// Mastering display luminance is coded in increments of 0.0001 cd/m²
+const MASTERING_LUMINANCE_SCALE: f64 = 10_000.0;
+// Content colour volume luminance is coded in increments of 0.0000001 cd/m²
+const CONTENT_LUMINANCE_SCALE: f64 = 10_000_000.0;
+// Ambient illuminance is coded in increments of 0.0001 lux
+const ILLUMINANCE_SCALE: f64 = 10_000.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HdrMetadataError {
+    UnexpectedEnd,
+    InvalidLength,
+    ValueOutOfRange,
+    CancelledContentColorVolume,
+}
+
+impl Display for HdrMetadataError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            HdrMetadataError::UnexpectedEnd => write!(f, "unexpected end of HDR metadata"),
+            HdrMetadataError::InvalidLength => write!(f, "HDR metadata has the wrong length"),
+            HdrMetadataError::ValueOutOfRange => write!(f, "value cannot be represented in the HDR metadata"),
+            HdrMetadataError::CancelledContentColorVolume => write!(f, "content colour volume cancels a previous one"),
+        }
+    }
+}
+
+impl Error for HdrMetadataError {}
+
+// Scales a real value to its coded integer, failing unless it is finite and the integer is within min..=max
+fn encode(value: f64, scale: f64, min: i64, max: i64) -> Result<i64, HdrMetadataError> {
+    let coded = (value * scale).round();
+    if coded.is_finite() && coded >= min as f64 && coded <= max as f64 {
+        Ok(coded as i64)
+    } else {
+        Err(HdrMetadataError::ValueOutOfRange)
+    }
+}
+
+#[inline]
+fn encode_u16(value: f64, scale: f64) -> Result<u16, HdrMetadataError> {
+    encode(value, scale, 0, u16::MAX as i64).map(|coded| coded as u16)
+}
+
+#[inline]
+fn encode_u32(value: f64, scale: f64) -> Result<u32, HdrMetadataError> {
+    encode(value, scale, 0, u32::MAX as i64).map(|coded| coded as u32)
+}
+
+#[inline]
+fn read_u16(reader: &mut ByteReader) -> Result<u16, HdrMetadataError> {
+    reader.read_u16().ok_or(HdrMetadataError::UnexpectedEnd)
+}
+
+#[inline]
+fn read_u32(reader: &mut ByteReader) -> Result<u32, HdrMetadataError> {
+    reader.read_uint(4).map(|value| value as u32).ok_or(HdrMetadataError::UnexpectedEnd)
+}
+
+// Checks that a fixed size payload has exactly len bytes
+#[inline]
+fn fixed_size_reader(data: &[u8], len: usize) -> Result<ByteReader<'_>, HdrMetadataError> {
+    if data.len() == len {
+        Ok(ByteReader::new(data))
+    } else {
+        Err(HdrMetadataError::InvalidLength)
+    }
+}
+
+/// CIE 1931 xy chromaticity coordinates
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Chromaticity {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Chromaticity {
+    #[inline]
+    pub fn new(x: f64, y: f64) -> Self {
+        Chromaticity { x, y }
+    }
+
+    #[inline]
+    fn read(reader: &mut ByteReader) -> Result<Self, HdrMetadataError> {
+        let x = read_u16(reader)? as f64 / CHROMATICITY_SCALE;
+        let y = read_u16(reader)? as f64 / CHROMATICITY_SCALE;
+        Ok(Chromaticity { x, y })
+    }
+
+    #[inline]
+    fn to_bytes(self) -> Result<[u8; 4], HdrMetadataError> {
+        let [x_high, x_low] = encode_u16(self.x, CHROMATICITY_SCALE)?.to_be_bytes();
+        let [y_high, y_low] = encode_u16(self.y, CHROMATICITY_SCALE)?.to_be_bytes();
+        Ok([x_high, x_low, y_high, y_low])
+    }
+}
+
+/// The colour volume of a mastering display, as defined in SMPTE ST 2086 and the mastering display colour volume SEI
+/// message of ITU-T H.265
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MasteringDisplayColorVolume {
+    // In the order they are coded, which H.265 recommends to be green, blue, red
+    pub display_primaries: [Chromaticity; 3],
+    pub white_point: Chromaticity,
+    // In cd/m²
+    pub max_luminance: f64,
+    // In cd/m²
+    pub min_luminance: f64,
+}
+
+impl MasteringDisplayColorVolume {
+    pub const LEN: usize = 24;
+
+    // Parses the 24 byte payload of the SEI message, the body of an mdcv box or the value of the
+    // kCMFormatDescriptionExtension_MasteringDisplayColorVolume extension
+    pub fn parse(data: &[u8]) -> Result<Self, HdrMetadataError> {
+        let mut reader = fixed_size_reader(data, Self::LEN)?;
+        let mut display_primaries = [Chromaticity::default(); 3];
+        for primary in display_primaries.iter_mut() {
+            *primary = Chromaticity::read(&mut reader)?;
+        }
+        Ok(MasteringDisplayColorVolume {
+            display_primaries,
+            white_point: Chromaticity::read(&mut reader)?,
+            max_luminance: read_u32(&mut reader)? as f64 / MASTERING_LUMINANCE_SCALE,
+            min_luminance: read_u32(&mut reader)? as f64 / MASTERING_LUMINANCE_SCALE,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 24], HdrMetadataError> {
+        let mut data = [0; 24];
+        let chromaticities = self.display_primaries.iter().chain(once(&self.white_point));
+        for (bytes, chromaticity) in data.chunks_exact_mut(4).zip(chromaticities) {
+            bytes.copy_from_slice(&chromaticity.to_bytes()?);
+        }
+        data[16..20].copy_from_slice(&encode_u32(self.max_luminance, MASTERING_LUMINANCE_SCALE)?.to_be_bytes());
+        data[20..24].copy_from_slice(&encode_u32(self.min_luminance, MASTERING_LUMINANCE_SCALE)?.to_be_bytes());
+        Ok(data)
+    }
+}
+
+/// The content light level information of CTA-861.3 and the content light level information SEI message of ITU-T
+/// H.265
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ContentLightLevelInfo {
+    // MaxCLL in cd/m²
+    pub max_content_light_level: u16,
+    // MaxFALL in cd/m²
+    pub max_pic_average_light_level: u16,
+}
+
+impl ContentLightLevelInfo {
+    pub const LEN: usize = 4;
+
+    // Parses the 4 byte payload of the SEI message, the body of a clli box or the value of the
+    // kCMFormatDescriptionExtension_ContentLightLevelInfo extension
+    pub fn parse(data: &[u8]) -> Result<Self, HdrMetadataError> {
+        let mut reader = fixed_size_reader(data, Self::LEN)?;
+        Ok(ContentLightLevelInfo { max_content_light_level: read_u16(&mut reader)?, max_pic_average_light_level: read_u16(&mut reader)? })
+    }
+
+    #[inline]
+    pub fn to_bytes(&self) -> [u8; 4] {
+        let [max_cll_high, max_cll_low] = self.max_content_light_level.to_be_bytes();
+        let [max_fall_high, max_fall_low] = self.max_pic_average_light_level.to_be_bytes();
+        [max_cll_high, max_cll_low, max_fall_high, max_fall_low]
+    }
+}
+
+/// The content colour volume SEI message of ITU-T H.265, without its cancel flag
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ContentColorVolume {
+    pub persistence_flag: bool,
+    // Coordinates may be outside 0 to 1, down to -100 and up to 100
+    pub primaries: Option<[Chromaticity; 3]>,
+    // In cd/m²
+    pub min_luminance: Option<f64>,
+    // In cd/m²
+    pub max_luminance: Option<f64>,
+    // In cd/m²
+    pub avg_luminance: Option<f64>,
+}
+
+impl ContentColorVolume {
+    // Parses the payload of the SEI message or the value of the kCMFormatDescriptionExtension_ContentColorVolume
+    // extension; its length depends on which values are present
+    pub fn parse(data: &[u8]) -> Result<Self, HdrMetadataError> {
+        let mut reader = ByteReader::new(data);
+        let flags = reader.read_u8().ok_or(HdrMetadataError::UnexpectedEnd)?;
+        if flags & 0x80 != 0 {
+            return Err(HdrMetadataError::CancelledContentColorVolume);
+        }
+        let primaries = if flags & 0x20 != 0 {
+            let mut primaries = [Chromaticity::default(); 3];
+            for primary in primaries.iter_mut() {
+                primary.x = read_u32(&mut reader)? as i32 as f64 / CHROMATICITY_SCALE;
+                primary.y = read_u32(&mut reader)? as i32 as f64 / CHROMATICITY_SCALE;
+            }
+            Some(primaries)
+        } else {
+            None
+        };
+        let mut read_luminance = |mask: u8| -> Result<Option<f64>, HdrMetadataError> {
+            if flags & mask != 0 {
+                Ok(Some(read_u32(&mut reader)? as f64 / CONTENT_LUMINANCE_SCALE))
+            } else {
+                Ok(None)
+            }
+        };
+        Ok(ContentColorVolume {
+            persistence_flag: flags & 0x40 != 0,
+            primaries,
+            min_luminance: read_luminance(0x10)?,
+            max_luminance: read_luminance(0x08)?,
+            avg_luminance: read_luminance(0x04)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, HdrMetadataError> {
+        let luminances = [(self.min_luminance, 0x10), (self.max_luminance, 0x08), (self.avg_luminance, 0x04)];
+        let mut flags = (self.persistence_flag as u8) << 6 | (self.primaries.is_some() as u8) << 5;
+        for &(luminance, mask) in luminances.iter() {
+            if luminance.is_some() {
+                flags |= mask;
+            }
+        }
+        let mut data = vec![flags];
+        if let Some(primaries) = self.primaries {
+            for primary in primaries.iter() {
+                for &coordinate in [primary.x, primary.y].iter() {
+                    let coded = encode(coordinate, CHROMATICITY_SCALE, -5_000_000, 5_000_000)? as i32;
+                    data.extend_from_slice(&coded.to_be_bytes());
+                }
+            }
+        }
+        for &(luminance, _) in luminances.iter() {
+            if let Some(luminance) = luminance {
+                data.extend_from_slice(&encode_u32(luminance, CONTENT_LUMINANCE_SCALE)?.to_be_bytes());
+            }
+        }
+        Ok(data)
+    }
+}
+
+/// The ambient viewing environment SEI message of ITU-T H.265
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AmbientViewingEnvironment {
+    // In lux
+    pub ambient_illuminance: f64,
+    pub ambient_light: Chromaticity,
+}
+
+impl AmbientViewingEnvironment {
+    pub const LEN: usize = 8;
+
+    // Parses the 8 byte payload of the SEI message, the body of an amve box or the value of the
+    // kCMFormatDescriptionExtension_AmbientViewingEnvironment extension
+    pub fn parse(data: &[u8]) -> Result<Self, HdrMetadataError> {
+        let mut reader = fixed_size_reader(data, Self::LEN)?;
+        Ok(AmbientViewingEnvironment {
+            ambient_illuminance: read_u32(&mut reader)? as f64 / ILLUMINANCE_SCALE,
+            ambient_light: Chromaticity::read(&mut reader)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 8], HdrMetadataError> {
+        let mut data = [0; 8];
+        // ambient_illuminance of 0 is forbidden
+        let illuminance = encode(self.ambient_illuminance, ILLUMINANCE_SCALE, 1, u32::MAX as i64)? as u32;
+        data[..4].copy_from_slice(&illuminance.to_be_bytes());
+        data[4..].copy_from_slice(&self.ambient_light.to_bytes()?);
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Display P3 primaries with a D65 white point, mastered on a 1000 cd/m² display
+    const MDCV: [u8; 24] = [
+        0x33, 0xC2, 0x86, 0xC4, 0x1D, 0x4C, 0x0B, 0xB8, 0x84, 0xD0, 0x3E, 0x80, 0x3D, 0x13, 0x40, 0x42, 0x00, 0x98, 0x96, 0x80, 0x00, 0x00, 0x00,
+        0x32,
+    ];
+
+    fn p3_primaries() -> [Chromaticity; 3] {
+        [Chromaticity::new(0.265, 0.690), Chromaticity::new(0.150, 0.060), Chromaticity::new(0.680, 0.320)]
+    }
+
+    #[test]
+    fn mastering_display_color_volume() {
+        let mdcv = MasteringDisplayColorVolume {
+            display_primaries: p3_primaries(),
+            white_point: Chromaticity::new(0.3127, 0.3290),
+            max_luminance: 1000.0,
+            min_luminance: 0.005,
+        };
+        assert_eq!(mdcv.to_bytes(), Ok(MDCV));
+        assert_eq!(MasteringDisplayColorVolume::parse(&MDCV), Ok(mdcv));
+        assert_eq!(MasteringDisplayColorVolume::parse(&MDCV[..23]), Err(HdrMetadataError::InvalidLength));
+        assert_eq!(MasteringDisplayColorVolume::parse(&[0; 25]), Err(HdrMetadataError::InvalidLength));
+        let out_of_range = MasteringDisplayColorVolume { white_point: Chromaticity::new(1.4, 0.3), ..mdcv };
+        assert_eq!(out_of_range.to_bytes(), Err(HdrMetadataError::ValueOutOfRange));
+        let negative = MasteringDisplayColorVolume { min_luminance: -0.001, ..mdcv };
+        assert_eq!(negative.to_bytes(), Err(HdrMetadataError::ValueOutOfRange));
+        let not_finite = MasteringDisplayColorVolume { max_luminance: f64::NAN, ..mdcv };
+        assert_eq!(not_finite.to_bytes(), Err(HdrMetadataError::ValueOutOfRange));
+    }
+
+    #[test]
+    fn content_light_level_info() {
+        let cll = ContentLightLevelInfo { max_content_light_level: 1000, max_pic_average_light_level: 400 };
+        assert_eq!(cll.to_bytes(), [0x03, 0xE8, 0x01, 0x90]);
+        assert_eq!(ContentLightLevelInfo::parse(&[0x03, 0xE8, 0x01, 0x90]), Ok(cll));
+        assert_eq!(ContentLightLevelInfo::parse(&[0x03, 0xE8, 0x01]), Err(HdrMetadataError::InvalidLength));
+    }
+
+    #[test]
+    fn ambient_viewing_environment() {
+        let ave = AmbientViewingEnvironment { ambient_illuminance: 314.0, ambient_light: Chromaticity::new(0.3127, 0.3290) };
+        let data = [0x00, 0x2F, 0xE9, 0xA0, 0x3D, 0x13, 0x40, 0x42];
+        assert_eq!(ave.to_bytes(), Ok(data));
+        assert_eq!(AmbientViewingEnvironment::parse(&data), Ok(ave));
+        assert_eq!(AmbientViewingEnvironment::parse(&data[..7]), Err(HdrMetadataError::InvalidLength));
+        // An illuminance of zero is forbidden, including values that round to zero
+        let zero = AmbientViewingEnvironment { ambient_illuminance: 0.0, ..ave };
+        assert_eq!(zero.to_bytes(), Err(HdrMetadataError::ValueOutOfRange));
+        let rounds_to_zero = AmbientViewingEnvironment { ambient_illuminance: 0.00004, ..ave };
+        assert_eq!(rounds_to_zero.to_bytes(), Err(HdrMetadataError::ValueOutOfRange));
+        let smallest = AmbientViewingEnvironment { ambient_illuminance: 0.0001, ..ave };
+        assert_eq!(smallest.to_bytes().map(|data| data[..4].to_vec()), Ok(vec![0, 0, 0, 1]));
+    }
+
+    #[test]
+    fn content_color_volume() {
+        let primaries = [Chromaticity::new(-0.5, 100.0), Chromaticity::new(0.680, 0.320), Chromaticity::new(0.150, 0.060)];
+        let ccv = ContentColorVolume { persistence_flag: true, primaries: Some(primaries), max_luminance: Some(50.0), ..Default::default() };
+        let data = [
+            0x68, 0xFF, 0xFF, 0x9E, 0x58, 0x00, 0x4C, 0x4B, 0x40, 0x00, 0x00, 0x84, 0xD0, 0x00, 0x00, 0x3E, 0x80, 0x00, 0x00, 0x1D, 0x4C, 0x00, 0x00,
+            0x0B, 0xB8, 0x1D, 0xCD, 0x65, 0x00,
+        ];
+        assert_eq!(ccv.to_bytes().as_deref(), Ok(&data[..]));
+        assert_eq!(ContentColorVolume::parse(&data), Ok(ccv));
+
+        for flags in 0..32u8 {
+            let ccv = ContentColorVolume {
+                persistence_flag: flags & 1 != 0,
+                primaries: if flags & 2 != 0 { Some(primaries) } else { None },
+                min_luminance: if flags & 4 != 0 { Some(0.0000001) } else { None },
+                max_luminance: if flags & 8 != 0 { Some(400.0) } else { None },
+                avg_luminance: if flags & 16 != 0 { Some(120.5) } else { None },
+            };
+            let data = ccv.to_bytes().unwrap();
+            let expected_flags = (flags & 1) << 6 | (flags & 2) << 4 | (flags & 4) << 2 | (flags & 8) | (flags & 16) >> 2;
+            assert_eq!(data[0], expected_flags);
+            let luminances = (flags >> 2).count_ones() as usize;
+            assert_eq!(data.len(), 1 + if flags & 2 != 0 { 24 } else { 0 } + 4 * luminances);
+            assert_eq!(ContentColorVolume::parse(&data), Ok(ccv));
+            if data.len() > 1 {
+                assert_eq!(ContentColorVolume::parse(&data[..data.len() - 1]), Err(HdrMetadataError::UnexpectedEnd));
+            }
+        }
+
+        assert_eq!(ContentColorVolume::parse(&[]), Err(HdrMetadataError::UnexpectedEnd));
+        assert_eq!(ContentColorVolume::parse(&[0x80]), Err(HdrMetadataError::CancelledContentColorVolume));
+        let below_range = [Chromaticity::new(-100.00002, 0.0), Chromaticity::default(), Chromaticity::default()];
+        let out_of_range = ContentColorVolume { primaries: Some(below_range), ..Default::default() };
+        assert_eq!(out_of_range.to_bytes(), Err(HdrMetadataError::ValueOutOfRange));
+        let negative = ContentColorVolume { avg_luminance: Some(-1.0), ..Default::default() };
+        assert_eq!(negative.to_bytes(), Err(HdrMetadataError::ValueOutOfRange));
+    }
+}
@@ -0,0 +1,1156 @@
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use core_foundation::{base::CFType, dictionary::CFDictionary, string::CFString};
+
+use crate::{
+    bitstream::{remove_emulation_prevention, BitReader, ByteReader},
+    color_description::ColorDescription,
+    hdr_metadata::{AmbientViewingEnvironment, ContentColorVolume, ContentLightLevelInfo, HdrMetadataError, MasteringDisplayColorVolume},
+    time::CMTime,
+    video_format_extensions::{ChromaLocation, CleanAperture, PixelAspectRatio, VideoFormatExtensions},
+};
+
+pub const HEVC_CONFIGURATION_ATOM: &str = "hvcC";
+
+pub const HEVC_NAL_UNIT_TYPE_VPS: u8 = 32;
+pub const HEVC_NAL_UNIT_TYPE_SPS: u8 = 33;
+pub const HEVC_NAL_UNIT_TYPE_PPS: u8 = 34;
+pub const HEVC_NAL_UNIT_TYPE_PREFIX_SEI: u8 = 39;
+pub const HEVC_NAL_UNIT_TYPE_SUFFIX_SEI: u8 = 40;
+
+pub const HEVC_SEI_MASTERING_DISPLAY_COLOUR_VOLUME: u32 = 137;
+pub const HEVC_SEI_CONTENT_LIGHT_LEVEL_INFO: u32 = 144;
+pub const HEVC_SEI_AMBIENT_VIEWING_ENVIRONMENT: u32 = 148;
+pub const HEVC_SEI_CONTENT_COLOUR_VOLUME: u32 = 149;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HevcConfigError {
+    UnexpectedEnd,
+    UnsupportedVersion,
+    InvalidNalUnitHeaderLength,
+    EmptyNalUnit,
+    UnexpectedNalUnitType,
+    MissingSequenceParameterSet,
+    TooManyArrays,
+    TooManyNalUnits,
+    NalUnitTooLong,
+    ValueOutOfRange,
+    InvalidSequenceParameterSet,
+}
+
+impl Display for HevcConfigError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            HevcConfigError::UnexpectedEnd => write!(f, "unexpected end of configuration record"),
+            HevcConfigError::UnsupportedVersion => write!(f, "unsupported configuration version"),
+            HevcConfigError::InvalidNalUnitHeaderLength => write!(f, "NAL unit header length must be 1, 2 or 4"),
+            HevcConfigError::EmptyNalUnit => write!(f, "NAL unit is empty"),
+            HevcConfigError::UnexpectedNalUnitType => write!(f, "NAL unit type does not match its array"),
+            HevcConfigError::MissingSequenceParameterSet => write!(f, "no sequence parameter set"),
+            HevcConfigError::TooManyArrays => write!(f, "too many NAL unit arrays"),
+            HevcConfigError::TooManyNalUnits => write!(f, "too many NAL units in an array"),
+            HevcConfigError::NalUnitTooLong => write!(f, "NAL unit is longer than 65535 bytes"),
+            HevcConfigError::ValueOutOfRange => write!(f, "field value does not fit in the configuration record"),
+            HevcConfigError::InvalidSequenceParameterSet => write!(f, "sequence parameter set is malformed"),
+        }
+    }
+}
+
+impl Error for HevcConfigError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HevcParameterSetError {
+    UnexpectedEnd,
+    UnexpectedNalUnitType,
+    InvalidValue,
+}
+
+impl Display for HevcParameterSetError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            HevcParameterSetError::UnexpectedEnd => write!(f, "unexpected end of NAL unit"),
+            HevcParameterSetError::UnexpectedNalUnitType => write!(f, "NAL unit is not of the expected type"),
+            HevcParameterSetError::InvalidValue => write!(f, "NAL unit has a value outside its allowed range"),
+        }
+    }
+}
+
+impl Error for HevcParameterSetError {}
+
+#[inline]
+fn nal_unit_type(nal_unit: &[u8]) -> Option<u8> {
+    nal_unit.first().map(|header| header >> 1 & 0x3F)
+}
+
+/// The NAL units of one type carried in an hvcC box
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HevcNalArray {
+    // Set when every NAL unit of this type is in the array and none are carried in the stream
+    pub array_completeness: bool,
+    pub nal_unit_type: u8,
+    pub nal_units: Vec<Vec<u8>>,
+}
+
+/// The HEVCDecoderConfigurationRecord carried in an hvcC box, as defined in ISO/IEC 14496-15
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HevcDecoderConfigurationRecord {
+    pub general_profile_space: u8,
+    pub general_tier_flag: bool,
+    pub general_profile_idc: u8,
+    pub general_profile_compatibility_flags: u32,
+    // The 48 bits of general_constraint_indicator_flags
+    pub general_constraint_indicator_flags: u64,
+    pub general_level_idc: u8,
+    pub min_spatial_segmentation_idc: u16,
+    pub parallelism_type: u8,
+    pub chroma_format: u8,
+    pub bit_depth_luma: u8,
+    pub bit_depth_chroma: u8,
+    // Average frame rate in frames per 256 seconds, or 0 if unspecified
+    pub avg_frame_rate: u16,
+    pub constant_frame_rate: u8,
+    pub num_temporal_layers: u8,
+    pub temporal_id_nested: bool,
+    pub nal_unit_header_length: u8,
+    pub arrays: Vec<HevcNalArray>,
+}
+
+#[inline]
+fn read_u8(reader: &mut ByteReader) -> Result<u8, HevcConfigError> {
+    reader.read_u8().ok_or(HevcConfigError::UnexpectedEnd)
+}
+
+#[inline]
+fn read_u16(reader: &mut ByteReader) -> Result<u16, HevcConfigError> {
+    reader.read_u16().ok_or(HevcConfigError::UnexpectedEnd)
+}
+
+#[inline]
+fn read_uint(reader: &mut ByteReader, len: usize) -> Result<u64, HevcConfigError> {
+    reader.read_uint(len).ok_or(HevcConfigError::UnexpectedEnd)
+}
+
+#[inline]
+fn check_nal_unit_header_length(nal_unit_header_length: u8) -> Result<(), HevcConfigError> {
+    match nal_unit_header_length {
+        1 | 2 | 4 => Ok(()),
+        _ => Err(HevcConfigError::InvalidNalUnitHeaderLength),
+    }
+}
+
+// Fails unless the value fits in the given number of bits
+#[inline]
+fn check_bits(value: u64, bits: u32) -> Result<(), HevcConfigError> {
+    if value >> bits == 0 {
+        Ok(())
+    } else {
+        Err(HevcConfigError::ValueOutOfRange)
+    }
+}
+
+impl HevcDecoderConfigurationRecord {
+    pub fn parse(data: &[u8]) -> Result<Self, HevcConfigError> {
+        let mut reader = ByteReader::new(data);
+        if read_u8(&mut reader)? != 1 {
+            return Err(HevcConfigError::UnsupportedVersion);
+        }
+        let byte = read_u8(&mut reader)?;
+        let general_profile_compatibility_flags = read_uint(&mut reader, 4)? as u32;
+        let general_constraint_indicator_flags = read_uint(&mut reader, 6)?;
+        let general_level_idc = read_u8(&mut reader)?;
+        let min_spatial_segmentation_idc = read_u16(&mut reader)? & 0x0FFF;
+        let parallelism_type = read_u8(&mut reader)? & 0x03;
+        let chroma_format = read_u8(&mut reader)? & 0x03;
+        let bit_depth_luma = (read_u8(&mut reader)? & 0x07) + 8;
+        let bit_depth_chroma = (read_u8(&mut reader)? & 0x07) + 8;
+        let avg_frame_rate = read_u16(&mut reader)?;
+        let flags = read_u8(&mut reader)?;
+        let nal_unit_header_length = (flags & 0x03) + 1;
+        check_nal_unit_header_length(nal_unit_header_length)?;
+        let array_count = read_u8(&mut reader)?;
+        let mut arrays = Vec::with_capacity(array_count as usize);
+        for _ in 0..array_count {
+            let array_header = read_u8(&mut reader)?;
+            let nal_unit_type = array_header & 0x3F;
+            let count = read_u16(&mut reader)?;
+            let mut nal_units = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let len = read_u16(&mut reader)? as usize;
+                let nal_unit = reader.read_bytes(len).ok_or(HevcConfigError::UnexpectedEnd)?;
+                match self::nal_unit_type(nal_unit) {
+                    None => return Err(HevcConfigError::EmptyNalUnit),
+                    Some(actual) if actual != nal_unit_type => return Err(HevcConfigError::UnexpectedNalUnitType),
+                    Some(_) => nal_units.push(nal_unit.to_vec()),
+                }
+            }
+            arrays.push(HevcNalArray { array_completeness: array_header & 0x80 != 0, nal_unit_type, nal_units });
+        }
+        Ok(HevcDecoderConfigurationRecord {
+            general_profile_space: byte >> 6,
+            general_tier_flag: byte & 0x20 != 0,
+            general_profile_idc: byte & 0x1F,
+            general_profile_compatibility_flags,
+            general_constraint_indicator_flags,
+            general_level_idc,
+            min_spatial_segmentation_idc,
+            parallelism_type,
+            chroma_format,
+            bit_depth_luma,
+            bit_depth_chroma,
+            avg_frame_rate,
+            constant_frame_rate: flags >> 6,
+            num_temporal_layers: flags >> 3 & 0x07,
+            temporal_id_nested: flags & 0x04 != 0,
+            nal_unit_header_length,
+            arrays,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, HevcConfigError> {
+        check_nal_unit_header_length(self.nal_unit_header_length)?;
+        check_bits(self.general_profile_space as u64, 2)?;
+        check_bits(self.general_profile_idc as u64, 5)?;
+        check_bits(self.general_constraint_indicator_flags, 48)?;
+        check_bits(self.min_spatial_segmentation_idc as u64, 12)?;
+        check_bits(self.parallelism_type as u64, 2)?;
+        check_bits(self.chroma_format as u64, 2)?;
+        if !(8..=15).contains(&self.bit_depth_luma) || !(8..=15).contains(&self.bit_depth_chroma) {
+            return Err(HevcConfigError::ValueOutOfRange);
+        }
+        check_bits(self.constant_frame_rate as u64, 2)?;
+        check_bits(self.num_temporal_layers as u64, 3)?;
+        let array_count = u8::try_from(self.arrays.len()).map_err(|_| HevcConfigError::TooManyArrays)?;
+        let mut data = Vec::new();
+        data.push(1);
+        data.push(self.general_profile_space << 6 | (self.general_tier_flag as u8) << 5 | self.general_profile_idc);
+        data.extend_from_slice(&self.general_profile_compatibility_flags.to_be_bytes());
+        data.extend_from_slice(&self.general_constraint_indicator_flags.to_be_bytes()[2..]);
+        data.push(self.general_level_idc);
+        data.extend_from_slice(&(0xF000 | self.min_spatial_segmentation_idc).to_be_bytes());
+        data.push(0xFC | self.parallelism_type);
+        data.push(0xFC | self.chroma_format);
+        data.push(0xF8 | (self.bit_depth_luma - 8));
+        data.push(0xF8 | (self.bit_depth_chroma - 8));
+        data.extend_from_slice(&self.avg_frame_rate.to_be_bytes());
+        data.push(
+            self.constant_frame_rate << 6 | self.num_temporal_layers << 3 | (self.temporal_id_nested as u8) << 2 | (self.nal_unit_header_length - 1),
+        );
+        data.push(array_count);
+        for array in &self.arrays {
+            check_bits(array.nal_unit_type as u64, 6)?;
+            let count = u16::try_from(array.nal_units.len()).map_err(|_| HevcConfigError::TooManyNalUnits)?;
+            data.push((array.array_completeness as u8) << 7 | array.nal_unit_type);
+            data.extend_from_slice(&count.to_be_bytes());
+            for nal_unit in &array.nal_units {
+                match nal_unit_type(nal_unit) {
+                    None => return Err(HevcConfigError::EmptyNalUnit),
+                    Some(actual) if actual != array.nal_unit_type => return Err(HevcConfigError::UnexpectedNalUnitType),
+                    Some(_) => {}
+                }
+                let len = u16::try_from(nal_unit.len()).map_err(|_| HevcConfigError::NalUnitTooLong)?;
+                data.extend_from_slice(&len.to_be_bytes());
+                data.extend_from_slice(nal_unit);
+            }
+        }
+        Ok(data)
+    }
+
+    // The NAL units of the given type, in the order they appear in the record
+    pub fn nal_units(&self, nal_unit_type: u8) -> impl Iterator<Item = &[u8]> {
+        self.arrays.iter().filter(move |array| array.nal_unit_type == nal_unit_type).flat_map(|array| array.nal_units.iter().map(Vec::as_slice))
+    }
+
+    // Builds a record from parameter set NAL units, as passed to CMVideoFormatDescription::from_hevc_parameter_sets.
+    // NAL units are grouped into complete arrays by type in order of first appearance, and the profile, tier, level,
+    // chroma format, bit depths and temporal layering are taken from the first sequence parameter set and the
+    // parallelism type from the first picture parameter set. The NAL unit list does not carry
+    // min_spatial_segmentation_idc, avg_frame_rate, constant_frame_rate or array_completeness, so these are 0, 0, 0
+    // and set
+    pub fn from_parameter_sets(parameter_sets: &[&[u8]], nal_unit_header_length: i32) -> Result<Self, HevcConfigError> {
+        let nal_unit_header_length = u8::try_from(nal_unit_header_length).map_err(|_| HevcConfigError::InvalidNalUnitHeaderLength)?;
+        check_nal_unit_header_length(nal_unit_header_length)?;
+        let mut arrays: Vec<HevcNalArray> = Vec::new();
+        for parameter_set in parameter_sets {
+            let nal_unit_type = nal_unit_type(parameter_set).ok_or(HevcConfigError::EmptyNalUnit)?;
+            match arrays.iter_mut().find(|array| array.nal_unit_type == nal_unit_type) {
+                Some(array) => array.nal_units.push(parameter_set.to_vec()),
+                None => arrays.push(HevcNalArray { array_completeness: true, nal_unit_type, nal_units: vec![parameter_set.to_vec()] }),
+            }
+        }
+        let sps = arrays
+            .iter()
+            .find(|array| array.nal_unit_type == HEVC_NAL_UNIT_TYPE_SPS)
+            .and_then(|array| array.nal_units.first())
+            .ok_or(HevcConfigError::MissingSequenceParameterSet)?;
+        let sps = HevcSequenceParameterSet::parse(sps).map_err(|_| HevcConfigError::InvalidSequenceParameterSet)?;
+        let profile_tier_level = sps.profile_tier_level;
+        let pps = arrays.iter().find(|array| array.nal_unit_type == HEVC_NAL_UNIT_TYPE_PPS).and_then(|array| array.nal_units.first());
+        let parallelism_type = match pps.map(|pps| HevcPictureParameterSet::parse(pps)) {
+            Some(Ok(pps)) => pps.parallelism_type(),
+            _ => 0,
+        };
+        Ok(HevcDecoderConfigurationRecord {
+            general_profile_space: profile_tier_level.general_profile_space,
+            general_tier_flag: profile_tier_level.general_tier_flag,
+            general_profile_idc: profile_tier_level.general_profile_idc,
+            general_profile_compatibility_flags: profile_tier_level.general_profile_compatibility_flags,
+            general_constraint_indicator_flags: profile_tier_level.general_constraint_indicator_flags,
+            general_level_idc: profile_tier_level.general_level_idc,
+            min_spatial_segmentation_idc: 0,
+            parallelism_type,
+            chroma_format: sps.chroma_format_idc,
+            bit_depth_luma: sps.bit_depth_luma,
+            bit_depth_chroma: sps.bit_depth_chroma,
+            avg_frame_rate: 0,
+            constant_frame_rate: 0,
+            num_temporal_layers: sps.max_sub_layers,
+            temporal_id_nested: sps.temporal_id_nesting_flag,
+            nal_unit_header_length,
+            arrays,
+        })
+    }
+
+    // Every NAL unit in the record in array order, and the NAL unit header length, as taken by
+    // CMVideoFormatDescription::from_hevc_parameter_sets; see from_parameter_sets for the fields this drops
+    pub fn to_parameter_sets(&self) -> (Vec<&[u8]>, i32) {
+        let parameter_sets = self.arrays.iter().flat_map(|array| array.nal_units.iter().map(Vec::as_slice)).collect();
+        (parameter_sets, self.nal_unit_header_length as i32)
+    }
+}
+
+#[inline]
+fn read_bits(reader: &mut BitReader, count: u32) -> Result<u32, HevcParameterSetError> {
+    reader.read_bits(count).ok_or(HevcParameterSetError::UnexpectedEnd)
+}
+
+#[inline]
+fn read_flag(reader: &mut BitReader) -> Result<bool, HevcParameterSetError> {
+    reader.read_bit().ok_or(HevcParameterSetError::UnexpectedEnd)
+}
+
+#[inline]
+fn read_se(reader: &mut BitReader) -> Result<i32, HevcParameterSetError> {
+    reader.read_se().ok_or(HevcParameterSetError::UnexpectedEnd)
+}
+
+#[inline]
+fn skip_bits(reader: &mut BitReader, count: usize) -> Result<(), HevcParameterSetError> {
+    reader.skip_bits(count).ok_or(HevcParameterSetError::UnexpectedEnd)
+}
+
+// Reads ue(v) and fails unless the value is at most max
+#[inline]
+fn read_ue(reader: &mut BitReader, max: u32) -> Result<u32, HevcParameterSetError> {
+    match reader.read_ue() {
+        Some(value) if value <= max => Ok(value),
+        Some(_) => Err(HevcParameterSetError::InvalidValue),
+        None => Err(HevcParameterSetError::UnexpectedEnd),
+    }
+}
+
+// Strips the two byte NAL unit header after checking its type
+fn nal_unit_payload(nal_unit: &[u8], expected_nal_unit_type: u8) -> Result<Vec<u8>, HevcParameterSetError> {
+    match nal_unit_type(nal_unit) {
+        Some(actual) if actual != expected_nal_unit_type => Err(HevcParameterSetError::UnexpectedNalUnitType),
+        Some(_) if nal_unit.len() >= 2 => Ok(remove_emulation_prevention(&nal_unit[2..])),
+        _ => Err(HevcParameterSetError::UnexpectedEnd),
+    }
+}
+
+/// The general profile, tier and level of an HEVC video or sequence parameter set
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HevcProfileTierLevel {
+    pub general_profile_space: u8,
+    pub general_tier_flag: bool,
+    pub general_profile_idc: u8,
+    pub general_profile_compatibility_flags: u32,
+    // The 48 bits starting with general_progressive_source_flag
+    pub general_constraint_indicator_flags: u64,
+    pub general_level_idc: u8,
+}
+
+impl HevcProfileTierLevel {
+    // Reads profile_tier_level(1, max_sub_layers_minus1), skipping the sub-layer information
+    fn parse(reader: &mut BitReader, max_sub_layers_minus1: usize) -> Result<Self, HevcParameterSetError> {
+        let profile_tier_level = HevcProfileTierLevel {
+            general_profile_space: read_bits(reader, 2)? as u8,
+            general_tier_flag: read_flag(reader)?,
+            general_profile_idc: read_bits(reader, 5)? as u8,
+            general_profile_compatibility_flags: read_bits(reader, 32)?,
+            general_constraint_indicator_flags: (read_bits(reader, 32)? as u64) << 16 | read_bits(reader, 16)? as u64,
+            general_level_idc: read_bits(reader, 8)? as u8,
+        };
+        let mut sub_layer_flags = [(false, false); 7];
+        for flags in sub_layer_flags.iter_mut().take(max_sub_layers_minus1) {
+            *flags = (read_flag(reader)?, read_flag(reader)?);
+        }
+        if max_sub_layers_minus1 > 0 {
+            skip_bits(reader, 2 * (8 - max_sub_layers_minus1))?;
+        }
+        for &(profile_present, level_present) in sub_layer_flags.iter().take(max_sub_layers_minus1) {
+            if profile_present {
+                skip_bits(reader, 88)?;
+            }
+            if level_present {
+                skip_bits(reader, 8)?;
+            }
+        }
+        Ok(profile_tier_level)
+    }
+}
+
+/// An HEVC video parameter set, as defined in ITU-T H.265 7.3.2.1, up to the timing information
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HevcVideoParameterSet {
+    pub video_parameter_set_id: u8,
+    pub max_layers: u8,
+    pub max_sub_layers: u8,
+    pub temporal_id_nesting_flag: bool,
+    pub profile_tier_level: HevcProfileTierLevel,
+    // num_units_in_tick and time_scale
+    pub timing_info: Option<(u32, u32)>,
+}
+
+impl HevcVideoParameterSet {
+    // Parses a video parameter set NAL unit, starting with its header
+    pub fn parse(nal_unit: &[u8]) -> Result<Self, HevcParameterSetError> {
+        let rbsp = nal_unit_payload(nal_unit, HEVC_NAL_UNIT_TYPE_VPS)?;
+        let reader = &mut BitReader::new(&rbsp);
+        let video_parameter_set_id = read_bits(reader, 4)? as u8;
+        skip_bits(reader, 2)?;
+        let max_layers = read_bits(reader, 6)? as u8 + 1;
+        let max_sub_layers_minus1 = read_bits(reader, 3)? as usize;
+        if max_sub_layers_minus1 > 6 {
+            return Err(HevcParameterSetError::InvalidValue);
+        }
+        let temporal_id_nesting_flag = read_flag(reader)?;
+        skip_bits(reader, 16)?;
+        let profile_tier_level = HevcProfileTierLevel::parse(reader, max_sub_layers_minus1)?;
+        let first_sub_layer = if read_flag(reader)? { 0 } else { max_sub_layers_minus1 };
+        for _ in first_sub_layer..=max_sub_layers_minus1 {
+            read_ue(reader, u32::MAX)?;
+            read_ue(reader, u32::MAX)?;
+            read_ue(reader, u32::MAX)?;
+        }
+        let max_layer_id = read_bits(reader, 6)? as usize;
+        let num_layer_sets = read_ue(reader, 1023)? as usize + 1;
+        skip_bits(reader, (num_layer_sets - 1) * (max_layer_id + 1))?;
+        let timing_info = if read_flag(reader)? { Some((read_bits(reader, 32)?, read_bits(reader, 32)?)) } else { None };
+        Ok(HevcVideoParameterSet {
+            video_parameter_set_id,
+            max_layers,
+            max_sub_layers: max_sub_layers_minus1 as u8 + 1,
+            temporal_id_nesting_flag,
+            profile_tier_level,
+            timing_info,
+        })
+    }
+}
+
+/// The video usability information of an HEVC sequence parameter set, up to the timing information
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HevcVuiParameters {
+    pub sample_aspect_ratio: Option<(u16, u16)>,
+    pub overscan_appropriate: Option<bool>,
+    pub video_format: u8,
+    pub video_full_range_flag: bool,
+    // colour_primaries, transfer_characteristics and matrix_coefficients as ITU-T H.273 code points
+    pub colour_description: Option<(u8, u8, u8)>,
+    pub chroma_sample_loc_type: Option<(u32, u32)>,
+    pub field_seq_flag: bool,
+    // def_disp_win_left_offset, def_disp_win_right_offset, def_disp_win_top_offset and def_disp_win_bottom_offset
+    pub default_display_window: Option<(u32, u32, u32, u32)>,
+    // num_units_in_tick and time_scale
+    pub timing_info: Option<(u32, u32)>,
+}
+
+// The sample aspect ratios of aspect_ratio_idc 1 to 16
+const SAMPLE_ASPECT_RATIOS: [(u16, u16); 16] = [
+    (1, 1),
+    (12, 11),
+    (10, 11),
+    (16, 11),
+    (40, 33),
+    (24, 11),
+    (20, 11),
+    (32, 11),
+    (80, 33),
+    (18, 11),
+    (15, 11),
+    (64, 33),
+    (160, 99),
+    (4, 3),
+    (3, 2),
+    (2, 1),
+];
+
+impl HevcVuiParameters {
+    fn parse(reader: &mut BitReader) -> Result<Self, HevcParameterSetError> {
+        let mut vui = HevcVuiParameters { video_format: 5, ..Default::default() };
+        if read_flag(reader)? {
+            let aspect_ratio_idc = read_bits(reader, 8)?;
+            vui.sample_aspect_ratio = match aspect_ratio_idc {
+                1..=16 => Some(SAMPLE_ASPECT_RATIOS[aspect_ratio_idc as usize - 1]),
+                255 => Some((read_bits(reader, 16)? as u16, read_bits(reader, 16)? as u16)),
+                _ => None,
+            };
+        }
+        if read_flag(reader)? {
+            vui.overscan_appropriate = Some(read_flag(reader)?);
+        }
+        if read_flag(reader)? {
+            vui.video_format = read_bits(reader, 3)? as u8;
+            vui.video_full_range_flag = read_flag(reader)?;
+            if read_flag(reader)? {
+                vui.colour_description = Some((read_bits(reader, 8)? as u8, read_bits(reader, 8)? as u8, read_bits(reader, 8)? as u8));
+            }
+        }
+        if read_flag(reader)? {
+            vui.chroma_sample_loc_type = Some((read_ue(reader, 5)?, read_ue(reader, 5)?));
+        }
+        read_flag(reader)?;
+        vui.field_seq_flag = read_flag(reader)?;
+        read_flag(reader)?;
+        if read_flag(reader)? {
+            let max = u32::MAX;
+            vui.default_display_window = Some((read_ue(reader, max)?, read_ue(reader, max)?, read_ue(reader, max)?, read_ue(reader, max)?));
+        }
+        if read_flag(reader)? {
+            vui.timing_info = Some((read_bits(reader, 32)?, read_bits(reader, 32)?));
+        }
+        Ok(vui)
+    }
+}
+
+fn skip_scaling_list_data(reader: &mut BitReader) -> Result<(), HevcParameterSetError> {
+    for size_id in 0..4 {
+        let step = if size_id == 3 { 3 } else { 1 };
+        for _ in (0..6).step_by(step) {
+            if !read_flag(reader)? {
+                read_ue(reader, 5)?;
+                continue;
+            }
+            let coef_num = 64.min(1 << (4 + (size_id << 1)));
+            if size_id > 1 {
+                read_se(reader)?;
+            }
+            for _ in 0..coef_num {
+                read_se(reader)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Skips st_ref_pic_set(index), returning its NumDeltaPocs
+fn skip_short_term_ref_pic_set(reader: &mut BitReader, index: usize, num_delta_pocs: &[u32]) -> Result<u32, HevcParameterSetError> {
+    if index != 0 && read_flag(reader)? {
+        read_flag(reader)?;
+        read_ue(reader, 32767)?;
+        let mut count = 0;
+        for _ in 0..=num_delta_pocs[index - 1] {
+            let used_by_curr_pic = read_flag(reader)?;
+            if used_by_curr_pic || read_flag(reader)? {
+                count += 1;
+            }
+        }
+        return Ok(count);
+    }
+    let num_negative_pics = read_ue(reader, 16)?;
+    let num_positive_pics = read_ue(reader, 16)?;
+    for _ in 0..num_negative_pics + num_positive_pics {
+        read_ue(reader, 32767)?;
+        read_flag(reader)?;
+    }
+    Ok(num_negative_pics + num_positive_pics)
+}
+
+/// An HEVC sequence parameter set, as defined in ITU-T H.265 7.3.2.2, up to the VUI timing information
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HevcSequenceParameterSet {
+    pub video_parameter_set_id: u8,
+    pub max_sub_layers: u8,
+    pub temporal_id_nesting_flag: bool,
+    pub profile_tier_level: HevcProfileTierLevel,
+    pub seq_parameter_set_id: u8,
+    pub chroma_format_idc: u8,
+    pub separate_colour_plane_flag: bool,
+    pub pic_width_in_luma_samples: u32,
+    pub pic_height_in_luma_samples: u32,
+    // conf_win_left_offset, conf_win_right_offset, conf_win_top_offset and conf_win_bottom_offset
+    pub conformance_window: Option<(u32, u32, u32, u32)>,
+    pub bit_depth_luma: u8,
+    pub bit_depth_chroma: u8,
+    pub log2_max_pic_order_cnt_lsb: u8,
+    pub vui_parameters: Option<HevcVuiParameters>,
+}
+
+impl HevcSequenceParameterSet {
+    // Parses a sequence parameter set NAL unit, starting with its header
+    pub fn parse(nal_unit: &[u8]) -> Result<Self, HevcParameterSetError> {
+        let rbsp = nal_unit_payload(nal_unit, HEVC_NAL_UNIT_TYPE_SPS)?;
+        let reader = &mut BitReader::new(&rbsp);
+        let video_parameter_set_id = read_bits(reader, 4)? as u8;
+        let max_sub_layers_minus1 = read_bits(reader, 3)? as usize;
+        if max_sub_layers_minus1 > 6 {
+            return Err(HevcParameterSetError::InvalidValue);
+        }
+        let mut sps = HevcSequenceParameterSet {
+            video_parameter_set_id,
+            max_sub_layers: max_sub_layers_minus1 as u8 + 1,
+            temporal_id_nesting_flag: read_flag(reader)?,
+            profile_tier_level: HevcProfileTierLevel::parse(reader, max_sub_layers_minus1)?,
+            seq_parameter_set_id: read_ue(reader, 15)? as u8,
+            chroma_format_idc: read_ue(reader, 3)? as u8,
+            ..Default::default()
+        };
+        if sps.chroma_format_idc == 3 {
+            sps.separate_colour_plane_flag = read_flag(reader)?;
+        }
+        sps.pic_width_in_luma_samples = read_ue(reader, u32::MAX)?;
+        sps.pic_height_in_luma_samples = read_ue(reader, u32::MAX)?;
+        if read_flag(reader)? {
+            let max = u32::MAX;
+            sps.conformance_window = Some((read_ue(reader, max)?, read_ue(reader, max)?, read_ue(reader, max)?, read_ue(reader, max)?));
+        }
+        sps.bit_depth_luma = read_ue(reader, 8)? as u8 + 8;
+        sps.bit_depth_chroma = read_ue(reader, 8)? as u8 + 8;
+        sps.log2_max_pic_order_cnt_lsb = read_ue(reader, 12)? as u8 + 4;
+        let first_sub_layer = if read_flag(reader)? { 0 } else { max_sub_layers_minus1 };
+        for _ in first_sub_layer..=max_sub_layers_minus1 {
+            read_ue(reader, u32::MAX)?;
+            read_ue(reader, u32::MAX)?;
+            read_ue(reader, u32::MAX)?;
+        }
+        for _ in 0..6 {
+            read_ue(reader, u32::MAX)?;
+        }
+        if read_flag(reader)? && read_flag(reader)? {
+            skip_scaling_list_data(reader)?;
+        }
+        read_flag(reader)?;
+        read_flag(reader)?;
+        if read_flag(reader)? {
+            skip_bits(reader, 8)?;
+            read_ue(reader, u32::MAX)?;
+            read_ue(reader, u32::MAX)?;
+            read_flag(reader)?;
+        }
+        let num_short_term_ref_pic_sets = read_ue(reader, 64)? as usize;
+        let mut num_delta_pocs = Vec::with_capacity(num_short_term_ref_pic_sets);
+        for index in 0..num_short_term_ref_pic_sets {
+            let count = skip_short_term_ref_pic_set(reader, index, &num_delta_pocs)?;
+            num_delta_pocs.push(count);
+        }
+        if read_flag(reader)? {
+            for _ in 0..read_ue(reader, 32)? {
+                skip_bits(reader, sps.log2_max_pic_order_cnt_lsb as usize + 1)?;
+            }
+        }
+        read_flag(reader)?;
+        read_flag(reader)?;
+        if read_flag(reader)? {
+            sps.vui_parameters = Some(HevcVuiParameters::parse(reader)?);
+        }
+        Ok(sps)
+    }
+
+    // The horizontal and vertical distance between chroma samples, in luma samples
+    fn chroma_subsampling(&self) -> (u64, u64) {
+        let chroma_array_type = if self.separate_colour_plane_flag { 0 } else { self.chroma_format_idc };
+        match chroma_array_type {
+            1 => (2, 2),
+            2 => (2, 1),
+            _ => (1, 1),
+        }
+    }
+
+    // The left, right, top and bottom conformance window offsets in luma samples
+    pub fn cropping(&self) -> (u64, u64, u64, u64) {
+        let (left, right, top, bottom) = match self.conformance_window {
+            Some(offsets) => offsets,
+            None => return (0, 0, 0, 0),
+        };
+        let (sub_width, sub_height) = self.chroma_subsampling();
+        (left as u64 * sub_width, right as u64 * sub_width, top as u64 * sub_height, bottom as u64 * sub_height)
+    }
+
+    // The width and height of the picture cropped to the conformance window, as reported by
+    // CMVideoFormatDescription::get_dimensions for a description created from this parameter set
+    pub fn dimensions(&self) -> Result<(i32, i32), HevcParameterSetError> {
+        let (left, right, top, bottom) = self.cropping();
+        let width = (self.pic_width_in_luma_samples as u64).checked_sub(left + right).and_then(|width| i32::try_from(width).ok());
+        let height = (self.pic_height_in_luma_samples as u64).checked_sub(top + bottom).and_then(|height| i32::try_from(height).ok());
+        match (width, height) {
+            (Some(width), Some(height)) if width > 0 && height > 0 => Ok((width, height)),
+            _ => Err(HevcParameterSetError::InvalidValue),
+        }
+    }
+
+    // The clean aperture of the conformance window within the decoded picture, or None if there is no conformance
+    // window
+    pub fn clean_aperture(&self) -> Option<CleanAperture> {
+        self.conformance_window?;
+        let (left, right, top, bottom) = self.cropping();
+        CleanAperture::from_cropping(self.pic_width_in_luma_samples as u64, self.pic_height_in_luma_samples as u64, left, right, top, bottom)
+    }
+
+    #[inline]
+    pub fn sample_aspect_ratio(&self) -> Option<(u16, u16)> {
+        self.vui_parameters.and_then(|vui| vui.sample_aspect_ratio).filter(|&(horizontal, vertical)| horizontal != 0 && vertical != 0)
+    }
+
+    // The duration of a picture from the VUI timing information
+    pub fn frame_duration(&self) -> Option<CMTime> {
+        let (num_units_in_tick, time_scale) = self.vui_parameters?.timing_info?;
+        if num_units_in_tick == 0 || time_scale == 0 {
+            return None;
+        }
+        Some(CMTime::make(num_units_in_tick as i64, i32::try_from(time_scale).ok()?))
+    }
+}
+
+/// An HEVC picture parameter set, as defined in ITU-T H.265 7.3.2.3, up to entropy_coding_sync_enabled_flag
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HevcPictureParameterSet {
+    pub pic_parameter_set_id: u8,
+    pub seq_parameter_set_id: u8,
+    pub dependent_slice_segments_enabled_flag: bool,
+    pub output_flag_present_flag: bool,
+    pub num_extra_slice_header_bits: u8,
+    pub sign_data_hiding_enabled_flag: bool,
+    pub cabac_init_present_flag: bool,
+    pub num_ref_idx_l0_default_active: u8,
+    pub num_ref_idx_l1_default_active: u8,
+    pub init_qp: i32,
+    pub constrained_intra_pred_flag: bool,
+    pub transform_skip_enabled_flag: bool,
+    pub diff_cu_qp_delta_depth: Option<u32>,
+    pub cb_qp_offset: i32,
+    pub cr_qp_offset: i32,
+    pub slice_chroma_qp_offsets_present_flag: bool,
+    pub weighted_pred_flag: bool,
+    pub weighted_bipred_flag: bool,
+    pub transquant_bypass_enabled_flag: bool,
+    pub tiles_enabled_flag: bool,
+    pub entropy_coding_sync_enabled_flag: bool,
+}
+
+impl HevcPictureParameterSet {
+    // Parses a picture parameter set NAL unit, starting with its header
+    pub fn parse(nal_unit: &[u8]) -> Result<Self, HevcParameterSetError> {
+        let rbsp = nal_unit_payload(nal_unit, HEVC_NAL_UNIT_TYPE_PPS)?;
+        let reader = &mut BitReader::new(&rbsp);
+        Ok(HevcPictureParameterSet {
+            pic_parameter_set_id: read_ue(reader, 63)? as u8,
+            seq_parameter_set_id: read_ue(reader, 15)? as u8,
+            dependent_slice_segments_enabled_flag: read_flag(reader)?,
+            output_flag_present_flag: read_flag(reader)?,
+            num_extra_slice_header_bits: read_bits(reader, 3)? as u8,
+            sign_data_hiding_enabled_flag: read_flag(reader)?,
+            cabac_init_present_flag: read_flag(reader)?,
+            num_ref_idx_l0_default_active: read_ue(reader, 14)? as u8 + 1,
+            num_ref_idx_l1_default_active: read_ue(reader, 14)? as u8 + 1,
+            init_qp: read_se(reader)? + 26,
+            constrained_intra_pred_flag: read_flag(reader)?,
+            transform_skip_enabled_flag: read_flag(reader)?,
+            diff_cu_qp_delta_depth: if read_flag(reader)? { Some(read_ue(reader, u32::MAX)?) } else { None },
+            cb_qp_offset: read_se(reader)?,
+            cr_qp_offset: read_se(reader)?,
+            slice_chroma_qp_offsets_present_flag: read_flag(reader)?,
+            weighted_pred_flag: read_flag(reader)?,
+            weighted_bipred_flag: read_flag(reader)?,
+            transquant_bypass_enabled_flag: read_flag(reader)?,
+            tiles_enabled_flag: read_flag(reader)?,
+            entropy_coding_sync_enabled_flag: read_flag(reader)?,
+        })
+    }
+
+    // The parallelismType of an HEVCDecoderConfigurationRecord describing pictures that use only this parameter set
+    pub fn parallelism_type(&self) -> u8 {
+        match (self.tiles_enabled_flag, self.entropy_coding_sync_enabled_flag) {
+            (true, true) => 0,
+            (true, false) => 2,
+            (false, true) => 3,
+            (false, false) => 1,
+        }
+    }
+}
+
+/// An SEI message with its payload after emulation prevention bytes are removed
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HevcSeiMessage {
+    pub payload_type: u32,
+    pub payload: Vec<u8>,
+}
+
+// Reads a payload type or size coded as a run of 0xFF bytes followed by a final byte
+fn read_sei_value(reader: &mut ByteReader) -> Result<u32, HevcParameterSetError> {
+    let mut value = 0u32;
+    loop {
+        let byte = reader.read_u8().ok_or(HevcParameterSetError::UnexpectedEnd)?;
+        value = value.checked_add(byte as u32).ok_or(HevcParameterSetError::InvalidValue)?;
+        if byte != 0xFF {
+            return Ok(value);
+        }
+    }
+}
+
+// Parses the messages of a prefix or suffix SEI NAL unit, starting with its header
+pub fn parse_sei_messages(nal_unit: &[u8]) -> Result<Vec<HevcSeiMessage>, HevcParameterSetError> {
+    let rbsp = match nal_unit_type(nal_unit) {
+        Some(HEVC_NAL_UNIT_TYPE_PREFIX_SEI) => nal_unit_payload(nal_unit, HEVC_NAL_UNIT_TYPE_PREFIX_SEI)?,
+        Some(HEVC_NAL_UNIT_TYPE_SUFFIX_SEI) => nal_unit_payload(nal_unit, HEVC_NAL_UNIT_TYPE_SUFFIX_SEI)?,
+        Some(_) => return Err(HevcParameterSetError::UnexpectedNalUnitType),
+        None => return Err(HevcParameterSetError::UnexpectedEnd),
+    };
+    let mut reader = ByteReader::new(&rbsp);
+    let mut messages = Vec::new();
+    // The payloads end at the rbsp_trailing_bits byte
+    while reader.remaining() > 1 {
+        let payload_type = read_sei_value(&mut reader)?;
+        let payload_size = read_sei_value(&mut reader)? as usize;
+        let payload = reader.read_bytes(payload_size).ok_or(HevcParameterSetError::UnexpectedEnd)?;
+        messages.push(HevcSeiMessage { payload_type, payload: payload.to_vec() });
+    }
+    Ok(messages)
+}
+
+/// The static HDR metadata carried in mastering display colour volume, content light level information, ambient
+/// viewing environment and content colour volume SEI messages
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HevcHdrMetadata {
+    pub mastering_display_colour_volume: Option<MasteringDisplayColorVolume>,
+    pub content_light_level_info: Option<ContentLightLevelInfo>,
+    pub ambient_viewing_environment: Option<AmbientViewingEnvironment>,
+    pub content_colour_volume: Option<ContentColorVolume>,
+}
+
+impl HevcHdrMetadata {
+    // Collects the HDR metadata from SEI NAL units; later messages replace earlier ones and a cancelled content colour
+    // volume removes the previous one
+    pub fn from_sei_nal_units<'a, I: IntoIterator<Item = &'a [u8]>>(nal_units: I) -> Result<Self, HevcParameterSetError> {
+        let mut metadata = HevcHdrMetadata::default();
+        for nal_unit in nal_units {
+            for message in parse_sei_messages(nal_unit)? {
+                let payload = message.payload.as_slice();
+                let invalid = |_| HevcParameterSetError::InvalidValue;
+                match message.payload_type {
+                    HEVC_SEI_MASTERING_DISPLAY_COLOUR_VOLUME => {
+                        metadata.mastering_display_colour_volume = Some(MasteringDisplayColorVolume::parse(payload).map_err(invalid)?)
+                    }
+                    HEVC_SEI_CONTENT_LIGHT_LEVEL_INFO => {
+                        metadata.content_light_level_info = Some(ContentLightLevelInfo::parse(payload).map_err(invalid)?)
+                    }
+                    HEVC_SEI_AMBIENT_VIEWING_ENVIRONMENT => {
+                        metadata.ambient_viewing_environment = Some(AmbientViewingEnvironment::parse(payload).map_err(invalid)?)
+                    }
+                    HEVC_SEI_CONTENT_COLOUR_VOLUME => {
+                        metadata.content_colour_volume = match ContentColorVolume::parse(payload) {
+                            Ok(value) => Some(value),
+                            Err(HdrMetadataError::CancelledContentColorVolume) => None,
+                            Err(_) => return Err(HevcParameterSetError::InvalidValue),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(metadata)
+    }
+
+    // The SEI messages carrying the metadata, in the order of payload type
+    pub fn to_sei_messages(&self) -> Result<Vec<HevcSeiMessage>, HdrMetadataError> {
+        let mut messages = Vec::new();
+        if let Some(value) = self.mastering_display_colour_volume {
+            messages.push(HevcSeiMessage { payload_type: HEVC_SEI_MASTERING_DISPLAY_COLOUR_VOLUME, payload: value.to_bytes()?.to_vec() });
+        }
+        if let Some(value) = self.content_light_level_info {
+            messages.push(HevcSeiMessage { payload_type: HEVC_SEI_CONTENT_LIGHT_LEVEL_INFO, payload: value.to_bytes().to_vec() });
+        }
+        if let Some(value) = self.ambient_viewing_environment {
+            messages.push(HevcSeiMessage { payload_type: HEVC_SEI_AMBIENT_VIEWING_ENVIRONMENT, payload: value.to_bytes()?.to_vec() });
+        }
+        if let Some(value) = self.content_colour_volume {
+            messages.push(HevcSeiMessage { payload_type: HEVC_SEI_CONTENT_COLOUR_VOLUME, payload: value.to_bytes()? });
+        }
+        Ok(messages)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.mastering_display_colour_volume.is_none() &&
+            self.content_light_level_info.is_none() &&
+            self.ambient_viewing_environment.is_none() &&
+            self.content_colour_volume.is_none()
+    }
+}
+
+impl HevcDecoderConfigurationRecord {
+    // The extensions CoreMedia derives from the first sequence parameter set and the HDR SEI messages in the record,
+    // together with the record as an hvcC sample description extension atom
+    pub fn to_video_format_extensions(&self) -> Result<VideoFormatExtensions, HevcConfigError> {
+        let sps = self.nal_units(HEVC_NAL_UNIT_TYPE_SPS).next().ok_or(HevcConfigError::MissingSequenceParameterSet)?;
+        let sps = HevcSequenceParameterSet::parse(sps).map_err(|_| HevcConfigError::InvalidSequenceParameterSet)?;
+        let sei = self.nal_units(HEVC_NAL_UNIT_TYPE_PREFIX_SEI).chain(self.nal_units(HEVC_NAL_UNIT_TYPE_SUFFIX_SEI));
+        // SEI NAL units in the record are informative, so malformed ones are ignored
+        let hdr_metadata = HevcHdrMetadata::from_sei_nal_units(sei).unwrap_or_default();
+        let mut extensions = VideoFormatExtensions {
+            format_name: Some("HEVC".to_string()),
+            depth: Some(24),
+            clean_aperture: sps.clean_aperture(),
+            pixel_aspect_ratio: sps.sample_aspect_ratio().map(|(horizontal, vertical)| PixelAspectRatio::new(horizontal as u32, vertical as u32)),
+            mastering_display_color_volume: hdr_metadata.mastering_display_colour_volume,
+            content_light_level_info: hdr_metadata.content_light_level_info,
+            content_color_volume: hdr_metadata.content_colour_volume,
+            ambient_viewing_environment: hdr_metadata.ambient_viewing_environment,
+            ..Default::default()
+        };
+        extensions.set_atom(HEVC_CONFIGURATION_ATOM, &self.to_bytes()?);
+        if let Some(vui) = sps.vui_parameters {
+            extensions.field_count = Some(if vui.field_seq_flag { 2 } else { 1 });
+            extensions.full_range_video = Some(vui.video_full_range_flag);
+            if let Some((colour_primaries, transfer_characteristics, matrix_coefficients)) = vui.colour_description {
+                let color =
+                    ColorDescription::from_code_points(colour_primaries, transfer_characteristics, matrix_coefficients, vui.video_full_range_flag);
+                extensions.set_color_description(&color);
+            }
+            if let Some((top_field, bottom_field)) = vui.chroma_sample_loc_type {
+                extensions.chroma_location_top_field = ChromaLocation::from_chroma_sample_loc_type(top_field);
+                extensions.chroma_location_bottom_field = ChromaLocation::from_chroma_sample_loc_type(bottom_field);
+            }
+        }
+        Ok(extensions)
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl HevcDecoderConfigurationRecord {
+    // The dictionary form of to_video_format_extensions, for CMVideoFormatDescription::new with
+    // kCMVideoCodecType_HEVC and the sequence parameter set's dimensions
+    pub fn to_extensions(&self) -> Result<CFDictionary<CFString, CFType>, HevcConfigError> {
+        self.to_video_format_extensions()?.to_dictionary().map_err(|_| HevcConfigError::ValueOutOfRange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Main 10 profile, level 4.1, 1920x1080 coded as 1920x1088 with conf_win_bottom_offset 4, BT.2020 PQ and three
+    // short-term reference picture sets, the second predicted from the first
+    const VPS: &[u8] = &[
+        0x40, 0x01, 0x0C, 0x01, 0xFF, 0xFF, 0x02, 0x20, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03, 0x00, 0x00, 0x03, 0x00, 0x7B, 0x95, 0xC0,
+        0xC0, 0x00, 0x00, 0xFA, 0x40, 0x00, 0x17, 0x70, 0x14,
+    ];
+    const SPS: &[u8] = &[
+        0x42, 0x01, 0x01, 0x02, 0x20, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03, 0x00, 0x00, 0x03, 0x00, 0x7B, 0xA0, 0x03, 0xC0, 0x80, 0x11,
+        0x07, 0xCA, 0xD9, 0x65, 0x79, 0x24, 0x49, 0x88, 0xFA, 0xDA, 0x12, 0xD1, 0xE0, 0x2D, 0x42, 0x44, 0x02, 0x6D, 0x84, 0x00, 0x00, 0x0F, 0xA4,
+        0x00, 0x01, 0x77, 0x00, 0x20,
+    ];
+    const PPS: &[u8] = &[0x44, 0x01, 0xC1, 0x72, 0xB0, 0x73, 0x24];
+    // Prefix SEI with a mastering display colour volume, whose minimum luminance needs an emulation prevention byte,
+    // and a content light level of 1000 and 400 cd/m²
+    const SEI: &[u8] = &[
+        0x4E, 0x01, 0x89, 0x18, 0x33, 0xC2, 0x86, 0xC4, 0x1D, 0x4C, 0x0B, 0xB8, 0x84, 0xD0, 0x3E, 0x80, 0x3D, 0x13, 0x40, 0x42, 0x00, 0x98, 0x96,
+        0x80, 0x00, 0x00, 0x03, 0x00, 0x01, 0x90, 0x04, 0x03, 0xE8, 0x01, 0x90, 0x80,
+    ];
+
+    // The same stream with three temporal sub-layers, the first with its own profile and both with their own level
+    const SUB_LAYER_SPS: &[u8] = &[
+        0x42, 0x01, 0x05, 0x02, 0x20, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03, 0x00, 0x00, 0x03, 0x00, 0x7B, 0xD0, 0x00, 0x02, 0x20, 0x00,
+        0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03, 0x00, 0x00, 0x03, 0x00, 0x78, 0x78, 0xA0, 0x03, 0xC0, 0x80, 0x11, 0x07, 0xCA, 0xD9, 0x65, 0x72,
+        0xB9, 0x5E, 0x49, 0x12, 0x62, 0x3E, 0xB6, 0x84, 0xB4, 0x78, 0x0B, 0x50, 0x91, 0x00, 0x9B, 0x61, 0x00, 0x00, 0x03, 0x03, 0xE9, 0x00, 0x00,
+        0x5D, 0xC0, 0x08,
+    ];
+    // The same stream with explicit and predicted scaling lists
+    const SCALING_SPS: &[u8] = &[
+        0x42, 0x01, 0x01, 0x02, 0x20, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03, 0x00, 0x00, 0x03, 0x00, 0x7B, 0xA0, 0x03, 0xC0, 0x80, 0x11,
+        0x07, 0xCA, 0xD9, 0x65, 0x79, 0x24, 0x4B, 0xAE, 0xBA, 0xEB, 0x92, 0xAA, 0x4A, 0xB0, 0x82, 0xEB, 0xAE, 0xBA, 0xEB, 0xAE, 0xBA, 0xEB, 0xAE,
+        0xBA, 0xEB, 0xAE, 0xB9, 0x2A, 0xC2, 0x0B, 0xAE, 0xBA, 0xEB, 0xAE, 0xBA, 0xEB, 0xAE, 0xBA, 0xEB, 0xAE, 0xBA, 0xEE, 0x23, 0xEB, 0x68, 0x4B,
+        0x47, 0x80, 0xB5, 0x09, 0x10, 0x09, 0xB6, 0x10, 0x00, 0x00, 0x3E, 0x90, 0x00, 0x05, 0xDC, 0x00, 0x80,
+    ];
+    // Suffix SEI with an unknown content light level, coded as zeros and so needing an emulation prevention byte
+    const UNKNOWN_CLL_SEI: &[u8] = &[0x50, 0x01, 0x90, 0x04, 0x00, 0x00, 0x03, 0x00, 0x00, 0x80];
+
+    fn hvcc(arrays: &[(bool, u8, &[u8])]) -> Vec<u8> {
+        let mut data =
+            vec![0x01, 0x02, 0x20, 0x00, 0x00, 0x00, 0x90, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7B, 0xF0, 0x00, 0xFF, 0xFD, 0xFA, 0xFA, 0x00, 0x00, 0x0F];
+        data.push(arrays.len() as u8);
+        for &(array_completeness, nal_unit_type, nal_unit) in arrays {
+            data.push((array_completeness as u8) << 7 | nal_unit_type);
+            data.extend_from_slice(&[0x00, 0x01]);
+            data.extend_from_slice(&(nal_unit.len() as u16).to_be_bytes());
+            data.extend_from_slice(nal_unit);
+        }
+        data
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let data = hvcc(&[
+            (true, HEVC_NAL_UNIT_TYPE_VPS, VPS),
+            (true, HEVC_NAL_UNIT_TYPE_SPS, SPS),
+            (true, HEVC_NAL_UNIT_TYPE_PPS, PPS),
+            (false, HEVC_NAL_UNIT_TYPE_PREFIX_SEI, SEI),
+        ]);
+        let record = HevcDecoderConfigurationRecord::parse(&data).unwrap();
+        assert_eq!((record.general_profile_space, record.general_tier_flag, record.general_profile_idc), (0, false, 2));
+        assert_eq!(record.general_profile_compatibility_flags, 0x2000_0000);
+        assert_eq!(record.general_constraint_indicator_flags, 0x9000_0000_0000);
+        assert_eq!(record.general_level_idc, 123);
+        assert_eq!((record.parallelism_type, record.chroma_format, record.bit_depth_luma, record.bit_depth_chroma), (3, 1, 10, 10));
+        assert_eq!((record.num_temporal_layers, record.temporal_id_nested, record.nal_unit_header_length), (1, true, 4));
+        let types: Vec<u8> = record.arrays.iter().map(|array| array.nal_unit_type).collect();
+        assert_eq!(types, vec![HEVC_NAL_UNIT_TYPE_VPS, HEVC_NAL_UNIT_TYPE_SPS, HEVC_NAL_UNIT_TYPE_PPS, HEVC_NAL_UNIT_TYPE_PREFIX_SEI]);
+        assert_eq!(record.nal_units(HEVC_NAL_UNIT_TYPE_SPS).collect::<Vec<_>>(), vec![SPS]);
+        assert_eq!(record.to_bytes().unwrap(), data);
+
+        let (parameter_sets, nal_unit_header_length) = record.to_parameter_sets();
+        assert_eq!(parameter_sets, vec![VPS, SPS, PPS, SEI]);
+        assert_eq!(nal_unit_header_length, 4);
+        let mut rebuilt = HevcDecoderConfigurationRecord::from_parameter_sets(&parameter_sets, nal_unit_header_length).unwrap();
+        for (rebuilt, original) in rebuilt.arrays.iter().zip(&record.arrays) {
+            assert_eq!((rebuilt.nal_unit_type, &rebuilt.nal_units), (original.nal_unit_type, &original.nal_units));
+            assert!(rebuilt.array_completeness);
+        }
+        // Only the fields the NAL unit list does not carry differ
+        assert_eq!((rebuilt.min_spatial_segmentation_idc, rebuilt.avg_frame_rate, rebuilt.constant_frame_rate), (0, 0, 0));
+        rebuilt.arrays[3].array_completeness = false;
+        assert_eq!(rebuilt, record);
+        assert_eq!(rebuilt.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn record_errors() {
+        let data = hvcc(&[(true, HEVC_NAL_UNIT_TYPE_SPS, SPS)]);
+        assert_eq!(HevcDecoderConfigurationRecord::parse(&data[..data.len() - 1]), Err(HevcConfigError::UnexpectedEnd));
+        let data = hvcc(&[(true, HEVC_NAL_UNIT_TYPE_PPS, SPS)]);
+        assert_eq!(HevcDecoderConfigurationRecord::parse(&data), Err(HevcConfigError::UnexpectedNalUnitType));
+        assert_eq!(HevcDecoderConfigurationRecord::from_parameter_sets(&[VPS, PPS], 4), Err(HevcConfigError::MissingSequenceParameterSet));
+        assert_eq!(HevcDecoderConfigurationRecord::from_parameter_sets(&[SPS], 0), Err(HevcConfigError::InvalidNalUnitHeaderLength));
+    }
+
+    #[test]
+    fn video_parameter_set() {
+        let vps = HevcVideoParameterSet::parse(VPS).unwrap();
+        assert_eq!((vps.video_parameter_set_id, vps.max_layers, vps.max_sub_layers, vps.temporal_id_nesting_flag), (0, 1, 1, true));
+        assert_eq!((vps.profile_tier_level.general_profile_idc, vps.profile_tier_level.general_level_idc), (2, 123));
+        assert_eq!(vps.timing_info, Some((1001, 24000)));
+        assert_eq!(HevcVideoParameterSet::parse(SPS), Err(HevcParameterSetError::UnexpectedNalUnitType));
+    }
+
+    #[test]
+    fn sequence_parameter_set() {
+        let sps = HevcSequenceParameterSet::parse(SPS).unwrap();
+        let profile_tier_level = HevcProfileTierLevel {
+            general_profile_space: 0,
+            general_tier_flag: false,
+            general_profile_idc: 2,
+            general_profile_compatibility_flags: 0x2000_0000,
+            general_constraint_indicator_flags: 0x9000_0000_0000,
+            general_level_idc: 123,
+        };
+        assert_eq!(sps.profile_tier_level, profile_tier_level);
+        assert_eq!((sps.max_sub_layers, sps.chroma_format_idc, sps.bit_depth_luma, sps.bit_depth_chroma), (1, 1, 10, 10));
+        assert_eq!((sps.pic_width_in_luma_samples, sps.pic_height_in_luma_samples), (1920, 1088));
+        assert_eq!(sps.conformance_window, Some((0, 0, 0, 4)));
+        assert_eq!(sps.cropping(), (0, 0, 0, 8));
+        assert_eq!(sps.dimensions(), Ok((1920, 1080)));
+        let aperture = sps.clean_aperture().unwrap();
+        assert_eq!((aperture.width, aperture.height, aperture.horizontal_offset, aperture.vertical_offset), (1920.0, 1080.0, 0.0, -4.0));
+        assert_eq!(sps.log2_max_pic_order_cnt_lsb, 8);
+        // The VUI follows the short-term reference picture sets, so reading it checks they were skipped correctly
+        let vui = sps.vui_parameters.unwrap();
+        assert_eq!(vui.sample_aspect_ratio, Some((1, 1)));
+        assert_eq!((vui.video_format, vui.video_full_range_flag, vui.colour_description), (5, false, Some((9, 16, 9))));
+        assert_eq!(vui.chroma_sample_loc_type, Some((2, 2)));
+        assert!(!vui.field_seq_flag);
+        assert_eq!(vui.default_display_window, None);
+        assert_eq!(vui.timing_info, Some((1001, 24000)));
+        assert_eq!(sps.frame_duration(), Some(CMTime::make(1001, 24000)));
+
+        let sub_layer_sps = HevcSequenceParameterSet::parse(SUB_LAYER_SPS).unwrap();
+        assert_eq!(sub_layer_sps.max_sub_layers, 3);
+        assert_eq!(sub_layer_sps.profile_tier_level, profile_tier_level);
+        assert_eq!(HevcSequenceParameterSet { max_sub_layers: 1, ..sub_layer_sps }, sps);
+        assert_eq!(HevcSequenceParameterSet::parse(SCALING_SPS).unwrap(), sps);
+
+        assert_eq!(HevcSequenceParameterSet::parse(&SPS[..30]), Err(HevcParameterSetError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn picture_parameter_set() {
+        let pps = HevcPictureParameterSet::parse(PPS).unwrap();
+        assert_eq!((pps.pic_parameter_set_id, pps.seq_parameter_set_id), (0, 0));
+        assert!(pps.sign_data_hiding_enabled_flag && !pps.cabac_init_present_flag);
+        assert_eq!((pps.num_ref_idx_l0_default_active, pps.num_ref_idx_l1_default_active, pps.init_qp), (1, 1, 26));
+        assert_eq!(pps.diff_cu_qp_delta_depth, Some(1));
+        assert_eq!((pps.cb_qp_offset, pps.cr_qp_offset), (0, 0));
+        assert!(!pps.tiles_enabled_flag && pps.entropy_coding_sync_enabled_flag);
+        assert_eq!(pps.parallelism_type(), 3);
+    }
+
+    #[test]
+    fn hdr_sei_messages() {
+        let messages = parse_sei_messages(SEI).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].payload_type, HEVC_SEI_MASTERING_DISPLAY_COLOUR_VOLUME);
+        assert_eq!(&messages[0].payload[16..], &[0x00, 0x98, 0x96, 0x80, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(messages[1], HevcSeiMessage { payload_type: HEVC_SEI_CONTENT_LIGHT_LEVEL_INFO, payload: vec![0x03, 0xE8, 0x01, 0x90] });
+        let messages = parse_sei_messages(UNKNOWN_CLL_SEI).unwrap();
+        assert_eq!(messages, vec![HevcSeiMessage { payload_type: HEVC_SEI_CONTENT_LIGHT_LEVEL_INFO, payload: vec![0; 4] }]);
+        assert_eq!(parse_sei_messages(PPS), Err(HevcParameterSetError::UnexpectedNalUnitType));
+        assert_eq!(parse_sei_messages(&SEI[..20]), Err(HevcParameterSetError::UnexpectedEnd));
+
+        let metadata = HevcHdrMetadata::from_sei_nal_units(vec![SEI]).unwrap();
+        let mastering_display = metadata.mastering_display_colour_volume.unwrap();
+        let primaries: Vec<(f64, f64)> = mastering_display.display_primaries.iter().map(|primary| (primary.x, primary.y)).collect();
+        assert_eq!(primaries, vec![(0.265, 0.69), (0.15, 0.06), (0.68, 0.32)]);
+        assert_eq!((mastering_display.white_point.x, mastering_display.white_point.y), (0.3127, 0.329));
+        assert_eq!((mastering_display.max_luminance, mastering_display.min_luminance), (1000.0, 0.0001));
+        let content_light_level = ContentLightLevelInfo { max_content_light_level: 1000, max_pic_average_light_level: 400 };
+        assert_eq!(metadata.content_light_level_info, Some(content_light_level));
+        assert_eq!((metadata.ambient_viewing_environment, metadata.content_colour_volume), (None, None));
+        let messages = metadata.to_sei_messages().unwrap();
+        assert_eq!(messages, parse_sei_messages(SEI).unwrap());
+
+        // Later messages replace earlier ones
+        let metadata = HevcHdrMetadata::from_sei_nal_units(vec![SEI, UNKNOWN_CLL_SEI]).unwrap();
+        assert_eq!(metadata.content_light_level_info, Some(ContentLightLevelInfo::default()));
+        assert!(metadata.mastering_display_colour_volume.is_some());
+    }
+
+    #[test]
+    fn video_format_extensions() {
+        let data = hvcc(&[
+            (true, HEVC_NAL_UNIT_TYPE_VPS, VPS),
+            (true, HEVC_NAL_UNIT_TYPE_SPS, SPS),
+            (true, HEVC_NAL_UNIT_TYPE_PPS, PPS),
+            (false, HEVC_NAL_UNIT_TYPE_PREFIX_SEI, SEI),
+        ]);
+        let record = HevcDecoderConfigurationRecord::parse(&data).unwrap();
+        let extensions = record.to_video_format_extensions().unwrap();
+        assert_eq!(extensions.format_name.as_deref(), Some("HEVC"));
+        let aperture = extensions.clean_aperture.unwrap();
+        assert_eq!((aperture.width, aperture.height, aperture.horizontal_offset, aperture.vertical_offset), (1920.0, 1080.0, 0.0, -4.0));
+        // The aperture is placed within the coded picture
+        assert!(extensions.validate(1920, 1088).is_ok());
+        assert_eq!(extensions.field_count, Some(1));
+        assert_eq!(extensions.pixel_aspect_ratio, Some(PixelAspectRatio::new(1, 1)));
+        let color = extensions.color_description();
+        assert_eq!(color, ColorDescription::from_code_points(9, 16, 9, false));
+        assert_eq!(extensions.full_range_video, Some(false));
+        assert_eq!(extensions.chroma_location_top_field, Some(ChromaLocation::TopLeft));
+        assert_eq!(extensions.chroma_location_bottom_field, Some(ChromaLocation::TopLeft));
+        let metadata = HevcHdrMetadata::from_sei_nal_units(vec![SEI]).unwrap();
+        assert_eq!(extensions.mastering_display_color_volume, metadata.mastering_display_colour_volume);
+        assert_eq!(extensions.content_light_level_info, metadata.content_light_level_info);
+        assert_eq!(extensions.atom("hvcC"), Some(data.as_slice()));
+    }
+}
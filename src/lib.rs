@@ -1,16 +1,25 @@
 #![allow(non_snake_case, non_camel_case_types, non_upper_case_globals, improper_ctypes)]
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern crate block;
 #[macro_use]
 extern crate cfg_if;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern crate core_audio_types;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 #[macro_use]
 extern crate core_foundation;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern crate core_graphics2 as core_graphics;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern crate core_video;
 extern crate libc;
-#[cfg(feature = "objc")]
+#[cfg(feature = "num-rational")]
+extern crate num_rational;
+#[cfg(all(feature = "objc", any(target_os = "macos", target_os = "ios")))]
 extern crate objc2;
+#[cfg(feature = "serde")]
+extern crate serde;
 
 pub type OSType = u32;
 
@@ -18,17 +27,49 @@ pub type OSType = u32;
 #[cfg_attr(feature = "link", link(name = "CoreMedia", kind = "framework"))]
 extern "C" {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub mod attachment;
 #[cfg(target_os = "ios")]
 pub mod audio_clock;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub mod audio_device_clock;
+pub mod av1;
+pub mod avc;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub mod base;
+mod bitstream;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub mod block_buffer;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub mod buffer_queue;
+pub mod codec_string;
+pub mod color_description;
 pub mod format_description;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub mod format_description_bridge;
+pub mod frame_grid;
+pub mod hdr_metadata;
+pub mod hevc;
+pub mod loop_timeline;
+pub mod rtp;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub mod sample_buffer;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub mod sample_queue;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub mod sync;
 pub mod time;
+#[cfg(any(feature = "native-time", not(any(target_os = "macos", target_os = "ios"))))]
+mod time_native;
+pub mod time_mapping_timeline;
 pub mod time_range;
+pub mod time_range_index;
+pub mod time_range_set;
+#[cfg(any(feature = "native-time", not(any(target_os = "macos", target_os = "ios"))))]
+mod time_range_native;
+pub mod timecode;
+pub mod timescale;
+pub mod timestamp;
+pub mod transport_stream;
+pub mod video_format_extensions;
+pub mod vp9;
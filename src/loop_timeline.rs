@@ -0,0 +1,264 @@
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::{
+    time::{
+        div_round, kCMTimeRoundingMethod_RoundTowardNegativeInfinity, kCMTimeRoundingMethod_RoundTowardPositiveInfinity, CMTime, CMTimeEpoch,
+        CMTimeRoundingMethod,
+    },
+    time_range::{CMTimeMapping, CMTimeRange},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoopTimelineError {
+    InvalidRange,
+    InvalidLoopCount,
+    NonNumericTime,
+    OutOfRange,
+    Overflow,
+}
+
+impl Display for LoopTimelineError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            LoopTimelineError::InvalidRange => write!(f, "loop range must have a numeric start and a positive numeric duration"),
+            LoopTimelineError::InvalidLoopCount => write!(f, "loop count must be at least one"),
+            LoopTimelineError::NonNumericTime => write!(f, "time is not numeric"),
+            LoopTimelineError::OutOfRange => write!(f, "time is outside the timeline"),
+            LoopTimelineError::Overflow => write!(f, "time is out of range"),
+        }
+    }
+}
+
+impl Error for LoopTimelineError {}
+
+#[inline]
+fn is_computable(time: &CMTime) -> bool {
+    time.is_numeric() && time.timescale > 0
+}
+
+/// Maps elapsed playback time onto a range that repeats, tagging each repetition with its own epoch
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LoopTimeline {
+    range: CMTimeRange,
+    loop_count: Option<u64>,
+}
+
+impl LoopTimeline {
+    // Loops the range indefinitely; the first pass carries the epoch of the range's start
+    pub fn new(range: CMTimeRange) -> Result<Self, LoopTimelineError> {
+        if !range.is_valid() || !is_computable(&range.start) || !is_computable(&range.duration) || range.duration.value <= 0 {
+            return Err(LoopTimelineError::InvalidRange);
+        }
+        Ok(LoopTimeline { range, loop_count: None })
+    }
+
+    // Plays the range loop_count times and then stops
+    pub fn with_loop_count(range: CMTimeRange, loop_count: u64) -> Result<Self, LoopTimelineError> {
+        if loop_count == 0 {
+            return Err(LoopTimelineError::InvalidLoopCount);
+        }
+        let mut timeline = Self::new(range)?;
+        timeline.loop_count = Some(loop_count);
+        Ok(timeline)
+    }
+
+    #[inline]
+    pub fn range(&self) -> CMTimeRange {
+        self.range
+    }
+
+    #[inline]
+    pub fn loop_count(&self) -> Option<u64> {
+        self.loop_count
+    }
+
+    #[inline]
+    pub fn first_epoch(&self) -> CMTimeEpoch {
+        self.range.start.epoch
+    }
+
+    // The elapsed time at which playback ends, or None when looping indefinitely
+    pub fn duration(&self) -> Result<Option<CMTime>, LoopTimelineError> {
+        match self.loop_count {
+            Some(count) => {
+                let count = i64::try_from(count).map_err(|_| LoopTimelineError::Overflow)?;
+                self.repeated_duration(count).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    // The loop duration repeated count times, in the loop duration's timescale
+    fn repeated_duration(&self, count: i64) -> Result<CMTime, LoopTimelineError> {
+        let value = self.range.duration.value.checked_mul(count).ok_or(LoopTimelineError::Overflow)?;
+        Ok(CMTime { value, epoch: 0, ..self.range.duration })
+    }
+
+    #[inline]
+    fn is_past_end(&self, pass: i64) -> bool {
+        self.loop_count.is_some_and(|count| pass as u128 >= count as u128)
+    }
+
+    fn pass_of_epoch(&self, epoch: CMTimeEpoch) -> Result<i64, LoopTimelineError> {
+        let pass = epoch.checked_sub(self.first_epoch()).ok_or(LoopTimelineError::Overflow)?;
+        if pass < 0 || self.is_past_end(pass) {
+            return Err(LoopTimelineError::OutOfRange);
+        }
+        Ok(pass)
+    }
+
+    // The zero-based number of completed passes at the elapsed time, rounded with the given method
+    fn pass_at(&self, elapsed: CMTime, method: CMTimeRoundingMethod) -> Result<i64, LoopTimelineError> {
+        let duration = self.range.duration;
+        let num = elapsed.value as i128 * duration.timescale as i128;
+        let den = duration.value as i128 * elapsed.timescale as i128;
+        let (pass, _) = div_round(num, den, method);
+        i64::try_from(pass).map_err(|_| LoopTimelineError::Overflow)
+    }
+
+    // Elapsed time is not tagged with an epoch, so any epoch it carries is ignored
+    fn check_elapsed(&self, elapsed: CMTime) -> Result<CMTime, LoopTimelineError> {
+        if !is_computable(&elapsed) {
+            return Err(LoopTimelineError::NonNumericTime);
+        }
+        let mut elapsed = elapsed;
+        elapsed.epoch = 0;
+        if elapsed < CMTime::ZERO {
+            return Err(LoopTimelineError::OutOfRange);
+        }
+        if let Some(end) = self.duration()? {
+            if elapsed > end {
+                return Err(LoopTimelineError::OutOfRange);
+            }
+        }
+        Ok(elapsed)
+    }
+
+    fn epoch_of(&self, pass: i64) -> Result<CMTimeEpoch, LoopTimelineError> {
+        self.first_epoch().checked_add(pass).ok_or(LoopTimelineError::Overflow)
+    }
+
+    // Maps elapsed playback time, measured from the start of the first pass, to a time in the loop range tagged with the
+    // epoch of its pass; the end of a finite timeline maps to the end of the range in the last pass
+    pub fn loop_time(&self, elapsed: CMTime) -> Result<CMTime, LoopTimelineError> {
+        let elapsed = self.check_elapsed(elapsed)?;
+        let mut pass = self.pass_at(elapsed, kCMTimeRoundingMethod_RoundTowardNegativeInfinity)?;
+        if pass > 0 && self.is_past_end(pass) {
+            pass -= 1;
+        }
+        let offset = elapsed - self.repeated_duration(pass)?;
+        let mut start = self.range.start;
+        start.epoch = 0;
+        let mut time = start + offset;
+        if !time.is_numeric() {
+            return Err(LoopTimelineError::Overflow);
+        }
+        time.epoch = self.epoch_of(pass)?;
+        Ok(time)
+    }
+
+    // Converts a time in the loop range tagged with the epoch of its pass back to elapsed playback time
+    pub fn elapsed_time(&self, time: CMTime) -> Result<CMTime, LoopTimelineError> {
+        if !is_computable(&time) {
+            return Err(LoopTimelineError::NonNumericTime);
+        }
+        let pass = self.pass_of_epoch(time.epoch)?;
+        let (mut time, mut start) = (time, self.range.start);
+        time.epoch = 0;
+        start.epoch = 0;
+        let offset = time - start;
+        if offset < CMTime::ZERO || offset > self.range.duration {
+            return Err(LoopTimelineError::OutOfRange);
+        }
+        let elapsed = self.repeated_duration(pass)? + offset;
+        if !elapsed.is_numeric() {
+            return Err(LoopTimelineError::Overflow);
+        }
+        Ok(elapsed)
+    }
+
+    // The elapsed time at which the pass with the given epoch starts
+    pub fn boundary(&self, epoch: CMTimeEpoch) -> Result<CMTime, LoopTimelineError> {
+        let pass = self.pass_of_epoch(epoch)?;
+        self.repeated_duration(pass)
+    }
+
+    // The first elapsed time after the given one at which playback wraps back to the start of the range, or None if
+    // there are no more wraps
+    pub fn next_boundary(&self, elapsed: CMTime) -> Result<Option<CMTime>, LoopTimelineError> {
+        let elapsed = self.check_elapsed(elapsed)?;
+        let pass = self.pass_at(elapsed, kCMTimeRoundingMethod_RoundTowardNegativeInfinity)?;
+        if self.is_past_end(pass.saturating_add(1)) {
+            return Ok(None);
+        }
+        let next = pass.checked_add(1).ok_or(LoopTimelineError::Overflow)?;
+        self.repeated_duration(next).map(Some)
+    }
+
+    // The elapsed times within the range, with the start included and the end excluded, at which playback wraps back
+    // to the start of the loop range
+    pub fn boundaries(&self, elapsed_range: CMTimeRange) -> Result<Vec<CMTime>, LoopTimelineError> {
+        let (start, end) = self.elapsed_bounds(&elapsed_range)?;
+        let mut first = self.pass_at(start, kCMTimeRoundingMethod_RoundTowardPositiveInfinity)?.max(1);
+        let mut last = self.pass_at(end, kCMTimeRoundingMethod_RoundTowardPositiveInfinity)?;
+        if let Some(count) = self.loop_count {
+            last = last.min(i64::try_from(count).unwrap_or(i64::MAX));
+        }
+        let mut boundaries = Vec::new();
+        while first < last {
+            boundaries.push(self.repeated_duration(first)?);
+            first += 1;
+        }
+        Ok(boundaries)
+    }
+
+    // Splits the elapsed range at the loop boundaries, mapping each piece of the loop range, tagged with the epoch of its
+    // pass, to the elapsed times it plays at; the part of the range past the end of a finite timeline is left out
+    pub fn segments(&self, elapsed_range: CMTimeRange) -> Result<Vec<CMTimeMapping>, LoopTimelineError> {
+        let (start, mut end) = self.elapsed_bounds(&elapsed_range)?;
+        if let Some(duration) = self.duration()? {
+            end = end.minimum(duration);
+        }
+        let mut segments = Vec::new();
+        if start >= end {
+            return Ok(segments);
+        }
+        let mut cuts = self.boundaries(CMTimeRange { start, duration: end - start })?;
+        cuts.push(end);
+        let mut piece_start = start;
+        for cut in cuts {
+            if cut == piece_start {
+                continue;
+            }
+            // Elapsed time and loop time advance at the same rate, so each piece keeps its duration
+            let duration = cut - piece_start;
+            segments.push(CMTimeMapping {
+                source: CMTimeRange { start: self.loop_time(piece_start)?, duration },
+                target: CMTimeRange { start: piece_start, duration },
+            });
+            piece_start = cut;
+        }
+        Ok(segments)
+    }
+
+    fn elapsed_bounds(&self, elapsed_range: &CMTimeRange) -> Result<(CMTime, CMTime), LoopTimelineError> {
+        if !elapsed_range.is_valid() || !is_computable(&elapsed_range.start) || !is_computable(&elapsed_range.duration) {
+            return Err(LoopTimelineError::NonNumericTime);
+        }
+        let (mut start, mut duration) = (elapsed_range.start, elapsed_range.duration);
+        start.epoch = 0;
+        duration.epoch = 0;
+        let end = start + duration;
+        if !end.is_numeric() {
+            return Err(LoopTimelineError::Overflow);
+        }
+        if start < CMTime::ZERO || end < start {
+            return Err(LoopTimelineError::OutOfRange);
+        }
+        Ok((start, end))
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use crate::sync::CMClock;
+use crate::time::{div_round, kCMTimeFlags_Valid, kCMTimeRoundingMethod_RoundHalfAwayFromZero, CMTime, CMTimeConversionError, CMTimeScale};
+
+// Seconds between the NTP epoch (1900) and the Unix epoch (1970)
+pub const NTP_UNIX_EPOCH_OFFSET: u64 = 2_208_988_800;
+
+const NTP_FRACTION_SCALE: i128 = 1 << 32;
+const NANOS_PER_SEC: CMTimeScale = 1_000_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RtpTimeError {
+    InvalidClockRate,
+    MissingSenderReport,
+    NonNumericTime,
+    Overflow,
+}
+
+impl Display for RtpTimeError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            RtpTimeError::InvalidClockRate => write!(f, "clock rate must be positive"),
+            RtpTimeError::MissingSenderReport => write!(f, "no RTCP sender report has been received"),
+            RtpTimeError::NonNumericTime => write!(f, "time is not numeric"),
+            RtpTimeError::Overflow => write!(f, "time is out of range"),
+        }
+    }
+}
+
+impl Error for RtpTimeError {}
+
+impl From<CMTimeConversionError> for RtpTimeError {
+    fn from(error: CMTimeConversionError) -> Self {
+        match error {
+            CMTimeConversionError::Overflow => RtpTimeError::Overflow,
+            _ => RtpTimeError::NonNumericTime,
+        }
+    }
+}
+
+/// A 64-bit NTP timestamp: seconds since 1900 in the upper 32 bits and a binary fraction in the lower 32 bits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NtpTimestamp(pub u64);
+
+impl NtpTimestamp {
+    #[inline]
+    pub fn from_parts(seconds: u32, fraction: u32) -> Self {
+        NtpTimestamp((seconds as u64) << 32 | fraction as u64)
+    }
+
+    #[inline]
+    pub fn seconds(&self) -> u32 {
+        (self.0 >> 32) as u32
+    }
+
+    #[inline]
+    pub fn fraction(&self) -> u32 {
+        self.0 as u32
+    }
+
+    // The middle 32 bits, as carried in the LSR field of RTCP reception reports
+    #[inline]
+    pub fn compact(&self) -> u32 {
+        (self.0 >> 16) as u32
+    }
+
+    // Signed distance from other in 1/2^32 second units, assuming the timestamps are less than 68 years apart
+    #[inline]
+    pub fn delta(&self, other: NtpTimestamp) -> i64 {
+        self.0.wrapping_sub(other.0) as i64
+    }
+
+    // Seconds since 1900, rounded to nanoseconds
+    pub fn to_time(&self) -> CMTime {
+        let (value, _) = div_round(self.0 as i128 * NANOS_PER_SEC as i128, NTP_FRACTION_SCALE, kCMTimeRoundingMethod_RoundHalfAwayFromZero);
+        CMTime { value: value as i64, timescale: NANOS_PER_SEC, flags: kCMTimeFlags_Valid, epoch: 0 }
+    }
+
+    pub fn from_time(time: CMTime) -> Result<Self, CMTimeConversionError> {
+        time.check_numeric()?;
+        if time.value < 0 {
+            return Err(CMTimeConversionError::Negative);
+        }
+        let (value, _) = div_round(time.value as i128 * NTP_FRACTION_SCALE, time.timescale as i128, kCMTimeRoundingMethod_RoundHalfAwayFromZero);
+        if value > u64::MAX as i128 {
+            return Err(CMTimeConversionError::Overflow);
+        }
+        Ok(NtpTimestamp(value as u64))
+    }
+}
+
+/// Relates the sender's NTP wall clock to the local host clock; every stream of a session should use the same anchor for lip sync
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NtpClockAnchor {
+    pub ntp: NtpTimestamp,
+    pub host_time: CMTime,
+}
+
+impl NtpClockAnchor {
+    #[inline]
+    pub fn new(ntp: NtpTimestamp, host_time: CMTime) -> Self {
+        NtpClockAnchor { ntp, host_time }
+    }
+
+    // Anchors the NTP timestamp to a host time in mach absolute time units
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    #[inline]
+    pub fn from_system_units(ntp: NtpTimestamp, host_time: u64) -> Self {
+        Self::new(ntp, CMClock::make_host_time_from_system_units(host_time))
+    }
+
+    // Host time of the point that is num / den seconds after the NTP timestamp, in the anchor's host timescale
+    fn host_time_after(&self, ntp: NtpTimestamp, num: i128, den: i128) -> Result<CMTime, RtpTimeError> {
+        self.host_time.check_numeric()?;
+        let timescale = self.host_time.timescale as i128;
+        let num = (self.ntp_delta(ntp) as i128 * den + num * NTP_FRACTION_SCALE).checked_mul(timescale).ok_or(RtpTimeError::Overflow)?;
+        let (delta, _) = div_round(num, den * NTP_FRACTION_SCALE, kCMTimeRoundingMethod_RoundHalfAwayFromZero);
+        let value = i64::try_from(self.host_time.value as i128 + delta).map_err(|_| RtpTimeError::Overflow)?;
+        Ok(CMTime { value, flags: kCMTimeFlags_Valid, ..self.host_time })
+    }
+
+    #[inline]
+    fn ntp_delta(&self, ntp: NtpTimestamp) -> i64 {
+        ntp.delta(self.ntp)
+    }
+
+    #[inline]
+    pub fn host_time_from_ntp(&self, ntp: NtpTimestamp) -> Result<CMTime, RtpTimeError> {
+        self.host_time_after(ntp, 0, 1)
+    }
+
+    // Seconds from the anchor's host time to host_time, as num / den
+    fn seconds_since(&self, host_time: CMTime) -> Result<(i128, i128), RtpTimeError> {
+        self.host_time.check_numeric()?;
+        host_time.check_numeric()?;
+        let num = host_time.value as i128 * self.host_time.timescale as i128 - self.host_time.value as i128 * host_time.timescale as i128;
+        Ok((num, host_time.timescale as i128 * self.host_time.timescale as i128))
+    }
+
+    pub fn ntp_from_host_time(&self, host_time: CMTime) -> Result<NtpTimestamp, RtpTimeError> {
+        let (num, den) = self.seconds_since(host_time)?;
+        let (delta, _) = div_round(num * NTP_FRACTION_SCALE, den, kCMTimeRoundingMethod_RoundHalfAwayFromZero);
+        Ok(NtpTimestamp(self.ntp.0.wrapping_add(i64::try_from(delta).map_err(|_| RtpTimeError::Overflow)? as u64)))
+    }
+}
+
+/// Maps the 32-bit RTP timestamps of one stream to CMTime, undoing wraparound and anchoring to wall clock with RTCP sender reports
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RtpTimeMapper {
+    clock_rate: CMTimeScale,
+    last: Option<i64>,
+    // The NTP timestamp and extended RTP timestamp of the most recent sender report
+    sender_report: Option<(NtpTimestamp, i64)>,
+}
+
+impl RtpTimeMapper {
+    pub fn new(clock_rate: CMTimeScale) -> Result<Self, RtpTimeError> {
+        if clock_rate <= 0 {
+            return Err(RtpTimeError::InvalidClockRate);
+        }
+        Ok(RtpTimeMapper { clock_rate, last: None, sender_report: None })
+    }
+
+    #[inline]
+    pub fn clock_rate(&self) -> CMTimeScale {
+        self.clock_rate
+    }
+
+    #[inline]
+    pub fn sender_report(&self) -> Option<(NtpTimestamp, i64)> {
+        self.sender_report
+    }
+
+    // Extends the timestamp to 64 bits, choosing the value nearest to the previous one so wraparound in either direction is undone
+    pub fn unwrap(&mut self, rtp_timestamp: u32) -> i64 {
+        let extended = match self.last {
+            None => rtp_timestamp as i64,
+            Some(last) => last + rtp_timestamp.wrapping_sub(last as u32) as i32 as i64,
+        };
+        self.last = Some(extended);
+        extended
+    }
+
+    // Media time of the timestamp in the stream's clock rate
+    #[inline]
+    pub fn time_from_rtp(&mut self, rtp_timestamp: u32) -> CMTime {
+        CMTime { value: self.unwrap(rtp_timestamp), timescale: self.clock_rate, flags: kCMTimeFlags_Valid, epoch: 0 }
+    }
+
+    pub fn rtp_from_time(&self, time: CMTime) -> Result<u32, RtpTimeError> {
+        time.check_numeric()?;
+        let (value, _) = div_round(time.value as i128 * self.clock_rate as i128, time.timescale as i128, kCMTimeRoundingMethod_RoundHalfAwayFromZero);
+        Ok(value as u32)
+    }
+
+    #[inline]
+    pub fn update_sender_report(&mut self, ntp: NtpTimestamp, rtp_timestamp: u32) {
+        let extended = self.unwrap(rtp_timestamp);
+        self.sender_report = Some((ntp, extended));
+    }
+
+    #[inline]
+    fn require_sender_report(&self) -> Result<(NtpTimestamp, i64), RtpTimeError> {
+        self.sender_report.ok_or(RtpTimeError::MissingSenderReport)
+    }
+
+    // Clock ticks from the sender report to the timestamp, taking the nearest extension of the timestamp to the
+    // sender report's so that conversions leave the unwrapping state of the stream untouched
+    fn ticks_since_sender_report(&self, rtp_timestamp: u32) -> Result<(NtpTimestamp, i128), RtpTimeError> {
+        let (ntp, reference) = self.require_sender_report()?;
+        Ok((ntp, rtp_timestamp.wrapping_sub(reference as u32) as i32 as i128))
+    }
+
+    pub fn ntp_from_rtp(&self, rtp_timestamp: u32) -> Result<NtpTimestamp, RtpTimeError> {
+        let (ntp, ticks) = self.ticks_since_sender_report(rtp_timestamp)?;
+        let (delta, _) = div_round(ticks * NTP_FRACTION_SCALE, self.clock_rate as i128, kCMTimeRoundingMethod_RoundHalfAwayFromZero);
+        Ok(NtpTimestamp(ntp.0.wrapping_add(i64::try_from(delta).map_err(|_| RtpTimeError::Overflow)? as u64)))
+    }
+
+    pub fn rtp_from_ntp(&self, ntp: NtpTimestamp) -> Result<u32, RtpTimeError> {
+        let (reference_ntp, reference) = self.require_sender_report()?;
+        let (ticks, _) =
+            div_round(ntp.delta(reference_ntp) as i128 * self.clock_rate as i128, NTP_FRACTION_SCALE, kCMTimeRoundingMethod_RoundHalfAwayFromZero);
+        Ok((reference as i128 + ticks) as u32)
+    }
+
+    // Host clock time at which the sample should be presented, in the timescale of the anchor's host time
+    pub fn host_time_from_rtp(&self, rtp_timestamp: u32, anchor: &NtpClockAnchor) -> Result<CMTime, RtpTimeError> {
+        let (ntp, ticks) = self.ticks_since_sender_report(rtp_timestamp)?;
+        anchor.host_time_after(ntp, ticks, self.clock_rate as i128)
+    }
+
+    pub fn rtp_from_host_time(&self, host_time: CMTime, anchor: &NtpClockAnchor) -> Result<u32, RtpTimeError> {
+        let (ntp, reference) = self.require_sender_report()?;
+        let (num, den) = anchor.seconds_since(host_time)?;
+        // Subtract the sender report's offset from the anchor, then convert seconds to clock ticks
+        let num = num * NTP_FRACTION_SCALE - anchor.ntp_delta(ntp) as i128 * den;
+        let num = num.checked_mul(self.clock_rate as i128).ok_or(RtpTimeError::Overflow)?;
+        let (ticks, _) = div_round(num, den * NTP_FRACTION_SCALE, kCMTimeRoundingMethod_RoundHalfAwayFromZero);
+        Ok((reference as i128 + ticks) as u32)
+    }
+}
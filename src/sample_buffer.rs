@@ -12,6 +12,8 @@ use core_video::image_buffer::{CVImageBuffer, CVImageBufferRef};
 use libc::{c_void, size_t};
 #[cfg(feature = "objc")]
 use objc2::encode::{Encoding, RefEncode};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use crate::{
     base::CMItemCount,
@@ -47,6 +49,7 @@ pub type CMSampleBufferRef = *mut opaqueCMSampleBuffer;
 
 #[repr(C, align(4))]
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CMSampleTimingInfo {
     pub duration: CMTime,
     pub presentationTimeStamp: CMTime,
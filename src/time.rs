@@ -1,11 +1,30 @@
+use std::{
+    cmp::Ordering,
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    hash::{Hash, Hasher},
+    ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign},
+    str::FromStr,
+    time::Duration,
+};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use core_foundation::{
     base::{kCFAllocatorDefault, CFAllocatorRef, TCFType},
     dictionary::{CFDictionary, CFDictionaryRef},
     number::CFNumber,
     string::{CFString, CFStringRef},
 };
-#[cfg(feature = "objc")]
+#[cfg(feature = "num-rational")]
+use num_rational::Ratio;
+#[cfg(all(feature = "objc", any(target_os = "macos", target_os = "ios")))]
 use objc2::encode::{Encode, Encoding};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(any(feature = "native-time", not(any(target_os = "macos", target_os = "ios"))))]
+use crate::time_native;
 
 pub type CMTimeValue = i64;
 pub type CMTimeScale = i32;
@@ -19,8 +38,11 @@ pub const kCMTimeFlags_NegativeInfinity: CMTimeFlags = 1 << 3;
 pub const kCMTimeFlags_Indefinite: CMTimeFlags = 1 << 4;
 pub const kCMTimeFlags_ImpliedValueFlagsMask: CMTimeFlags = kCMTimeFlags_PositiveInfinity | kCMTimeFlags_NegativeInfinity | kCMTimeFlags_Indefinite;
 
+pub const kCMTimeMaxTimescale: CMTimeScale = 0x7fffffff;
+
 #[repr(C, align(4))]
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CMTime {
     pub value: CMTimeValue,
     pub timescale: CMTimeScale,
@@ -28,6 +50,7 @@ pub struct CMTime {
     pub epoch: CMTimeEpoch,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     pub static kCMTimeInvalid: CMTime;
     pub static kCMTimeIndefinite: CMTime;
@@ -51,6 +74,7 @@ pub const kCMTimeRoundingMethod_RoundTowardPositiveInfinity: CMTimeRoundingMetho
 pub const kCMTimeRoundingMethod_RoundTowardNegativeInfinity: CMTimeRoundingMethod = 6;
 pub const kCMTimeRoundingMethod_Default: CMTimeRoundingMethod = kCMTimeRoundingMethod_RoundHalfAwayFromZero;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     pub fn CMTimeConvertScale(time: CMTime, newTimescale: CMTimeScale, method: CMTimeRoundingMethod) -> CMTime;
     pub fn CMTimeAdd(lhs: CMTime, rhs: CMTime) -> CMTime;
@@ -74,82 +98,654 @@ extern "C" {
     pub fn CMTimeShow(time: CMTime);
 }
 
-#[cfg(feature = "objc")]
+#[cfg(all(feature = "objc", any(target_os = "macos", target_os = "ios")))]
 unsafe impl Encode for CMTime {
     const ENCODING: Encoding = Encoding::Struct("?", &[CMTimeValue::ENCODING, CMTimeScale::ENCODING, CMTimeFlags::ENCODING, CMTimeEpoch::ENCODING]);
 }
 
 impl CMTime {
+    pub const INVALID: CMTime = CMTime { value: 0, timescale: 0, flags: 0, epoch: 0 };
+    pub const INDEFINITE: CMTime = CMTime { value: 0, timescale: 0, flags: kCMTimeFlags_Valid | kCMTimeFlags_Indefinite, epoch: 0 };
+    pub const POSITIVE_INFINITY: CMTime = CMTime { value: 0, timescale: 0, flags: kCMTimeFlags_Valid | kCMTimeFlags_PositiveInfinity, epoch: 0 };
+    pub const NEGATIVE_INFINITY: CMTime = CMTime { value: 0, timescale: 0, flags: kCMTimeFlags_Valid | kCMTimeFlags_NegativeInfinity, epoch: 0 };
+    pub const ZERO: CMTime = CMTime { value: 0, timescale: 1, flags: kCMTimeFlags_Valid, epoch: 0 };
+
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.flags & kCMTimeFlags_Valid != 0
+    }
+
+    #[inline]
+    pub fn is_invalid(&self) -> bool {
+        !self.is_valid()
+    }
+
+    #[inline]
+    pub fn is_positive_infinity(&self) -> bool {
+        self.is_valid() && self.flags & kCMTimeFlags_PositiveInfinity != 0
+    }
+
+    #[inline]
+    pub fn is_negative_infinity(&self) -> bool {
+        self.is_valid() && self.flags & kCMTimeFlags_NegativeInfinity != 0
+    }
+
+    #[inline]
+    pub fn is_indefinite(&self) -> bool {
+        self.is_valid() && self.flags & kCMTimeFlags_Indefinite != 0
+    }
+
+    #[inline]
+    pub fn is_numeric(&self) -> bool {
+        self.flags & (kCMTimeFlags_Valid | kCMTimeFlags_ImpliedValueFlagsMask) == kCMTimeFlags_Valid
+    }
+
+    #[inline]
+    pub fn has_been_rounded(&self) -> bool {
+        self.flags & kCMTimeFlags_HasBeenRounded != 0
+    }
+}
+
+cfg_if! {
+    if #[cfg(any(feature = "native-time", not(any(target_os = "macos", target_os = "ios"))))] {
+        impl CMTime {
+            #[inline]
+            pub fn make(value: CMTimeValue, timescale: CMTimeScale) -> Self {
+                time_native::make(value, timescale, 0)
+            }
+
+            #[inline]
+            pub fn make_with_epoch(value: CMTimeValue, timescale: CMTimeScale, epoch: CMTimeEpoch) -> Self {
+                time_native::make(value, timescale, epoch)
+            }
+
+            #[inline]
+            pub fn make_with_seconds(seconds: f64, preferred_time_scale: i32) -> Self {
+                time_native::make_with_seconds(seconds, preferred_time_scale)
+            }
+
+            #[inline]
+            pub fn get_seconds(&self) -> f64 {
+                time_native::get_seconds(*self)
+            }
+
+            #[inline]
+            pub fn convert_scale(&self, new_timescale: CMTimeScale, method: CMTimeRoundingMethod) -> Self {
+                time_native::convert_scale(*self, new_timescale, method)
+            }
+
+            #[inline]
+            pub fn add(&self, time: CMTime) -> Self {
+                time_native::add(*self, time)
+            }
+
+            #[inline]
+            pub fn subtract(&self, time: CMTime) -> Self {
+                time_native::subtract(*self, time)
+            }
+
+            #[inline]
+            pub fn multiply(&self, multiplier: i32) -> Self {
+                time_native::multiply(*self, multiplier)
+            }
+
+            #[inline]
+            pub fn multiply_by_float64(&self, multiplier: f64) -> Self {
+                time_native::multiply_by_float64(*self, multiplier)
+            }
+
+            #[inline]
+            pub fn multiply_by_ratio(&self, multiplier: i32, divisor: i32) -> Self {
+                time_native::multiply_by_ratio(*self, multiplier, divisor)
+            }
+
+            #[inline]
+            pub fn compare(&self, time: CMTime) -> i32 {
+                time_native::compare(*self, time)
+            }
+
+            #[inline]
+            pub fn minimum(&self, time: CMTime) -> Self {
+                time_native::minimum(*self, time)
+            }
+
+            #[inline]
+            pub fn maximum(&self, time: CMTime) -> Self {
+                time_native::maximum(*self, time)
+            }
+
+            #[inline]
+            pub fn absolute_value(&self) -> Self {
+                time_native::absolute_value(*self)
+            }
+        }
+    } else {
+        impl CMTime {
+            #[inline]
+            pub fn make(value: CMTimeValue, timescale: CMTimeScale) -> Self {
+                unsafe { CMTimeMake(value, timescale) }
+            }
+
+            #[inline]
+            pub fn make_with_epoch(value: CMTimeValue, timescale: CMTimeScale, epoch: CMTimeEpoch) -> Self {
+                unsafe { CMTimeMakeWithEpoch(value, timescale, epoch) }
+            }
+
+            #[inline]
+            pub fn make_with_seconds(seconds: f64, preferred_time_scale: i32) -> Self {
+                unsafe { CMTimeMakeWithSeconds(seconds, preferred_time_scale) }
+            }
+
+            #[inline]
+            pub fn get_seconds(&self) -> f64 {
+                unsafe { CMTimeGetSeconds(*self) }
+            }
+
+            #[inline]
+            pub fn convert_scale(&self, new_timescale: CMTimeScale, method: CMTimeRoundingMethod) -> Self {
+                unsafe { CMTimeConvertScale(*self, new_timescale, method) }
+            }
+
+            #[inline]
+            pub fn add(&self, time: CMTime) -> Self {
+                unsafe { CMTimeAdd(*self, time) }
+            }
+
+            #[inline]
+            pub fn subtract(&self, time: CMTime) -> Self {
+                unsafe { CMTimeSubtract(*self, time) }
+            }
+
+            #[inline]
+            pub fn multiply(&self, multiplier: i32) -> Self {
+                unsafe { CMTimeMultiply(*self, multiplier) }
+            }
+
+            #[inline]
+            pub fn multiply_by_float64(&self, multiplier: f64) -> Self {
+                unsafe { CMTimeMultiplyByFloat64(*self, multiplier) }
+            }
+
+            #[inline]
+            pub fn multiply_by_ratio(&self, multiplier: i32, divisor: i32) -> Self {
+                unsafe { CMTimeMultiplyByRatio(*self, multiplier, divisor) }
+            }
+
+            #[inline]
+            pub fn compare(&self, time: CMTime) -> i32 {
+                unsafe { CMTimeCompare(*self, time) }
+            }
+
+            #[inline]
+            pub fn minimum(&self, time: CMTime) -> Self {
+                unsafe { CMTimeMinimum(*self, time) }
+            }
+
+            #[inline]
+            pub fn maximum(&self, time: CMTime) -> Self {
+                unsafe { CMTimeMaximum(*self, time) }
+            }
+
+            #[inline]
+            pub fn absolute_value(&self) -> Self {
+                unsafe { CMTimeAbsoluteValue(*self) }
+            }
+        }
+    }
+}
+
+pub(crate) fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+// Reduces num / den (den > 0) to lowest terms
+#[inline]
+pub(crate) fn reduce(num: i128, den: i128) -> (i128, i128) {
+    let divisor = gcd(num.unsigned_abs(), den as u128) as i128;
+    (num / divisor, den / divisor)
+}
+
+// Divides num by den (den > 0) and rounds the quotient, returning whether rounding was necessary
+pub(crate) fn div_round(num: i128, den: i128, method: CMTimeRoundingMethod) -> (i128, bool) {
+    let quotient = num / den;
+    let remainder = num % den;
+    if remainder == 0 {
+        return (quotient, false);
+    }
+    let away = if num < 0 { quotient - 1 } else { quotient + 1 };
+    let rounded = match method {
+        kCMTimeRoundingMethod_RoundTowardZero => quotient,
+        kCMTimeRoundingMethod_RoundAwayFromZero => away,
+        kCMTimeRoundingMethod_RoundTowardPositiveInfinity => {
+            if num > 0 {
+                away
+            } else {
+                quotient
+            }
+        }
+        kCMTimeRoundingMethod_RoundTowardNegativeInfinity => {
+            if num < 0 {
+                away
+            } else {
+                quotient
+            }
+        }
+        _ => {
+            if remainder.unsigned_abs() * 2 >= den.unsigned_abs() {
+                away
+            } else {
+                quotient
+            }
+        }
+    };
+    (rounded, true)
+}
+
+#[inline]
+pub(crate) fn resolve_rounding_method(method: CMTimeRoundingMethod, timescale: CMTimeScale, new_timescale: CMTimeScale) -> CMTimeRoundingMethod {
+    match method {
+        kCMTimeRoundingMethod_RoundHalfAwayFromZero |
+        kCMTimeRoundingMethod_RoundTowardZero |
+        kCMTimeRoundingMethod_RoundAwayFromZero |
+        kCMTimeRoundingMethod_RoundTowardPositiveInfinity |
+        kCMTimeRoundingMethod_RoundTowardNegativeInfinity => method,
+        kCMTimeRoundingMethod_QuickTime => {
+            if new_timescale < timescale {
+                kCMTimeRoundingMethod_RoundTowardZero
+            } else {
+                kCMTimeRoundingMethod_RoundAwayFromZero
+            }
+        }
+        _ => kCMTimeRoundingMethod_Default,
+    }
+}
+
+impl PartialEq for CMTime {
     #[inline]
-    pub fn make(value: CMTimeValue, timescale: CMTimeScale) -> Self {
-        unsafe { CMTimeMake(value, timescale) }
+    fn eq(&self, other: &Self) -> bool {
+        self.compare(*other) == 0
     }
+}
+
+impl Eq for CMTime {}
 
+impl PartialOrd for CMTime {
     #[inline]
-    pub fn make_with_epoch(value: CMTimeValue, timescale: CMTimeScale, epoch: CMTimeEpoch) -> Self {
-        unsafe { CMTimeMakeWithEpoch(value, timescale, epoch) }
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
+}
 
+impl Ord for CMTime {
     #[inline]
-    pub fn make_with_seconds(seconds: f64, preferred_time_scale: i32) -> Self {
-        unsafe { CMTimeMakeWithSeconds(seconds, preferred_time_scale) }
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.compare(*other).cmp(&0)
     }
+}
+
+impl Hash for CMTime {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Equal times must hash identically, so numeric times are reduced to lowest terms and the flags are ignored
+        if self.is_numeric() && self.timescale > 0 {
+            let (value, timescale) = reduce(self.value as i128, self.timescale as i128);
+            0u8.hash(state);
+            self.epoch.hash(state);
+            (value as i64).hash(state);
+            (timescale as i64).hash(state);
+        } else if self.is_positive_infinity() {
+            1u8.hash(state);
+        } else if self.is_negative_infinity() {
+            2u8.hash(state);
+        } else if self.is_indefinite() {
+            3u8.hash(state);
+        } else {
+            4u8.hash(state);
+        }
+    }
+}
+
+impl Add for CMTime {
+    type Output = CMTime;
 
     #[inline]
-    pub fn get_seconds(&self) -> f64 {
-        unsafe { CMTimeGetSeconds(*self) }
+    fn add(self, rhs: CMTime) -> CMTime {
+        CMTime::add(&self, rhs)
     }
+}
 
+impl AddAssign for CMTime {
     #[inline]
-    pub fn convert_scale(&self, new_timescale: CMTimeScale, method: CMTimeRoundingMethod) -> Self {
-        unsafe { CMTimeConvertScale(*self, new_timescale, method) }
+    fn add_assign(&mut self, rhs: CMTime) {
+        *self = CMTime::add(self, rhs);
     }
+}
+
+impl Sub for CMTime {
+    type Output = CMTime;
 
     #[inline]
-    pub fn add(&self, time: CMTime) -> Self {
-        unsafe { CMTimeAdd(*self, time) }
+    fn sub(self, rhs: CMTime) -> CMTime {
+        self.subtract(rhs)
     }
+}
 
+impl SubAssign for CMTime {
     #[inline]
-    pub fn subtract(&self, time: CMTime) -> Self {
-        unsafe { CMTimeSubtract(*self, time) }
+    fn sub_assign(&mut self, rhs: CMTime) {
+        *self = self.subtract(rhs);
     }
+}
+
+impl Neg for CMTime {
+    type Output = CMTime;
 
     #[inline]
-    pub fn multiply(&self, multiplier: i32) -> Self {
-        unsafe { CMTimeMultiply(*self, multiplier) }
+    fn neg(self) -> CMTime {
+        self.multiply(-1)
     }
+}
+
+impl Mul<i32> for CMTime {
+    type Output = CMTime;
 
     #[inline]
-    pub fn multiply_by_float64(&self, multiplier: f64) -> Self {
-        unsafe { CMTimeMultiplyByFloat64(*self, multiplier) }
+    fn mul(self, rhs: i32) -> CMTime {
+        self.multiply(rhs)
     }
+}
+
+impl Mul<f64> for CMTime {
+    type Output = CMTime;
 
     #[inline]
-    pub fn multiply_by_ratio(&self, multiplier: i32, divisor: i32) -> Self {
-        unsafe { CMTimeMultiplyByRatio(*self, multiplier, divisor) }
+    fn mul(self, rhs: f64) -> CMTime {
+        self.multiply_by_float64(rhs)
     }
+}
+
+const NANOS_PER_SEC: CMTimeScale = 1_000_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CMTimeConversionError {
+    Invalid,
+    Indefinite,
+    PositiveInfinity,
+    NegativeInfinity,
+    Negative,
+    ZeroDenominator,
+    Overflow,
+}
+
+impl Display for CMTimeConversionError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CMTimeConversionError::Invalid => write!(f, "time is invalid"),
+            CMTimeConversionError::Indefinite => write!(f, "time is indefinite"),
+            CMTimeConversionError::PositiveInfinity => write!(f, "time is positive infinity"),
+            CMTimeConversionError::NegativeInfinity => write!(f, "time is negative infinity"),
+            CMTimeConversionError::Negative => write!(f, "time is negative"),
+            CMTimeConversionError::ZeroDenominator => write!(f, "denominator is zero"),
+            CMTimeConversionError::Overflow => write!(f, "value cannot be represented without overflow"),
+        }
+    }
+}
+
+impl Error for CMTimeConversionError {}
+
+impl CMTime {
+    pub(crate) fn check_numeric(&self) -> Result<(), CMTimeConversionError> {
+        if self.is_positive_infinity() {
+            Err(CMTimeConversionError::PositiveInfinity)
+        } else if self.is_negative_infinity() {
+            Err(CMTimeConversionError::NegativeInfinity)
+        } else if self.is_indefinite() {
+            Err(CMTimeConversionError::Indefinite)
+        } else if !self.is_numeric() || self.timescale <= 0 {
+            Err(CMTimeConversionError::Invalid)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn to_duration(&self, method: CMTimeRoundingMethod) -> Result<Duration, CMTimeConversionError> {
+        self.check_numeric()?;
+        if self.value < 0 {
+            return Err(CMTimeConversionError::Negative);
+        }
+        let method = resolve_rounding_method(method, self.timescale, NANOS_PER_SEC);
+        let (nanos, _) = div_round(self.value as i128 * NANOS_PER_SEC as i128, self.timescale as i128, method);
+        Ok(Duration::new((nanos / NANOS_PER_SEC as i128) as u64, (nanos % NANOS_PER_SEC as i128) as u32))
+    }
+
+    pub fn from_duration(duration: Duration) -> Self {
+        let nanos = duration.as_nanos() as i128;
+        let mut timescale = NANOS_PER_SEC;
+        let mut rounded = false;
+        // Durations beyond the range of a nanosecond CMTimeValue are expressed in a coarser timescale
+        while timescale > 0 {
+            let (value, inexact) = div_round(nanos * timescale as i128, NANOS_PER_SEC as i128, kCMTimeRoundingMethod_Default);
+            rounded |= inexact;
+            if let Ok(value) = CMTimeValue::try_from(value) {
+                return CMTime {
+                    value,
+                    timescale,
+                    flags: if rounded { kCMTimeFlags_Valid | kCMTimeFlags_HasBeenRounded } else { kCMTimeFlags_Valid },
+                    epoch: 0,
+                };
+            }
+            timescale /= 2;
+        }
+        CMTime::POSITIVE_INFINITY
+    }
+}
+
+impl TryFrom<CMTime> for Duration {
+    type Error = CMTimeConversionError;
 
     #[inline]
-    pub fn compare(&self, time: CMTime) -> i32 {
-        unsafe { CMTimeCompare(*self, time) }
+    fn try_from(time: CMTime) -> Result<Self, Self::Error> {
+        time.to_duration(kCMTimeRoundingMethod_Default)
     }
+}
 
+impl From<Duration> for CMTime {
     #[inline]
-    pub fn minimum(&self, time: CMTime) -> Self {
-        unsafe { CMTimeMinimum(*self, time) }
+    fn from(duration: Duration) -> Self {
+        CMTime::from_duration(duration)
     }
+}
 
+impl TryFrom<CMTime> for (i64, i64) {
+    type Error = CMTimeConversionError;
+
+    fn try_from(time: CMTime) -> Result<Self, Self::Error> {
+        time.check_numeric()?;
+        let (numerator, denominator) = reduce(time.value as i128, time.timescale as i128);
+        Ok((numerator as i64, denominator as i64))
+    }
+}
+
+impl TryFrom<(i64, i64)> for CMTime {
+    type Error = CMTimeConversionError;
+
+    fn try_from((numerator, denominator): (i64, i64)) -> Result<Self, Self::Error> {
+        if denominator == 0 {
+            return Err(CMTimeConversionError::ZeroDenominator);
+        }
+        let (mut numerator, mut denominator) = (numerator as i128, denominator as i128);
+        if denominator < 0 {
+            numerator = -numerator;
+            denominator = -denominator;
+        }
+        let (numerator, denominator) = reduce(numerator, denominator);
+        match (CMTimeValue::try_from(numerator), CMTimeScale::try_from(denominator)) {
+            (Ok(value), Ok(timescale)) => Ok(CMTime { value, timescale, flags: kCMTimeFlags_Valid, epoch: 0 }),
+            _ => Err(CMTimeConversionError::Overflow),
+        }
+    }
+}
+
+#[cfg(feature = "num-rational")]
+impl TryFrom<CMTime> for Ratio<i64> {
+    type Error = CMTimeConversionError;
+
+    #[inline]
+    fn try_from(time: CMTime) -> Result<Self, Self::Error> {
+        let (numerator, denominator) = <(i64, i64)>::try_from(time)?;
+        Ok(Ratio::new_raw(numerator, denominator))
+    }
+}
+
+#[cfg(feature = "num-rational")]
+impl TryFrom<Ratio<i64>> for CMTime {
+    type Error = CMTimeConversionError;
+
+    #[inline]
+    fn try_from(ratio: Ratio<i64>) -> Result<Self, Self::Error> {
+        CMTime::try_from((*ratio.numer(), *ratio.denom()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CMTimeParseError {
+    InvalidSyntax,
+    InvalidNumber,
+    TrailingCharacters,
+}
+
+impl Display for CMTimeParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CMTimeParseError::InvalidSyntax => write!(f, "invalid time description"),
+            CMTimeParseError::InvalidNumber => write!(f, "invalid number in time description"),
+            CMTimeParseError::TrailingCharacters => write!(f, "unexpected characters after time description"),
+        }
+    }
+}
+
+impl Error for CMTimeParseError {}
+
+// Formats the same text as CMTimeCopyDescription
+impl Display for CMTime {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if !self.is_valid() {
+            write!(f, "{{INVALID}}")
+        } else if self.is_positive_infinity() {
+            write!(f, "{{+INFINITY}}")
+        } else if self.is_negative_infinity() {
+            write!(f, "{{-INFINITY}}")
+        } else if self.is_indefinite() {
+            write!(f, "{{INDEFINITE}}")
+        } else {
+            write!(f, "{{{}/{} = {:.3}", self.value, self.timescale, self.value as f64 / self.timescale as f64)?;
+            if self.has_been_rounded() {
+                write!(f, ", rounded")?;
+            }
+            if self.epoch != 0 {
+                write!(f, ", epoch = {}", self.epoch)?;
+            }
+            write!(f, "}}")
+        }
+    }
+}
+
+// Cursor over a description string, shared by the CMTime, CMTimeRange and CMTimeMapping parsers
+pub(crate) struct DescriptionParser<'a> {
+    s: &'a str,
+}
+
+impl<'a> DescriptionParser<'a> {
     #[inline]
-    pub fn maximum(&self, time: CMTime) -> Self {
-        unsafe { CMTimeMaximum(*self, time) }
+    pub(crate) fn new(s: &'a str) -> Self {
+        DescriptionParser { s }
+    }
+
+    pub(crate) fn expect(&mut self, token: &str) -> Result<(), CMTimeParseError> {
+        let s = self.s.trim_start();
+        match s.strip_prefix(token) {
+            Some(rest) => {
+                self.s = rest;
+                Ok(())
+            }
+            None => Err(CMTimeParseError::InvalidSyntax),
+        }
     }
 
     #[inline]
-    pub fn absolute_value(&self) -> Self {
-        unsafe { CMTimeAbsoluteValue(*self) }
+    fn accept(&mut self, token: &str) -> bool {
+        self.expect(token).is_ok()
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> &'a str {
+        let s = self.s.trim_start();
+        let end = s.find(|c: char| !predicate(c)).unwrap_or(s.len());
+        self.s = &s[end..];
+        &s[..end]
+    }
+
+    fn integer<T: FromStr>(&mut self) -> Result<T, CMTimeParseError> {
+        self.take_while(|c| c.is_ascii_digit() || c == '-' || c == '+').parse().map_err(|_| CMTimeParseError::InvalidNumber)
+    }
+
+    pub(crate) fn time(&mut self) -> Result<CMTime, CMTimeParseError> {
+        self.expect("{")?;
+        let time = if self.accept("INVALID") {
+            CMTime::INVALID
+        } else if self.accept("+INFINITY") {
+            CMTime::POSITIVE_INFINITY
+        } else if self.accept("-INFINITY") {
+            CMTime::NEGATIVE_INFINITY
+        } else if self.accept("INDEFINITE") {
+            CMTime::INDEFINITE
+        } else {
+            let value = self.integer()?;
+            self.expect("/")?;
+            let timescale = self.integer()?;
+            self.expect("=")?;
+            // The seconds are derived from the value and timescale, so they are only checked for being present
+            if self.take_while(|c| c != ',' && c != '}').trim_end().is_empty() {
+                return Err(CMTimeParseError::InvalidNumber);
+            }
+            let mut time = CMTime { value, timescale, flags: kCMTimeFlags_Valid, epoch: 0 };
+            if self.accept(", rounded") {
+                time.flags |= kCMTimeFlags_HasBeenRounded;
+            }
+            if self.accept(", epoch =") {
+                time.epoch = self.integer()?;
+            }
+            time
+        };
+        self.expect("}")?;
+        Ok(time)
     }
 
+    pub(crate) fn finish(&mut self) -> Result<(), CMTimeParseError> {
+        if self.s.trim().is_empty() {
+            Ok(())
+        } else {
+            Err(CMTimeParseError::TrailingCharacters)
+        }
+    }
+}
+
+impl FromStr for CMTime {
+    type Err = CMTimeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = DescriptionParser::new(s);
+        let time = parser.time()?;
+        parser.finish()?;
+        Ok(time)
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl CMTime {
     #[inline]
     pub fn copy_as_dictionary(&self) -> Option<CFDictionary<CFString, CFNumber>> {
         unsafe {
@@ -0,0 +1,401 @@
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::{
+    time::{div_round, kCMTimeFlags_Valid, kCMTimeRoundingMethod_RoundHalfAwayFromZero, CMTime, CMTimeScale, CMTimeValue},
+    time_range::{CMTimeMapping, CMTimeRange},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimelineError {
+    InvalidMapping,
+    NotContiguous,
+    OutOfRange,
+    InvalidTimescale,
+    Overflow,
+    TruncatedEditList,
+    UnsupportedEditListVersion,
+}
+
+impl Display for TimelineError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TimelineError::InvalidMapping => write!(f, "time mapping is not valid"),
+            TimelineError::NotContiguous => write!(f, "target ranges are not contiguous"),
+            TimelineError::OutOfRange => write!(f, "time is outside the timeline"),
+            TimelineError::InvalidTimescale => write!(f, "timescale must be positive"),
+            TimelineError::Overflow => write!(f, "time cannot be represented in the edit list"),
+            TimelineError::TruncatedEditList => write!(f, "edit list is truncated"),
+            TimelineError::UnsupportedEditListVersion => write!(f, "unsupported edit list version"),
+        }
+    }
+}
+
+impl Error for TimelineError {}
+
+// 16.16 fixed point rate of a segment that plays at normal speed
+const UNITY_MEDIA_RATE: i32 = 0x0001_0000;
+
+/// An entry of an ISO BMFF edit list box; a media_time of -1 marks an empty edit
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EditListEntry {
+    pub segment_duration: u64,
+    pub media_time: i64,
+    pub media_rate_integer: i16,
+    pub media_rate_fraction: i16,
+}
+
+impl EditListEntry {
+    #[inline]
+    pub fn media_rate(&self) -> i32 {
+        (self.media_rate_integer as i32) << 16 | self.media_rate_fraction as u16 as i32
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.media_time == -1
+    }
+}
+
+// Parses the body of an elst box, following the box header
+pub fn parse_edit_list(data: &[u8]) -> Result<Vec<EditListEntry>, TimelineError> {
+    let read = |offset: usize, length: usize| data.get(offset..offset + length).ok_or(TimelineError::TruncatedEditList);
+    let header = read(0, 8)?;
+    let version = header[0];
+    let entry_count = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    let entry_size = match version {
+        0 => 12,
+        1 => 20,
+        _ => return Err(TimelineError::UnsupportedEditListVersion),
+    };
+    if entry_count > (data.len() - 8) / entry_size {
+        return Err(TimelineError::TruncatedEditList);
+    }
+    let mut entries = Vec::with_capacity(entry_count);
+    for index in 0..entry_count {
+        let entry = read(8 + index * entry_size, entry_size)?;
+        let (segment_duration, media_time, rate) = if version == 1 {
+            let mut duration = [0u8; 8];
+            let mut time = [0u8; 8];
+            duration.copy_from_slice(&entry[0..8]);
+            time.copy_from_slice(&entry[8..16]);
+            (u64::from_be_bytes(duration), i64::from_be_bytes(time), &entry[16..20])
+        } else {
+            let duration = u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]);
+            let time = i32::from_be_bytes([entry[4], entry[5], entry[6], entry[7]]);
+            (duration as u64, time as i64, &entry[8..12])
+        };
+        entries.push(EditListEntry {
+            segment_duration,
+            media_time,
+            media_rate_integer: i16::from_be_bytes([rate[0], rate[1]]),
+            media_rate_fraction: i16::from_be_bytes([rate[2], rate[3]]),
+        });
+    }
+    Ok(entries)
+}
+
+// Writes the body of an elst box, using version 1 only when a value does not fit in 32 bits
+pub fn write_edit_list(entries: &[EditListEntry]) -> Vec<u8> {
+    let version =
+        if entries.iter().any(|entry| entry.segment_duration > u32::MAX as u64 || i32::try_from(entry.media_time).is_err()) { 1 } else { 0 };
+    let mut data = Vec::with_capacity(8 + entries.len() * if version == 1 { 20 } else { 12 });
+    data.extend_from_slice(&[version, 0, 0, 0]);
+    data.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+    for entry in entries {
+        if version == 1 {
+            data.extend_from_slice(&entry.segment_duration.to_be_bytes());
+            data.extend_from_slice(&entry.media_time.to_be_bytes());
+        } else {
+            data.extend_from_slice(&(entry.segment_duration as u32).to_be_bytes());
+            data.extend_from_slice(&(entry.media_time as i32).to_be_bytes());
+        }
+        data.extend_from_slice(&entry.media_rate_integer.to_be_bytes());
+        data.extend_from_slice(&entry.media_rate_fraction.to_be_bytes());
+    }
+    data
+}
+
+#[inline]
+fn is_computable(time: &CMTime) -> bool {
+    time.is_numeric() && time.timescale > 0
+}
+
+// Rounds the time to the nearest whole number of units of the timescale
+fn value_in_timescale(time: CMTime, timescale: CMTimeScale) -> Result<i128, TimelineError> {
+    if !is_computable(&time) {
+        return Err(TimelineError::InvalidMapping);
+    }
+    Ok(div_round(time.value as i128 * timescale as i128, time.timescale as i128, kCMTimeRoundingMethod_RoundHalfAwayFromZero).0)
+}
+
+fn make_time(value: i128, timescale: CMTimeScale) -> Result<CMTime, TimelineError> {
+    let value = CMTimeValue::try_from(value).map_err(|_| TimelineError::Overflow)?;
+    Ok(CMTime { value, timescale, flags: kCMTimeFlags_Valid, epoch: 0 })
+}
+
+fn validate_mapping(mapping: &CMTimeMapping) -> Result<(), TimelineError> {
+    let target = &mapping.target;
+    if !is_computable(&target.start) || !is_computable(&target.duration) || target.duration.value < 0 {
+        return Err(TimelineError::InvalidMapping);
+    }
+    if !mapping.is_empty() {
+        let source = &mapping.source;
+        if !is_computable(&source.start) || !is_computable(&source.duration) || source.duration.value < 0 {
+            return Err(TimelineError::InvalidMapping);
+        }
+    }
+    Ok(())
+}
+
+// Maps a time inside the segment's target range to its source range; segments without source duration hold their start
+fn map_to_source(mapping: &CMTimeMapping, time: CMTime) -> CMTime {
+    if mapping.source.duration == CMTime::ZERO || mapping.target.duration == CMTime::ZERO {
+        mapping.source.start
+    } else {
+        mapping.target.map_time(time, mapping.source)
+    }
+}
+
+// Shifts the target range of the segment by the offset
+#[inline]
+fn shifted(mapping: &CMTimeMapping, offset: CMTime) -> CMTimeMapping {
+    CMTimeMapping { source: mapping.source, target: CMTimeRange { start: mapping.target.start + offset, duration: mapping.target.duration } }
+}
+
+/// An edit list: segments whose target ranges follow each other without gaps, each mapping to a source range or empty
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TimeMappingTimeline {
+    segments: Vec<CMTimeMapping>,
+}
+
+impl TimeMappingTimeline {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_segments(segments: Vec<CMTimeMapping>) -> Result<Self, TimelineError> {
+        let mut timeline = TimeMappingTimeline::new();
+        for segment in segments {
+            timeline.push(segment)?;
+        }
+        Ok(timeline)
+    }
+
+    #[inline]
+    pub fn segments(&self) -> &[CMTimeMapping] {
+        &self.segments
+    }
+
+    #[inline]
+    pub fn into_segments(self) -> Vec<CMTimeMapping> {
+        self.segments
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    #[inline]
+    pub fn start(&self) -> CMTime {
+        self.segments.first().map_or(CMTime::ZERO, |segment| segment.target.start)
+    }
+
+    #[inline]
+    pub fn end(&self) -> CMTime {
+        self.segments.last().map_or(CMTime::ZERO, |segment| segment.target.start + segment.target.duration)
+    }
+
+    #[inline]
+    pub fn target_range(&self) -> CMTimeRange {
+        CMTimeRange::from_time_to_time(self.start(), self.end())
+    }
+
+    // Appends a segment, which must start where the timeline ends
+    pub fn push(&mut self, segment: CMTimeMapping) -> Result<(), TimelineError> {
+        validate_mapping(&segment)?;
+        if !self.segments.is_empty() && segment.target.start != self.end() {
+            return Err(TimelineError::NotContiguous);
+        }
+        self.segments.push(segment);
+        Ok(())
+    }
+
+    #[inline]
+    pub fn push_empty(&mut self, duration: CMTime) -> Result<(), TimelineError> {
+        let end = self.end();
+        self.push(CMTimeMapping::make_empty(CMTimeRange { start: end, duration }))
+    }
+
+    // Index of the segment whose target range contains the time
+    pub fn segment_index_at(&self, time: CMTime) -> Option<usize> {
+        let index = self.segments.partition_point(|segment| segment.target.start + segment.target.duration <= time);
+        self.segments.get(index).filter(|segment| segment.target.start <= time).map(|_| index)
+    }
+
+    // The source time presented at the target time, or None inside an empty edit or outside the timeline
+    pub fn source_time(&self, target_time: CMTime) -> Option<CMTime> {
+        let segment = &self.segments[self.segment_index_at(target_time)?];
+        if segment.is_empty() {
+            return None;
+        }
+        Some(map_to_source(segment, target_time))
+    }
+
+    // The first target time at which the source time is presented
+    pub fn target_time(&self, source_time: CMTime) -> Option<CMTime> {
+        self.segments.iter().filter(|segment| !segment.is_empty()).find_map(|segment| {
+            let source = &segment.source;
+            if source.duration == CMTime::ZERO {
+                Some(segment.target.start).filter(|_| source.start == source_time)
+            } else if source.contains_time(source_time) {
+                Some(source.map_time(source_time, segment.target))
+            } else {
+                None
+            }
+        })
+    }
+
+    // Splits the segment containing the time so that a segment boundary falls on it, returning the index of the segment
+    // that starts at the time
+    pub fn split(&mut self, time: CMTime) -> Result<usize, TimelineError> {
+        if time == self.end() {
+            return Ok(self.segments.len());
+        }
+        let index = self.segment_index_at(time).ok_or(TimelineError::OutOfRange)?;
+        let segment = self.segments[index];
+        if segment.target.start == time {
+            return Ok(index);
+        }
+        let target_end = segment.target.start + segment.target.duration;
+        let (first_source, second_source) = if segment.is_empty() {
+            (segment.source, segment.source)
+        } else {
+            let split_point = map_to_source(&segment, time);
+            let source_end = segment.source.start + segment.source.duration;
+            (CMTimeRange::from_time_to_time(segment.source.start, split_point), CMTimeRange::from_time_to_time(split_point, source_end))
+        };
+        self.segments[index] = CMTimeMapping { source: first_source, target: CMTimeRange::from_time_to_time(segment.target.start, time) };
+        self.segments.insert(index + 1, CMTimeMapping { source: second_source, target: CMTimeRange::from_time_to_time(time, target_end) });
+        Ok(index + 1)
+    }
+
+    // Inserts the segment at the target time, moving later segments back by its duration
+    pub fn insert(&mut self, time: CMTime, segment: CMTimeMapping) -> Result<(), TimelineError> {
+        validate_mapping(&segment)?;
+        let time = if self.segments.is_empty() { segment.target.start } else { time };
+        let index = if self.segments.is_empty() { 0 } else { self.split(time)? };
+        let duration = segment.target.duration;
+        for later in &mut self.segments[index..] {
+            *later = shifted(later, duration);
+        }
+        self.segments.insert(index, CMTimeMapping { source: segment.source, target: CMTimeRange { start: time, duration } });
+        Ok(())
+    }
+
+    // Removes the target range from the timeline, moving later segments forward to close the gap
+    pub fn remove(&mut self, range: CMTimeRange) -> Result<(), TimelineError> {
+        let (first, last) = self.split_range(range)?;
+        self.segments.drain(first..last);
+        let offset = -range.duration;
+        for later in &mut self.segments[first..] {
+            *later = shifted(later, offset);
+        }
+        Ok(())
+    }
+
+    // Keeps only the part of the timeline inside the target range
+    pub fn trim(&mut self, range: CMTimeRange) -> Result<(), TimelineError> {
+        let (first, last) = self.split_range(range)?;
+        self.segments.truncate(last);
+        self.segments.drain(..first);
+        Ok(())
+    }
+
+    // Splits at both ends of the range, returning the indices of the segments inside it
+    fn split_range(&mut self, range: CMTimeRange) -> Result<(usize, usize), TimelineError> {
+        if !is_computable(&range.start) || !is_computable(&range.duration) || range.duration.value < 0 {
+            return Err(TimelineError::OutOfRange);
+        }
+        let end = range.start + range.duration;
+        if range.start < self.start() || end > self.end() {
+            return Err(TimelineError::OutOfRange);
+        }
+        let first = self.split(range.start)?;
+        let last = self.split(end)?;
+        Ok((first, last))
+    }
+
+    // Builds a timeline from edit list entries, whose segment durations are in the movie timescale and media times in the
+    // media timescale; the media rate scales the source duration of each segment
+    pub fn from_edit_list(entries: &[EditListEntry], movie_timescale: CMTimeScale, media_timescale: CMTimeScale) -> Result<Self, TimelineError> {
+        if movie_timescale <= 0 || media_timescale <= 0 {
+            return Err(TimelineError::InvalidTimescale);
+        }
+        let mut timeline = TimeMappingTimeline::new();
+        let mut start: i128 = 0;
+        for entry in entries {
+            let target =
+                CMTimeRange { start: make_time(start, movie_timescale)?, duration: make_time(entry.segment_duration as i128, movie_timescale)? };
+            start += entry.segment_duration as i128;
+            let segment = if entry.is_empty() {
+                CMTimeMapping::make_empty(target)
+            } else {
+                let num = entry.segment_duration as i128 * media_timescale as i128 * entry.media_rate() as i128;
+                let (source_duration, _) =
+                    div_round(num, movie_timescale as i128 * UNITY_MEDIA_RATE as i128, kCMTimeRoundingMethod_RoundHalfAwayFromZero);
+                let source = CMTimeRange {
+                    start: make_time(entry.media_time as i128, media_timescale)?,
+                    duration: make_time(source_duration, media_timescale)?,
+                };
+                CMTimeMapping { source, target }
+            };
+            timeline.push(segment)?;
+        }
+        Ok(timeline)
+    }
+
+    // Exports the timeline as edit list entries, rounding to the timescales; a timeline that starts after zero is preceded by an
+    // empty edit
+    pub fn to_edit_list(&self, movie_timescale: CMTimeScale, media_timescale: CMTimeScale) -> Result<Vec<EditListEntry>, TimelineError> {
+        if movie_timescale <= 0 || media_timescale <= 0 {
+            return Err(TimelineError::InvalidTimescale);
+        }
+        let to_u64 = |value: i128| u64::try_from(value).map_err(|_| TimelineError::Overflow);
+        let mut entries = Vec::with_capacity(self.segments.len() + 1);
+        let leading = value_in_timescale(self.start(), movie_timescale)?;
+        if leading > 0 {
+            entries.push(EditListEntry { segment_duration: to_u64(leading)?, media_time: -1, media_rate_integer: 1, media_rate_fraction: 0 });
+        }
+        for segment in &self.segments {
+            let segment_duration = to_u64(value_in_timescale(segment.target.duration, movie_timescale)?)?;
+            if segment.is_empty() {
+                entries.push(EditListEntry { segment_duration, media_time: -1, media_rate_integer: 1, media_rate_fraction: 0 });
+                continue;
+            }
+            let media_time = i64::try_from(value_in_timescale(segment.source.start, media_timescale)?).map_err(|_| TimelineError::Overflow)?;
+            let (source, target) = (segment.source.duration, segment.target.duration);
+            let rate = if source == target {
+                UNITY_MEDIA_RATE
+            } else if target.value == 0 {
+                0
+            } else {
+                let num = source.value as i128 * target.timescale as i128 * UNITY_MEDIA_RATE as i128;
+                let (rate, _) = div_round(num, source.timescale as i128 * target.value as i128, kCMTimeRoundingMethod_RoundHalfAwayFromZero);
+                i32::try_from(rate).map_err(|_| TimelineError::Overflow)?
+            };
+            entries.push(EditListEntry { segment_duration, media_time, media_rate_integer: (rate >> 16) as i16, media_rate_fraction: rate as i16 });
+        }
+        Ok(entries)
+    }
+}
@@ -0,0 +1,490 @@
+// Pure Rust implementation of the CMTime arithmetic, following the semantics documented in CMTime.h.
+
+use std::convert::TryFrom;
+
+use crate::time::{
+    div_round, gcd, kCMTimeFlags_HasBeenRounded, kCMTimeFlags_Valid, kCMTimeMaxTimescale, kCMTimeRoundingMethod_Default,
+    kCMTimeRoundingMethod_QuickTime, reduce, resolve_rounding_method, CMTime, CMTimeEpoch, CMTimeFlags, CMTimeRoundingMethod, CMTimeScale,
+    CMTimeValue,
+};
+
+// Numeric times with a non-positive timescale cannot take part in arithmetic
+#[inline]
+fn is_computable(time: &CMTime) -> bool {
+    time.is_numeric() && time.timescale > 0
+}
+
+#[inline]
+fn infinity_sign(time: &CMTime) -> i32 {
+    if time.is_positive_infinity() {
+        1
+    } else if time.is_negative_infinity() {
+        -1
+    } else {
+        0
+    }
+}
+
+#[inline]
+fn infinity(sign: i32) -> CMTime {
+    if sign < 0 {
+        CMTime::NEGATIVE_INFINITY
+    } else {
+        CMTime::POSITIVE_INFINITY
+    }
+}
+
+// Expresses the rational number of seconds num / den (den > 0) in the given timescale. When the value does not fit
+// and halve_on_overflow is set, the timescale is repeatedly halved, otherwise the result saturates to an infinity.
+pub(crate) fn make_rational(
+    num: i128,
+    den: i128,
+    mut timescale: CMTimeScale,
+    method: CMTimeRoundingMethod,
+    halve_on_overflow: bool,
+    flags: CMTimeFlags,
+    epoch: CMTimeEpoch,
+) -> CMTime {
+    let mut method = method;
+    let mut rounded = false;
+    loop {
+        let (value, inexact) = div_round(num * timescale as i128, den, method);
+        rounded |= inexact;
+        if let Ok(value) = CMTimeValue::try_from(value) {
+            let mut flags = kCMTimeFlags_Valid | flags & kCMTimeFlags_HasBeenRounded;
+            if rounded {
+                flags |= kCMTimeFlags_HasBeenRounded;
+            }
+            return CMTime { value, timescale, flags, epoch };
+        }
+        if !halve_on_overflow || timescale <= 1 {
+            return infinity(if num < 0 { -1 } else { 1 });
+        }
+        timescale /= 2;
+        method = kCMTimeRoundingMethod_Default;
+    }
+}
+
+#[inline]
+pub(crate) fn make(value: CMTimeValue, timescale: CMTimeScale, epoch: CMTimeEpoch) -> CMTime {
+    CMTime { value, timescale, flags: kCMTimeFlags_Valid, epoch }
+}
+
+pub(crate) fn make_with_seconds(seconds: f64, preferred_time_scale: CMTimeScale) -> CMTime {
+    if seconds.is_nan() || preferred_time_scale <= 0 {
+        return CMTime::INVALID;
+    }
+    if seconds.is_infinite() {
+        return infinity(if seconds < 0.0 { -1 } else { 1 });
+    }
+    let mut timescale = preferred_time_scale;
+    while timescale > 0 {
+        let exact = seconds * timescale as f64;
+        let value = exact.round();
+        if value.abs() < 9223372036854775808.0 {
+            let mut flags = kCMTimeFlags_Valid;
+            if value != exact {
+                flags |= kCMTimeFlags_HasBeenRounded;
+            }
+            return CMTime { value: value as CMTimeValue, timescale, flags, epoch: 0 };
+        }
+        timescale /= 2;
+    }
+    infinity(if seconds < 0.0 { -1 } else { 1 })
+}
+
+pub(crate) fn get_seconds(time: CMTime) -> f64 {
+    if time.is_positive_infinity() {
+        f64::INFINITY
+    } else if time.is_negative_infinity() {
+        f64::NEG_INFINITY
+    } else if time.is_numeric() {
+        time.value as f64 / time.timescale as f64
+    } else {
+        f64::NAN
+    }
+}
+
+pub(crate) fn convert_scale(time: CMTime, new_timescale: CMTimeScale, method: CMTimeRoundingMethod) -> CMTime {
+    if !time.is_numeric() {
+        return time;
+    }
+    if time.timescale <= 0 || new_timescale <= 0 {
+        return CMTime::INVALID;
+    }
+    if time.timescale == new_timescale {
+        return time;
+    }
+    let resolved = resolve_rounding_method(method, time.timescale, new_timescale);
+    let mut result = make_rational(time.value as i128, time.timescale as i128, new_timescale, resolved, false, time.flags, time.epoch);
+    // QuickTime rounding never rounds a negative time to zero
+    if method == kCMTimeRoundingMethod_QuickTime && result.is_numeric() && result.value == 0 && time.value < 0 {
+        result.value = -1;
+    }
+    result
+}
+
+fn sum(lhs: CMTime, rhs: CMTime, negate: bool) -> CMTime {
+    if !lhs.is_valid() || !rhs.is_valid() {
+        return CMTime::INVALID;
+    }
+    let lhs_infinity = infinity_sign(&lhs);
+    let rhs_infinity = if negate { -infinity_sign(&rhs) } else { infinity_sign(&rhs) };
+    if lhs_infinity != 0 || rhs_infinity != 0 {
+        if lhs_infinity != 0 && rhs_infinity != 0 && lhs_infinity != rhs_infinity {
+            return CMTime::INVALID;
+        }
+        return infinity(lhs_infinity + rhs_infinity);
+    }
+    if lhs.is_indefinite() || rhs.is_indefinite() {
+        return CMTime::INDEFINITE;
+    }
+    if !is_computable(&lhs) || !is_computable(&rhs) {
+        return CMTime::INVALID;
+    }
+    // Epoch zero times are durations and can be combined with times in any epoch
+    let epoch = if lhs.epoch == rhs.epoch {
+        0
+    } else if rhs.epoch == 0 {
+        lhs.epoch
+    } else if lhs.epoch == 0 && !negate {
+        rhs.epoch
+    } else {
+        return CMTime::INVALID;
+    };
+    let flags = (lhs.flags | rhs.flags) & kCMTimeFlags_HasBeenRounded;
+    let rhs_value = if negate { -(rhs.value as i128) } else { rhs.value as i128 };
+    if lhs.timescale == rhs.timescale {
+        let timescale = lhs.timescale;
+        return make_rational(lhs.value as i128 + rhs_value, timescale as i128, timescale, kCMTimeRoundingMethod_Default, true, flags, epoch);
+    }
+    let lcm = lhs.timescale as i128 / gcd(lhs.timescale as u128, rhs.timescale as u128) as i128 * rhs.timescale as i128;
+    let timescale = if lcm > kCMTimeMaxTimescale as i128 { kCMTimeMaxTimescale } else { lcm as CMTimeScale };
+    let num = lhs.value as i128 * rhs.timescale as i128 + rhs_value * lhs.timescale as i128;
+    let den = lhs.timescale as i128 * rhs.timescale as i128;
+    make_rational(num, den, timescale, kCMTimeRoundingMethod_Default, true, flags, epoch)
+}
+
+#[inline]
+pub(crate) fn add(lhs: CMTime, rhs: CMTime) -> CMTime {
+    sum(lhs, rhs, false)
+}
+
+#[inline]
+pub(crate) fn subtract(lhs: CMTime, rhs: CMTime) -> CMTime {
+    sum(lhs, rhs, true)
+}
+
+pub(crate) fn multiply(time: CMTime, multiplier: i32) -> CMTime {
+    multiply_by_ratio_unreduced(time, multiplier as i128, 1)
+}
+
+fn multiply_by_ratio_unreduced(time: CMTime, multiplier: i128, divisor: i128) -> CMTime {
+    if !time.is_valid() {
+        return CMTime::INVALID;
+    }
+    let infinity_sign = infinity_sign(&time);
+    if infinity_sign != 0 {
+        return infinity(if (multiplier < 0) != (divisor < 0) { -infinity_sign } else { infinity_sign });
+    }
+    if time.is_indefinite() {
+        return CMTime::INDEFINITE;
+    }
+    if !is_computable(&time) {
+        return CMTime::INVALID;
+    }
+    let (num, den) = if divisor < 0 {
+        (-(time.value as i128 * multiplier), time.timescale as i128 * -divisor)
+    } else {
+        (time.value as i128 * multiplier, time.timescale as i128 * divisor)
+    };
+    make_rational(num, den, time.timescale, kCMTimeRoundingMethod_Default, true, time.flags, time.epoch)
+}
+
+pub(crate) fn multiply_by_float64(time: CMTime, multiplier: f64) -> CMTime {
+    if !time.is_valid() || multiplier.is_nan() {
+        return CMTime::INVALID;
+    }
+    let infinity_sign = infinity_sign(&time);
+    if infinity_sign != 0 {
+        return infinity(if multiplier < 0.0 { -infinity_sign } else { infinity_sign });
+    }
+    if time.is_indefinite() {
+        return CMTime::INDEFINITE;
+    }
+    if !is_computable(&time) {
+        return CMTime::INVALID;
+    }
+    if multiplier.is_infinite() {
+        return infinity(if (multiplier < 0.0) != (time.value < 0) { -1 } else { 1 });
+    }
+    let mut timescale = time.timescale;
+    let mut exact = time.value as f64 * multiplier;
+    while timescale > 0 {
+        let value = exact.round();
+        if value.abs() < 9223372036854775808.0 {
+            let mut flags = kCMTimeFlags_Valid | time.flags & kCMTimeFlags_HasBeenRounded;
+            if value != exact {
+                flags |= kCMTimeFlags_HasBeenRounded;
+            }
+            return CMTime { value: value as CMTimeValue, timescale, flags, epoch: time.epoch };
+        }
+        let halved = timescale / 2;
+        exact = exact * halved as f64 / timescale as f64;
+        timescale = halved;
+    }
+    infinity(if exact < 0.0 { -1 } else { 1 })
+}
+
+pub(crate) fn multiply_by_ratio(time: CMTime, multiplier: i32, divisor: i32) -> CMTime {
+    if divisor == 0 {
+        return CMTime::INVALID;
+    }
+    if !is_computable(&time) {
+        return multiply_by_ratio_unreduced(time, multiplier as i128, divisor as i128);
+    }
+    scale_exact(time, time.value as i128 * multiplier as i128, time.timescale as i128 * divisor as i128)
+}
+
+// Expresses the rational number of seconds num / den (den != 0) derived from time, keeping the time's timescale when the
+// result is exact in it, otherwise using the smallest exact timescale and only rounding when there is none
+pub(crate) fn scale_exact(time: CMTime, num: i128, den: i128) -> CMTime {
+    let (mut num, mut den) = (num, den);
+    if den < 0 {
+        num = -num;
+        den = -den;
+    }
+    let (num, den) = reduce(num, den);
+    let timescale = time.timescale as i128;
+    let lcm = timescale / gcd(timescale as u128, den as u128) as i128 * den;
+    let exact_timescale = if lcm <= kCMTimeMaxTimescale as i128 {
+        Some(lcm)
+    } else if den <= kCMTimeMaxTimescale as i128 {
+        Some(den)
+    } else {
+        None
+    };
+    if let Some(exact_timescale) = exact_timescale {
+        if let Ok(value) = CMTimeValue::try_from(num * exact_timescale / den) {
+            return CMTime { value, timescale: exact_timescale as CMTimeScale, flags: time.flags, epoch: time.epoch };
+        }
+    }
+    make_rational(num, den, kCMTimeMaxTimescale, kCMTimeRoundingMethod_Default, true, time.flags, time.epoch)
+}
+
+#[inline]
+fn order_rank(time: &CMTime) -> i32 {
+    if !time.is_valid() {
+        4
+    } else if time.is_positive_infinity() {
+        3
+    } else if time.is_indefinite() {
+        2
+    } else if time.is_negative_infinity() {
+        0
+    } else if time.timescale <= 0 {
+        4
+    } else {
+        1
+    }
+}
+
+// -infinity < all finite values < indefinite < +infinity < invalid
+pub(crate) fn compare(time1: CMTime, time2: CMTime) -> i32 {
+    let rank1 = order_rank(&time1);
+    let rank2 = order_rank(&time2);
+    if rank1 != rank2 || rank1 != 1 {
+        return (rank1 - rank2).signum();
+    }
+    if time1.epoch != time2.epoch {
+        return if time1.epoch < time2.epoch { -1 } else { 1 };
+    }
+    let lhs = time1.value as i128 * time2.timescale as i128;
+    let rhs = time2.value as i128 * time1.timescale as i128;
+    (lhs - rhs).signum() as i32
+}
+
+#[inline]
+pub(crate) fn minimum(time1: CMTime, time2: CMTime) -> CMTime {
+    if compare(time1, time2) > 0 {
+        time2
+    } else {
+        time1
+    }
+}
+
+#[inline]
+pub(crate) fn maximum(time1: CMTime, time2: CMTime) -> CMTime {
+    if compare(time1, time2) < 0 {
+        time2
+    } else {
+        time1
+    }
+}
+
+pub(crate) fn absolute_value(time: CMTime) -> CMTime {
+    if time.is_negative_infinity() {
+        return CMTime::POSITIVE_INFINITY;
+    }
+    if !is_computable(&time) || time.value >= 0 {
+        return time;
+    }
+    make_rational(-(time.value as i128), time.timescale as i128, time.timescale, kCMTimeRoundingMethod_Default, true, time.flags, time.epoch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::{
+        kCMTimeRoundingMethod_RoundAwayFromZero, kCMTimeRoundingMethod_RoundHalfAwayFromZero, kCMTimeRoundingMethod_RoundTowardNegativeInfinity,
+        kCMTimeRoundingMethod_RoundTowardPositiveInfinity, kCMTimeRoundingMethod_RoundTowardZero,
+    };
+
+    fn time(value: CMTimeValue, timescale: CMTimeScale) -> CMTime {
+        make(value, timescale, 0)
+    }
+
+    fn parts(time: CMTime) -> (CMTimeValue, CMTimeScale, bool, CMTimeEpoch) {
+        (time.value, time.timescale, time.has_been_rounded(), time.epoch)
+    }
+
+    fn kind(time: CMTime) -> &'static str {
+        if !time.is_valid() {
+            "invalid"
+        } else if time.is_indefinite() {
+            "indefinite"
+        } else if time.is_positive_infinity() {
+            "+infinity"
+        } else if time.is_negative_infinity() {
+            "-infinity"
+        } else {
+            "numeric"
+        }
+    }
+
+    #[test]
+    fn rounding_methods() {
+        let cases = [
+            (kCMTimeRoundingMethod_RoundHalfAwayFromZero, 3, -3, 1, -1),
+            (kCMTimeRoundingMethod_RoundTowardZero, 3, -3, 0, 0),
+            (kCMTimeRoundingMethod_RoundAwayFromZero, 4, -4, 1, -1),
+            (kCMTimeRoundingMethod_RoundTowardPositiveInfinity, 4, -3, 1, 0),
+            (kCMTimeRoundingMethod_RoundTowardNegativeInfinity, 3, -4, 0, -1),
+        ];
+        for &(method, third, negative_third, half, negative_half) in &cases {
+            assert_eq!(parts(convert_scale(time(1, 3), 10, method)), (third, 10, true, 0), "method {}", method);
+            assert_eq!(parts(convert_scale(time(-1, 3), 10, method)), (negative_third, 10, true, 0), "method {}", method);
+            assert_eq!(parts(convert_scale(time(1, 20), 10, method)), (half, 10, true, 0), "method {}", method);
+            assert_eq!(parts(convert_scale(time(-1, 20), 10, method)), (negative_half, 10, true, 0), "method {}", method);
+            assert_eq!(parts(convert_scale(time(3, 10), 1000, method)), (300, 1000, false, 0), "method {}", method);
+        }
+    }
+
+    #[test]
+    fn quicktime_rounding() {
+        // Away from zero when converting to a larger timescale, towards zero when converting to a smaller one
+        assert_eq!(parts(convert_scale(time(1, 3), 10, kCMTimeRoundingMethod_QuickTime)), (4, 10, true, 0));
+        assert_eq!(parts(convert_scale(time(7, 30), 10, kCMTimeRoundingMethod_QuickTime)), (2, 10, true, 0));
+        assert_eq!(parts(convert_scale(time(-7, 30), 10, kCMTimeRoundingMethod_QuickTime)), (-2, 10, true, 0));
+        // A negative time never rounds to zero
+        assert_eq!(parts(convert_scale(time(-1, 30), 10, kCMTimeRoundingMethod_QuickTime)), (-1, 10, true, 0));
+        assert_eq!(parts(convert_scale(time(1, 30), 10, kCMTimeRoundingMethod_QuickTime)), (0, 10, true, 0));
+    }
+
+    #[test]
+    fn has_been_rounded() {
+        let rounded = convert_scale(time(1, 3), 10, kCMTimeRoundingMethod_Default);
+        assert!(rounded.has_been_rounded());
+        // The flag is sticky through later exact arithmetic
+        assert_eq!(parts(add(rounded, time(1, 10))), (4, 10, true, 0));
+        assert_eq!(parts(multiply(rounded, 2)), (6, 10, true, 0));
+        assert_eq!(parts(add(time(1, 3), time(1, 6))), (3, 6, false, 0));
+        // 1/3 + 1/7 in the least common multiple timescale is exact
+        assert_eq!(parts(add(time(1, 3), time(1, 7))), (10, 21, false, 0));
+        assert!(!multiply_by_float64(time(3, 10), 2.0).has_been_rounded());
+        assert_eq!(parts(multiply_by_float64(time(1, 10), 0.25)), (0, 10, true, 0));
+    }
+
+    #[test]
+    fn special_value_propagation() {
+        let numeric = time(1, 1);
+        let cases = [
+            (CMTime::INVALID, numeric, "invalid", "invalid"),
+            (CMTime::INVALID, CMTime::POSITIVE_INFINITY, "invalid", "invalid"),
+            (CMTime::INDEFINITE, numeric, "indefinite", "indefinite"),
+            (CMTime::INDEFINITE, CMTime::INVALID, "invalid", "invalid"),
+            (CMTime::POSITIVE_INFINITY, numeric, "+infinity", "+infinity"),
+            (numeric, CMTime::POSITIVE_INFINITY, "+infinity", "-infinity"),
+            (CMTime::NEGATIVE_INFINITY, numeric, "-infinity", "-infinity"),
+            (CMTime::POSITIVE_INFINITY, CMTime::POSITIVE_INFINITY, "+infinity", "invalid"),
+            (CMTime::POSITIVE_INFINITY, CMTime::NEGATIVE_INFINITY, "invalid", "+infinity"),
+            (CMTime::NEGATIVE_INFINITY, CMTime::NEGATIVE_INFINITY, "-infinity", "invalid"),
+        ];
+        for &(lhs, rhs, sum, difference) in &cases {
+            assert_eq!(kind(add(lhs, rhs)), sum, "{} + {}", kind(lhs), kind(rhs));
+            assert_eq!(kind(subtract(lhs, rhs)), difference, "{} - {}", kind(lhs), kind(rhs));
+        }
+
+        assert_eq!(kind(multiply(CMTime::POSITIVE_INFINITY, -2)), "-infinity");
+        assert_eq!(kind(multiply_by_ratio(CMTime::NEGATIVE_INFINITY, 1, -2)), "+infinity");
+        assert_eq!(kind(multiply_by_ratio(numeric, 1, 0)), "invalid");
+        assert_eq!(kind(multiply_by_float64(numeric, f64::NAN)), "invalid");
+        assert_eq!(kind(multiply_by_float64(time(-1, 1), f64::INFINITY)), "-infinity");
+        assert_eq!(kind(multiply(CMTime::INDEFINITE, 2)), "indefinite");
+        assert_eq!(kind(convert_scale(CMTime::INDEFINITE, 10, kCMTimeRoundingMethod_Default)), "indefinite");
+        assert_eq!(kind(convert_scale(numeric, 0, kCMTimeRoundingMethod_Default)), "invalid");
+        assert_eq!(kind(absolute_value(CMTime::NEGATIVE_INFINITY)), "+infinity");
+        assert_eq!(kind(make_with_seconds(f64::NAN, 600)), "invalid");
+        assert_eq!(kind(make_with_seconds(f64::NEG_INFINITY, 600)), "-infinity");
+        assert!(get_seconds(CMTime::INVALID).is_nan());
+        assert!(get_seconds(CMTime::INDEFINITE).is_nan());
+        assert_eq!(get_seconds(CMTime::POSITIVE_INFINITY), f64::INFINITY);
+    }
+
+    #[test]
+    fn ordering() {
+        let ordered =
+            [CMTime::NEGATIVE_INFINITY, time(-1, 1), time(1, 3), time(1, 2), CMTime::INDEFINITE, CMTime::POSITIVE_INFINITY, CMTime::INVALID];
+        for (i, lhs) in ordered.iter().enumerate() {
+            for (j, rhs) in ordered.iter().enumerate() {
+                assert_eq!(compare(*lhs, *rhs), (i as i32 - j as i32).signum(), "{} {}", i, j);
+            }
+        }
+        assert_eq!(compare(time(1, 2), time(500, 1000)), 0);
+        assert_eq!(compare(make(5, 1, 1), make(1, 1, 2)), -1);
+        assert_eq!(parts(minimum(time(1, 2), time(500, 1000))), (1, 2, false, 0));
+        assert_eq!(parts(maximum(time(1, 3), time(1, 2))), (1, 2, false, 0));
+    }
+
+    #[test]
+    fn overflow_halves_timescale() {
+        // The sum does not fit in timescale 1000, so the timescale is halved and the value rounded half away from zero
+        let sum = add(time(i64::MAX, 1000), time(1000, 1000));
+        assert_eq!(parts(sum), (4611686018427388404, 500, true, 0));
+        assert_eq!(parts(multiply(time(i64::MAX, 2), 2)), (i64::MAX, 1, false, 0));
+        assert_eq!(parts(make_rational(3, 1, 1000, kCMTimeRoundingMethod_Default, true, 0, 7)), (3000, 1000, false, 7));
+        let halved = make_rational(i64::MAX as i128, 1, 4, kCMTimeRoundingMethod_RoundTowardZero, true, 0, 0);
+        assert_eq!(parts(halved), (i64::MAX, 1, false, 0));
+        // Without halving, or when even timescale 1 overflows, the result saturates to an infinity
+        assert_eq!(kind(make_rational(i64::MAX as i128 + 1, 1, 2, kCMTimeRoundingMethod_Default, false, 0, 0)), "+infinity");
+        assert_eq!(kind(make_rational(-(i64::MAX as i128) * 4, 1, 2, kCMTimeRoundingMethod_Default, true, 0, 0)), "-infinity");
+        assert_eq!(kind(convert_scale(time(i64::MAX, 1), 2, kCMTimeRoundingMethod_Default)), "+infinity");
+    }
+
+    #[test]
+    fn epochs() {
+        // Epoch zero times are durations that can be added to a time in any epoch
+        assert_eq!(parts(add(make(10, 1, 3), time(5, 1))), (15, 1, false, 3));
+        assert_eq!(parts(add(time(5, 1), make(10, 1, 3))), (15, 1, false, 3));
+        assert_eq!(parts(subtract(make(10, 1, 3), time(5, 1))), (5, 1, false, 3));
+        // The difference of two times in the same epoch is a duration
+        assert_eq!(parts(subtract(make(10, 1, 3), make(4, 1, 3))), (6, 1, false, 0));
+        // A time cannot be subtracted from a duration, and times in different epochs cannot be combined
+        assert_eq!(kind(subtract(time(5, 1), make(10, 1, 3))), "invalid");
+        assert_eq!(kind(add(make(1, 1, 1), make(1, 1, 2))), "invalid");
+        assert_eq!(kind(subtract(make(1, 1, 1), make(1, 1, 2))), "invalid");
+        // Scaling keeps the epoch
+        assert_eq!(parts(multiply_by_ratio(make(3, 2, 4), 1, 3)), (1, 2, false, 4));
+        assert_eq!(parts(convert_scale(make(1, 2, 4), 10, kCMTimeRoundingMethod_Default)), (5, 10, false, 4));
+    }
+}
@@ -1,19 +1,32 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use core_foundation::{
     base::{kCFAllocatorDefault, Boolean, CFAllocatorRef, TCFType},
     dictionary::{CFDictionary, CFDictionaryRef},
     number::CFNumber,
     string::{CFString, CFStringRef},
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use crate::time::CMTime;
+use crate::time::{CMTime, CMTimeParseError, DescriptionParser};
+#[cfg(any(feature = "native-time", not(any(target_os = "macos", target_os = "ios"))))]
+use crate::time_range_native;
 
 #[repr(C, align(4))]
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CMTimeRange {
     pub start: CMTime,
     pub duration: CMTime,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     pub static kCMTimeRangeZero: CMTimeRange;
     pub static kCMTimeRangeInvalid: CMTimeRange;
@@ -26,6 +39,7 @@ extern "C" {
     pub fn CMTimeRangeContainsTimeRange(range: CMTimeRange, otherRange: CMTimeRange) -> Boolean;
     pub fn CMTimeRangeGetEnd(range: CMTimeRange) -> CMTime;
     pub fn CMTimeRangeFromRangeToRange(t: CMTime, fromRange: CMTimeRange, toRange: CMTimeRange) -> CMTime;
+    pub fn CMTimeMapTimeFromRangeToRange(t: CMTime, fromRange: CMTimeRange, toRange: CMTimeRange) -> CMTime;
     pub fn CMTimeClampToRange(time: CMTime, range: CMTimeRange) -> CMTime;
     pub fn CMTimeMapDurationFromRangeToRange(dur: CMTime, fromRange: CMTimeRange, toRange: CMTimeRange) -> CMTime;
     pub fn CMTimeFoldIntoRange(time: CMTime, foldRange: CMTimeRange) -> CMTime;
@@ -41,12 +55,14 @@ extern "C" {
 }
 
 #[repr(C, align(4))]
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CMTimeMapping {
     pub source: CMTimeRange,
     pub target: CMTimeRange,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     pub static kCMTimeMappingInvalid: CMTimeMapping;
 
@@ -65,46 +81,277 @@ extern "C" {
 impl PartialEq for CMTimeRange {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
-        unsafe { CMTimeRangeEqual(*self, *other) != 0 }
+        self.start == other.start && self.duration == other.duration
+    }
+}
+
+impl Eq for CMTimeRange {}
+
+impl Hash for CMTimeRange {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.start.hash(state);
+        self.duration.hash(state);
+    }
+}
+
+impl Display for CMTimeRange {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{{{}, {}}}", self.start, self.duration)
+    }
+}
+
+impl Display for CMTimeMapping {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{{source = {}, target = {}}}", self.source, self.target)
+    }
+}
+
+impl<'a> DescriptionParser<'a> {
+    fn time_range(&mut self) -> Result<CMTimeRange, CMTimeParseError> {
+        self.expect("{")?;
+        let start = self.time()?;
+        self.expect(",")?;
+        let duration = self.time()?;
+        self.expect("}")?;
+        Ok(CMTimeRange { start, duration })
+    }
+
+    fn time_mapping(&mut self) -> Result<CMTimeMapping, CMTimeParseError> {
+        self.expect("{")?;
+        self.expect("source")?;
+        self.expect("=")?;
+        let source = self.time_range()?;
+        self.expect(",")?;
+        self.expect("target")?;
+        self.expect("=")?;
+        let target = self.time_range()?;
+        self.expect("}")?;
+        Ok(CMTimeMapping { source, target })
+    }
+}
+
+impl FromStr for CMTimeRange {
+    type Err = CMTimeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = DescriptionParser::new(s);
+        let range = parser.time_range()?;
+        parser.finish()?;
+        Ok(range)
+    }
+}
+
+impl FromStr for CMTimeMapping {
+    type Err = CMTimeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = DescriptionParser::new(s);
+        let mapping = parser.time_mapping()?;
+        parser.finish()?;
+        Ok(mapping)
     }
 }
 
 impl CMTimeRange {
+    pub const ZERO: CMTimeRange = CMTimeRange { start: CMTime::ZERO, duration: CMTime::ZERO };
+    pub const INVALID: CMTimeRange = CMTimeRange { start: CMTime::INVALID, duration: CMTime::INVALID };
+
     #[inline]
-    pub fn make(start: CMTime, duration: CMTime) -> Self {
-        unsafe { CMTimeRangeMake(start, duration) }
+    pub fn is_valid(&self) -> bool {
+        self.start.is_valid() && self.duration.is_valid() && self.duration.epoch == 0 && self.duration.value >= 0
     }
 
     #[inline]
-    pub fn get_union(&self, other: CMTimeRange) -> CMTimeRange {
-        unsafe { CMTimeRangeGetUnion(*self, other) }
+    pub fn is_invalid(&self) -> bool {
+        !self.is_valid()
     }
 
     #[inline]
-    pub fn get_intersection(&self, other: CMTimeRange) -> CMTimeRange {
-        unsafe { CMTimeRangeGetIntersection(*self, other) }
+    pub fn is_indefinite(&self) -> bool {
+        self.is_valid() && (self.start.is_indefinite() || self.duration.is_indefinite())
     }
 
     #[inline]
-    pub fn equal(&self, other: CMTimeRange) -> bool {
-        unsafe { CMTimeRangeEqual(*self, other) != 0 }
+    pub fn is_empty(&self) -> bool {
+        self.is_valid() && self.duration == CMTime::ZERO
     }
+}
+
+cfg_if! {
+    if #[cfg(any(feature = "native-time", not(any(target_os = "macos", target_os = "ios"))))] {
+        impl CMTimeRange {
+            #[inline]
+            pub fn make(start: CMTime, duration: CMTime) -> Self {
+                CMTimeRange { start, duration }
+            }
+
+            #[inline]
+            pub fn from_time_to_time(start: CMTime, end: CMTime) -> Self {
+                time_range_native::from_time_to_time(start, end)
+            }
+
+            #[inline]
+            pub fn get_union(&self, other: CMTimeRange) -> CMTimeRange {
+                time_range_native::get_union(*self, other)
+            }
+
+            #[inline]
+            pub fn get_intersection(&self, other: CMTimeRange) -> CMTimeRange {
+                time_range_native::get_intersection(*self, other)
+            }
+
+            #[inline]
+            pub fn equal(&self, other: CMTimeRange) -> bool {
+                *self == other
+            }
+
+            #[inline]
+            pub fn contains_time(&self, time: CMTime) -> bool {
+                time_range_native::contains_time(*self, time)
+            }
+
+            #[inline]
+            pub fn contains_time_range(&self, other: CMTimeRange) -> bool {
+                time_range_native::contains_time_range(*self, other)
+            }
+
+            #[inline]
+            pub fn get_end(&self) -> CMTime {
+                time_range_native::get_end(*self)
+            }
+
+            #[inline]
+            pub fn clamp_time(&self, time: CMTime) -> CMTime {
+                time_range_native::clamp_time(time, *self)
+            }
+
+            #[inline]
+            pub fn fold_time(&self, time: CMTime) -> CMTime {
+                time_range_native::fold_time(time, *self)
+            }
+
+            #[inline]
+            pub fn map_time(&self, time: CMTime, to_range: CMTimeRange) -> CMTime {
+                time_range_native::map_time(time, *self, to_range)
+            }
+
+            #[inline]
+            pub fn map_duration(&self, duration: CMTime, to_range: CMTimeRange) -> CMTime {
+                time_range_native::map_duration(duration, *self, to_range)
+            }
+        }
+
+        impl CMTimeMapping {
+            #[inline]
+            pub fn make(source: CMTimeRange, target: CMTimeRange) -> Self {
+                CMTimeMapping { source, target }
+            }
+
+            #[inline]
+            pub fn make_empty(target: CMTimeRange) -> Self {
+                CMTimeMapping { source: CMTimeRange::INVALID, target }
+            }
+        }
+    } else {
+        impl CMTimeRange {
+            #[inline]
+            pub fn make(start: CMTime, duration: CMTime) -> Self {
+                unsafe { CMTimeRangeMake(start, duration) }
+            }
+
+            #[inline]
+            pub fn from_time_to_time(start: CMTime, end: CMTime) -> Self {
+                unsafe { CMTimeRangeFromTimeToTime(start, end) }
+            }
+
+            #[inline]
+            pub fn get_union(&self, other: CMTimeRange) -> CMTimeRange {
+                unsafe { CMTimeRangeGetUnion(*self, other) }
+            }
+
+            #[inline]
+            pub fn get_intersection(&self, other: CMTimeRange) -> CMTimeRange {
+                unsafe { CMTimeRangeGetIntersection(*self, other) }
+            }
+
+            #[inline]
+            pub fn equal(&self, other: CMTimeRange) -> bool {
+                unsafe { CMTimeRangeEqual(*self, other) != 0 }
+            }
+
+            #[inline]
+            pub fn contains_time(&self, time: CMTime) -> bool {
+                unsafe { CMTimeRangeContainsTime(*self, time) != 0 }
+            }
+
+            #[inline]
+            pub fn contains_time_range(&self, other: CMTimeRange) -> bool {
+                unsafe { CMTimeRangeContainsTimeRange(*self, other) != 0 }
+            }
+
+            #[inline]
+            pub fn get_end(&self) -> CMTime {
+                unsafe { CMTimeRangeGetEnd(*self) }
+            }
+
+            #[inline]
+            pub fn clamp_time(&self, time: CMTime) -> CMTime {
+                unsafe { CMTimeClampToRange(time, *self) }
+            }
+
+            #[inline]
+            pub fn fold_time(&self, time: CMTime) -> CMTime {
+                unsafe { CMTimeFoldIntoRange(time, *self) }
+            }
+
+            #[inline]
+            pub fn map_time(&self, time: CMTime, to_range: CMTimeRange) -> CMTime {
+                unsafe { CMTimeMapTimeFromRangeToRange(time, *self, to_range) }
+            }
+
+            #[inline]
+            pub fn map_duration(&self, duration: CMTime, to_range: CMTimeRange) -> CMTime {
+                unsafe { CMTimeMapDurationFromRangeToRange(duration, *self, to_range) }
+            }
+        }
+
+        impl CMTimeMapping {
+            #[inline]
+            pub fn make(source: CMTimeRange, target: CMTimeRange) -> Self {
+                unsafe { CMTimeMappingMake(source, target) }
+            }
+
+            #[inline]
+            pub fn make_empty(target: CMTimeRange) -> Self {
+                unsafe { CMTimeMappingMakeEmpty(target) }
+            }
+        }
+    }
+}
+
+impl CMTimeMapping {
+    pub const INVALID: CMTimeMapping = CMTimeMapping { source: CMTimeRange::INVALID, target: CMTimeRange::INVALID };
 
     #[inline]
-    pub fn contains_time(&self, time: CMTime) -> bool {
-        unsafe { CMTimeRangeContainsTime(*self, time) != 0 }
+    pub fn is_valid(&self) -> bool {
+        self.target.is_valid()
     }
 
     #[inline]
-    pub fn contains_time_range(&self, other: CMTimeRange) -> bool {
-        unsafe { CMTimeRangeContainsTimeRange(*self, other) != 0 }
+    pub fn is_invalid(&self) -> bool {
+        !self.is_valid()
     }
 
+    // An empty mapping has a valid target and no numeric source start
     #[inline]
-    pub fn get_end(&self) -> CMTime {
-        unsafe { CMTimeRangeGetEnd(*self) }
+    pub fn is_empty(&self) -> bool {
+        !self.source.start.is_numeric() && self.target.is_valid()
     }
+}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl CMTimeRange {
     #[inline]
     pub fn copy_as_dictionary(&self) -> Option<CFDictionary<CFString, CFDictionary<CFString, CFNumber>>> {
         unsafe {
@@ -140,17 +387,8 @@ impl CMTimeRange {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CMTimeMapping {
-    #[inline]
-    pub fn make(source: CMTimeRange, target: CMTimeRange) -> Self {
-        unsafe { CMTimeMappingMake(source, target) }
-    }
-
-    #[inline]
-    pub fn make_empty(target: CMTimeRange) -> Self {
-        unsafe { CMTimeMappingMakeEmpty(target) }
-    }
-
     #[inline]
     pub fn copy_as_dictionary(&self) -> Option<CFDictionary<CFString, CFDictionary<CFString, CFDictionary<CFString, CFNumber>>>> {
         unsafe {
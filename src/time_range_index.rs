@@ -0,0 +1,370 @@
+use std::{cmp::Ordering, iter::FromIterator};
+
+use crate::{time::CMTime, time_range::CMTimeRange};
+
+/// Identifies an entry of a TimeRangeIndex, ordered by the start and end of its range and then by insertion order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimeRangeKey {
+    start: CMTime,
+    end: CMTime,
+    id: u64,
+}
+
+impl TimeRangeKey {
+    #[inline]
+    pub fn start(&self) -> CMTime {
+        self.start
+    }
+
+    #[inline]
+    pub fn end(&self) -> CMTime {
+        self.end
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Node<T> {
+    key: TimeRangeKey,
+    range: CMTimeRange,
+    value: T,
+    // The latest end of any range in this subtree
+    max_end: CMTime,
+    height: i32,
+    left: Option<Box<Node<T>>>,
+    right: Option<Box<Node<T>>>,
+}
+
+type Link<T> = Option<Box<Node<T>>>;
+
+#[inline]
+fn height<T>(link: &Link<T>) -> i32 {
+    link.as_ref().map_or(0, |node| node.height)
+}
+
+impl<T> Node<T> {
+    fn new(key: TimeRangeKey, range: CMTimeRange, value: T) -> Box<Self> {
+        Box::new(Node { key, range, value, max_end: key.end, height: 1, left: None, right: None })
+    }
+
+    fn update(&mut self) {
+        self.height = 1 + height(&self.left).max(height(&self.right));
+        let mut max_end = self.key.end;
+        for child in [&self.left, &self.right].iter().copied().flatten() {
+            max_end = max_end.max(child.max_end);
+        }
+        self.max_end = max_end;
+    }
+
+    #[inline]
+    fn balance_factor(&self) -> i32 {
+        height(&self.left) - height(&self.right)
+    }
+}
+
+fn rotate_left<T>(mut node: Box<Node<T>>) -> Box<Node<T>> {
+    let mut pivot = node.right.take().expect("rotation requires a right child");
+    node.right = pivot.left.take();
+    node.update();
+    pivot.left = Some(node);
+    pivot.update();
+    pivot
+}
+
+fn rotate_right<T>(mut node: Box<Node<T>>) -> Box<Node<T>> {
+    let mut pivot = node.left.take().expect("rotation requires a left child");
+    node.left = pivot.right.take();
+    node.update();
+    pivot.right = Some(node);
+    pivot.update();
+    pivot
+}
+
+fn rebalance<T>(mut node: Box<Node<T>>) -> Box<Node<T>> {
+    node.update();
+    let balance = node.balance_factor();
+    if balance > 1 {
+        if node.left.as_ref().map_or(0, |left| left.balance_factor()) < 0 {
+            node.left = node.left.take().map(rotate_left);
+        }
+        return rotate_right(node);
+    }
+    if balance < -1 {
+        if node.right.as_ref().map_or(0, |right| right.balance_factor()) > 0 {
+            node.right = node.right.take().map(rotate_right);
+        }
+        return rotate_left(node);
+    }
+    node
+}
+
+fn insert_node<T>(link: Link<T>, new: Box<Node<T>>) -> Box<Node<T>> {
+    match link {
+        None => new,
+        Some(mut node) => {
+            if new.key < node.key {
+                node.left = Some(insert_node(node.left.take(), new));
+            } else {
+                node.right = Some(insert_node(node.right.take(), new));
+            }
+            rebalance(node)
+        }
+    }
+}
+
+// Detaches the leftmost node of the subtree, returning the remaining subtree and the detached node
+fn remove_min<T>(mut node: Box<Node<T>>) -> (Link<T>, Box<Node<T>>) {
+    match node.left.take() {
+        None => (node.right.take(), node),
+        Some(left) => {
+            let (left, min) = remove_min(left);
+            node.left = left;
+            (Some(rebalance(node)), min)
+        }
+    }
+}
+
+fn remove_node<T>(link: Link<T>, key: &TimeRangeKey) -> (Link<T>, Option<Box<Node<T>>>) {
+    let mut node = match link {
+        None => return (None, None),
+        Some(node) => node,
+    };
+    let removed = match key.cmp(&node.key) {
+        Ordering::Less => {
+            let (left, removed) = remove_node(node.left.take(), key);
+            node.left = left;
+            removed
+        }
+        Ordering::Greater => {
+            let (right, removed) = remove_node(node.right.take(), key);
+            node.right = right;
+            removed
+        }
+        Ordering::Equal => {
+            let (left, right) = (node.left.take(), node.right.take());
+            let replacement = match right {
+                None => left,
+                Some(right) => {
+                    let (right, mut successor) = remove_min(right);
+                    successor.left = left;
+                    successor.right = right;
+                    Some(rebalance(successor))
+                }
+            };
+            return (replacement, Some(node));
+        }
+    };
+    (Some(rebalance(node)), removed)
+}
+
+// Builds a balanced subtree from entries sorted by key
+fn build<T>(entries: &mut Vec<Option<(TimeRangeKey, CMTimeRange, T)>>, low: usize, high: usize) -> Link<T> {
+    if low >= high {
+        return None;
+    }
+    let middle = low + (high - low) / 2;
+    let left = build(entries, low, middle);
+    let right = build(entries, middle + 1, high);
+    let (key, range, value) = entries[middle].take().expect("each entry is used once");
+    let mut node = Node::new(key, range, value);
+    node.left = left;
+    node.right = right;
+    node.update();
+    Some(node)
+}
+
+/// An interval tree of values keyed by CMTimeRange, answering stabbing and overlap queries in O(log n + k)
+#[derive(Clone, Debug)]
+pub struct TimeRangeIndex<T> {
+    root: Link<T>,
+    len: usize,
+    next_id: u64,
+}
+
+impl<T> Default for TimeRangeIndex<T> {
+    fn default() -> Self {
+        TimeRangeIndex { root: None, len: 0, next_id: 0 }
+    }
+}
+
+impl<T> TimeRangeIndex<T> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    // Ranges that are invalid or indefinite cannot be ordered and are rejected
+    fn make_key(&mut self, range: &CMTimeRange) -> Option<TimeRangeKey> {
+        if !range.is_valid() || range.is_indefinite() {
+            return None;
+        }
+        let end = range.get_end();
+        if !end.is_valid() || end.is_indefinite() {
+            return None;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        Some(TimeRangeKey { start: range.start, end, id })
+    }
+
+    // Builds a balanced index in one pass, skipping invalid ranges
+    pub fn from_entries<I: IntoIterator<Item = (CMTimeRange, T)>>(entries: I) -> Self {
+        let mut index = TimeRangeIndex::new();
+        let mut sorted: Vec<Option<(TimeRangeKey, CMTimeRange, T)>> = Vec::new();
+        for (range, value) in entries {
+            if let Some(key) = index.make_key(&range) {
+                sorted.push(Some((key, range, value)));
+            }
+        }
+        sorted.sort_by(|a, b| a.as_ref().map(|entry| entry.0).cmp(&b.as_ref().map(|entry| entry.0)));
+        let len = sorted.len();
+        index.root = build(&mut sorted, 0, len);
+        index.len = len;
+        index
+    }
+
+    pub fn insert(&mut self, range: CMTimeRange, value: T) -> Option<TimeRangeKey> {
+        let key = self.make_key(&range)?;
+        self.root = Some(insert_node(self.root.take(), Node::new(key, range, value)));
+        self.len += 1;
+        Some(key)
+    }
+
+    pub fn remove(&mut self, key: &TimeRangeKey) -> Option<(CMTimeRange, T)> {
+        let (root, removed) = remove_node(self.root.take(), key);
+        self.root = root;
+        removed.map(|node| {
+            self.len -= 1;
+            (node.range, node.value)
+        })
+    }
+
+    fn find(&self, key: &TimeRangeKey) -> Option<&Node<T>> {
+        let mut link = &self.root;
+        while let Some(node) = link {
+            link = match key.cmp(&node.key) {
+                Ordering::Less => &node.left,
+                Ordering::Greater => &node.right,
+                Ordering::Equal => return Some(node),
+            };
+        }
+        None
+    }
+
+    #[inline]
+    pub fn get(&self, key: &TimeRangeKey) -> Option<&T> {
+        self.find(key).map(|node| &node.value)
+    }
+
+    pub fn get_mut(&mut self, key: &TimeRangeKey) -> Option<&mut T> {
+        let mut link = &mut self.root;
+        while let Some(node) = link {
+            link = match key.cmp(&node.key) {
+                Ordering::Less => &mut node.left,
+                Ordering::Greater => &mut node.right,
+                Ordering::Equal => return Some(&mut node.value),
+            };
+        }
+        None
+    }
+
+    // Entries whose range contains the time, with the start included and the end excluded, in key order
+    pub fn stab(&self, time: CMTime) -> Vec<(TimeRangeKey, &CMTimeRange, &T)> {
+        let mut result = Vec::new();
+        if time.is_valid() && !time.is_indefinite() {
+            collect_overlapping(&self.root, time, None, &mut result);
+        }
+        result
+    }
+
+    // Entries whose range shares at least one time with the given range, in key order; empty ranges never overlap
+    pub fn overlapping(&self, range: CMTimeRange) -> Vec<(TimeRangeKey, &CMTimeRange, &T)> {
+        let mut result = Vec::new();
+        if range.is_valid() && !range.is_indefinite() && !range.is_empty() {
+            let end = range.get_end();
+            if end.is_valid() && !end.is_indefinite() {
+                collect_overlapping(&self.root, range.start, Some(end), &mut result);
+            }
+        }
+        result
+    }
+
+    // All entries in key order
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(&self.root);
+        iter
+    }
+}
+
+// Visits the nodes overlapping [start, end), or containing start when end is None
+fn collect_overlapping<'a, T>(link: &'a Link<T>, start: CMTime, end: Option<CMTime>, result: &mut Vec<(TimeRangeKey, &'a CMTimeRange, &'a T)>) {
+    let node = match link {
+        Some(node) if node.max_end > start => node,
+        _ => return,
+    };
+    collect_overlapping(&node.left, start, end, result);
+    let starts_in_range = match end {
+        Some(end) => node.key.start < end,
+        None => node.key.start <= start,
+    };
+    if starts_in_range {
+        if node.key.end > start && node.key.end > node.key.start {
+            result.push((node.key, &node.range, &node.value));
+        }
+        collect_overlapping(&node.right, start, end, result);
+    }
+}
+
+pub struct Iter<'a, T> {
+    stack: Vec<&'a Node<T>>,
+}
+
+impl<'a, T> Iter<'a, T> {
+    fn push_left(&mut self, mut link: &'a Link<T>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = &node.left;
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (TimeRangeKey, &'a CMTimeRange, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(&node.right);
+        Some((node.key, &node.range, &node.value))
+    }
+}
+
+impl<T> FromIterator<(CMTimeRange, T)> for TimeRangeIndex<T> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = (CMTimeRange, T)>>(iter: I) -> Self {
+        Self::from_entries(iter)
+    }
+}
+
+impl<T> Extend<(CMTimeRange, T)> for TimeRangeIndex<T> {
+    fn extend<I: IntoIterator<Item = (CMTimeRange, T)>>(&mut self, iter: I) {
+        for (range, value) in iter {
+            self.insert(range, value);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+// Pure Rust implementation of the CMTimeRange and CMTimeMapping operations, following the semantics documented in
+// CMTimeRange.h.
+
+use crate::{time::CMTime, time_native::scale_exact, time_range::CMTimeRange};
+
+#[inline]
+fn is_computable(time: &CMTime) -> bool {
+    time.is_numeric() && time.timescale > 0
+}
+
+#[inline]
+pub(crate) fn get_end(range: CMTimeRange) -> CMTime {
+    if !range.is_valid() {
+        return CMTime::INVALID;
+    }
+    range.start + range.duration
+}
+
+#[inline]
+pub(crate) fn from_time_to_time(start: CMTime, end: CMTime) -> CMTimeRange {
+    CMTimeRange { start, duration: end - start }
+}
+
+pub(crate) fn get_union(range: CMTimeRange, other: CMTimeRange) -> CMTimeRange {
+    if !range.is_valid() || !other.is_valid() {
+        return CMTimeRange::INVALID;
+    }
+    let start = range.start.minimum(other.start);
+    let end = get_end(range).maximum(get_end(other));
+    from_time_to_time(start, end)
+}
+
+// Ranges that do not overlap intersect in kCMTimeRangeZero
+pub(crate) fn get_intersection(range: CMTimeRange, other: CMTimeRange) -> CMTimeRange {
+    if !range.is_valid() || !other.is_valid() {
+        return CMTimeRange::INVALID;
+    }
+    let start = range.start.maximum(other.start);
+    let end = get_end(range).minimum(get_end(other));
+    if end < start {
+        return CMTimeRange::ZERO;
+    }
+    from_time_to_time(start, end)
+}
+
+// The start is included and the end is excluded
+#[inline]
+pub(crate) fn contains_time(range: CMTimeRange, time: CMTime) -> bool {
+    range.is_valid() && time.is_valid() && time >= range.start && time < get_end(range)
+}
+
+#[inline]
+pub(crate) fn contains_time_range(range: CMTimeRange, other: CMTimeRange) -> bool {
+    range.is_valid() && other.is_valid() && other.start >= range.start && get_end(other) <= get_end(range)
+}
+
+// Times before the range clamp to its start and times after it to its end
+pub(crate) fn clamp_time(time: CMTime, range: CMTimeRange) -> CMTime {
+    if !time.is_valid() || !range.is_valid() || range.is_empty() {
+        return CMTime::INVALID;
+    }
+    let end = get_end(range);
+    if time < range.start {
+        range.start
+    } else if time > end {
+        end
+    } else {
+        time
+    }
+}
+
+// Wraps the time into the range, so that the range's start follows its end
+pub(crate) fn fold_time(time: CMTime, range: CMTimeRange) -> CMTime {
+    if !time.is_valid() || !range.is_valid() || range.is_empty() {
+        return CMTime::INVALID;
+    }
+    if !is_computable(&range.duration) {
+        // A range without a numeric duration never wraps
+        return if contains_time(range, time) { time } else { CMTime::INVALID };
+    }
+    let offset = time - range.start;
+    if !is_computable(&offset) {
+        return CMTime::INVALID;
+    }
+    let (offset_timescale, duration_timescale) = (offset.timescale as i128, range.duration.timescale as i128);
+    let remainder = (offset.value as i128 * duration_timescale).rem_euclid(range.duration.value as i128 * offset_timescale);
+    range.start + scale_exact(offset, remainder, offset_timescale * duration_timescale)
+}
+
+// Scales the time by to_range.duration / from_range.duration, or returns None if the durations cannot be divided
+fn scale_by_durations(time: CMTime, from_range: CMTimeRange, to_range: CMTimeRange) -> Option<CMTime> {
+    let (from, to) = (from_range.duration, to_range.duration);
+    if !is_computable(&from) || !is_computable(&to) {
+        return None;
+    }
+    if !is_computable(&time) {
+        // Infinite and indefinite times are unchanged by a positive scale
+        return Some(time);
+    }
+    let num = (time.value as i128).checked_mul(to.value as i128 * from.timescale as i128);
+    let den = (time.timescale as i128).checked_mul(to.timescale as i128 * from.value as i128);
+    match (num, den) {
+        (Some(num), Some(den)) => Some(scale_exact(time, num, den)),
+        _ => Some(time.multiply_by_float64(to.get_seconds() / from.get_seconds())),
+    }
+}
+
+pub(crate) fn map_time(time: CMTime, from_range: CMTimeRange, to_range: CMTimeRange) -> CMTime {
+    if !time.is_valid() || !from_range.is_valid() || !to_range.is_valid() || from_range.is_empty() || to_range.is_empty() {
+        return CMTime::INVALID;
+    }
+    if time.epoch != from_range.start.epoch {
+        return CMTime::INVALID;
+    }
+    let offset = time - from_range.start;
+    if from_range.duration.is_positive_infinity() && to_range.duration.is_positive_infinity() {
+        // Ranges of unbounded length are offset but not scaled
+        return offset + to_range.start;
+    }
+    match scale_by_durations(offset, from_range, to_range) {
+        Some(scaled) => scaled + to_range.start,
+        None => CMTime::INVALID,
+    }
+}
+
+pub(crate) fn map_duration(duration: CMTime, from_range: CMTimeRange, to_range: CMTimeRange) -> CMTime {
+    if !duration.is_valid() || duration.epoch != 0 || !from_range.is_valid() || !to_range.is_valid() || from_range.is_empty() || to_range.is_empty() {
+        return CMTime::INVALID;
+    }
+    if from_range.duration.is_positive_infinity() && to_range.duration.is_positive_infinity() {
+        return duration;
+    }
+    scale_by_durations(duration, from_range, to_range).unwrap_or(CMTime::INVALID)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: i64, timescale: i32) -> CMTime {
+        CMTime::make(value, timescale)
+    }
+
+    fn range(start: i64, duration: i64) -> CMTimeRange {
+        CMTimeRange { start: time(start, 1), duration: time(duration, 1) }
+    }
+
+    fn unbounded(start: i64) -> CMTimeRange {
+        CMTimeRange { start: time(start, 1), duration: CMTime::POSITIVE_INFINITY }
+    }
+
+    fn indefinite(start: i64) -> CMTimeRange {
+        CMTimeRange { start: time(start, 1), duration: CMTime::INDEFINITE }
+    }
+
+    #[test]
+    fn union() {
+        assert_eq!(get_union(range(0, 2), range(5, 1)), range(0, 6));
+        assert_eq!(get_union(range(3, 2), range(0, 1)), range(0, 5));
+        // An empty range still extends the union to its start
+        assert_eq!(get_union(range(0, 2), range(10, 0)), range(0, 10));
+        assert_eq!(get_union(range(0, 2), unbounded(5)), unbounded(0));
+        assert!(get_union(range(0, 2), indefinite(1)).duration.is_indefinite());
+        assert!(get_union(range(0, 2), CMTimeRange::INVALID).is_invalid());
+        assert!(get_union(CMTimeRange { start: time(0, 1), duration: time(-1, 1) }, range(0, 1)).is_invalid());
+    }
+
+    #[test]
+    fn intersection() {
+        assert_eq!(get_intersection(range(0, 5), range(3, 5)), range(3, 2));
+        assert_eq!(get_intersection(range(0, 5), unbounded(3)), range(3, 2));
+        // Ranges that do not overlap intersect in kCMTimeRangeZero, and touching ranges in an empty range
+        let disjoint = get_intersection(range(0, 2), range(5, 1));
+        assert_eq!((disjoint.start, disjoint.duration), (CMTime::ZERO, CMTime::ZERO));
+        let touching = get_intersection(range(0, 2), range(2, 1));
+        assert!(touching.is_empty());
+        assert_eq!(touching.start, time(2, 1));
+        assert!(get_intersection(range(0, 2), CMTimeRange::INVALID).is_invalid());
+    }
+
+    #[test]
+    fn contains() {
+        assert!(contains_time(range(1, 2), time(1, 1)));
+        assert!(!contains_time(range(1, 2), time(3, 1)));
+        assert!(!contains_time(range(1, 0), time(1, 1)));
+        assert!(!contains_time(range(1, 2), CMTime::INVALID));
+        assert!(contains_time(unbounded(1), time(1000, 1)));
+        assert!(contains_time_range(range(0, 5), range(1, 4)));
+        assert!(!contains_time_range(range(0, 5), range(1, 5)));
+        assert!(!contains_time_range(range(0, 5), CMTimeRange::INVALID));
+        assert!(get_end(CMTimeRange::INVALID).is_invalid());
+        assert!(get_end(indefinite(0)).is_indefinite());
+    }
+
+    #[test]
+    fn clamp() {
+        assert_eq!(clamp_time(time(-1, 1), range(0, 5)), time(0, 1));
+        assert_eq!(clamp_time(time(7, 2), range(0, 5)), time(7, 2));
+        assert_eq!(clamp_time(time(6, 1), range(0, 5)), time(5, 1));
+        assert_eq!(clamp_time(CMTime::POSITIVE_INFINITY, range(0, 5)), time(5, 1));
+        assert!(clamp_time(CMTime::POSITIVE_INFINITY, unbounded(0)).is_positive_infinity());
+        // An empty range has nothing to clamp to
+        assert!(clamp_time(time(1, 1), range(1, 0)).is_invalid());
+        assert!(clamp_time(CMTime::INVALID, range(0, 5)).is_invalid());
+        assert!(clamp_time(time(1, 1), CMTimeRange::INVALID).is_invalid());
+    }
+
+    #[test]
+    fn fold() {
+        assert_eq!(fold_time(time(7, 1), range(2, 3)), time(4, 1));
+        assert_eq!(fold_time(time(0, 1), range(2, 3)), time(3, 1));
+        assert_eq!(fold_time(time(5, 1), range(2, 3)), time(2, 1));
+        assert_eq!(fold_time(time(13, 2), range(2, 3)), time(7, 2));
+        assert!(fold_time(time(1, 1), range(1, 0)).is_invalid());
+        assert!(fold_time(CMTime::POSITIVE_INFINITY, range(2, 3)).is_invalid());
+        // A range without a numeric duration never wraps
+        assert_eq!(fold_time(time(100, 1), unbounded(2)), time(100, 1));
+        assert!(fold_time(time(1, 1), unbounded(2)).is_invalid());
+        assert!(fold_time(time(1, 1), indefinite(2)).is_invalid());
+    }
+
+    #[test]
+    fn map_time_between_ranges() {
+        assert_eq!(map_time(time(5, 1), range(0, 10), range(100, 20)), time(110, 1));
+        assert_eq!(map_time(time(1, 3), range(0, 1), range(0, 2)), time(2, 3));
+        // Times outside the source range are extrapolated
+        assert_eq!(map_time(time(-1, 1), range(0, 10), range(100, 20)), time(98, 1));
+        // The time must be in the epoch of the source range
+        let from = CMTimeRange { start: CMTime::make_with_epoch(0, 1, 2), duration: time(10, 1) };
+        let mapped = map_time(CMTime::make_with_epoch(5, 1, 2), from, range(100, 20));
+        assert_eq!((mapped.value, mapped.timescale, mapped.epoch), (110, 1, 0));
+        assert!(map_time(time(5, 1), from, range(100, 20)).is_invalid());
+        // Infinite offsets stay infinite, and unbounded ranges are offset but not scaled
+        assert!(map_time(CMTime::POSITIVE_INFINITY, range(0, 10), range(100, 20)).is_positive_infinity());
+        assert!(map_time(CMTime::NEGATIVE_INFINITY, range(0, 10), range(100, 20)).is_negative_infinity());
+        assert_eq!(map_time(time(5, 1), unbounded(0), unbounded(100)), time(105, 1));
+        assert!(map_time(time(5, 1), unbounded(0), range(100, 20)).is_invalid());
+        assert!(map_time(time(5, 1), indefinite(0), range(100, 20)).is_invalid());
+        assert!(map_time(time(5, 1), range(0, 0), range(100, 20)).is_invalid());
+        assert!(map_time(time(5, 1), range(0, 10), range(100, 0)).is_invalid());
+        assert!(map_time(CMTime::INVALID, range(0, 10), range(100, 20)).is_invalid());
+    }
+
+    #[test]
+    fn map_duration_between_ranges() {
+        assert_eq!(map_duration(time(5, 1), range(0, 10), range(100, 20)), time(10, 1));
+        assert_eq!(map_duration(time(1, 1), range(0, 3), range(0, 1)), time(1, 3));
+        assert!(map_duration(CMTime::POSITIVE_INFINITY, range(0, 10), range(100, 20)).is_positive_infinity());
+        assert!(map_duration(CMTime::INDEFINITE, range(0, 10), range(100, 20)).is_indefinite());
+        assert_eq!(map_duration(time(5, 1), unbounded(0), unbounded(100)), time(5, 1));
+        // Durations are in epoch zero
+        assert!(map_duration(CMTime::make_with_epoch(5, 1, 1), range(0, 10), range(100, 20)).is_invalid());
+        assert!(map_duration(time(5, 1), indefinite(0), range(100, 20)).is_invalid());
+        assert!(map_duration(time(5, 1), range(0, 0), range(100, 20)).is_invalid());
+        assert!(map_duration(time(5, 1), CMTimeRange::INVALID, range(100, 20)).is_invalid());
+    }
+}
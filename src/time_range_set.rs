@@ -0,0 +1,381 @@
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    iter::FromIterator,
+};
+
+use crate::{
+    time::{reduce, CMTime, CMTimeEpoch},
+    time_range::CMTimeRange,
+};
+
+/// A set of times stored as sorted, disjoint and coalesced half-open ranges
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CMTimeRangeSet {
+    // Start and end of each range
+    ranges: Vec<(Endpoint, Endpoint)>,
+}
+
+// A time as an exact number of seconds, so that a range end stays exact when the start and duration timescales have
+// no common timescale that CMTime can represent
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum ExactTime {
+    NegativeInfinity,
+    // The epoch, and the seconds as a numerator and a positive denominator with no common factor
+    Finite(CMTimeEpoch, i128, i128),
+    PositiveInfinity,
+}
+
+impl ExactTime {
+    #[inline]
+    fn new(epoch: CMTimeEpoch, num: i128, den: i128) -> Self {
+        let (num, den) = reduce(num, den);
+        ExactTime::Finite(epoch, num, den)
+    }
+
+    // None for invalid and indefinite times
+    fn from_time(time: CMTime) -> Option<Self> {
+        if time.is_positive_infinity() {
+            Some(ExactTime::PositiveInfinity)
+        } else if time.is_negative_infinity() {
+            Some(ExactTime::NegativeInfinity)
+        } else if time.is_numeric() && time.timescale > 0 {
+            Some(Self::new(time.epoch, time.value as i128, time.timescale as i128))
+        } else {
+            None
+        }
+    }
+
+    // None where CMTimeAdd gives an invalid or indefinite time; an epoch zero time is a duration and keeps this
+    // time's epoch
+    fn checked_add(self, other: ExactTime) -> Option<Self> {
+        match (self, other) {
+            (ExactTime::Finite(epoch, a, b), ExactTime::Finite(other_epoch, c, d)) if other_epoch == 0 || epoch == other_epoch => {
+                Some(Self::new(epoch, a * d + c * b, b * d))
+            }
+            (ExactTime::Finite(..), ExactTime::Finite(..)) => None,
+            (ExactTime::Finite(..), infinity) | (infinity, ExactTime::Finite(..)) => Some(infinity),
+            _ if self == other => Some(self),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn rank(&self) -> u8 {
+        match self {
+            ExactTime::NegativeInfinity => 0,
+            ExactTime::Finite(..) => 1,
+            ExactTime::PositiveInfinity => 2,
+        }
+    }
+}
+
+impl PartialOrd for ExactTime {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ExactTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (ExactTime::Finite(epoch, a, b), ExactTime::Finite(other_epoch, c, d)) => {
+                // Whole seconds are compared first so that the cross products of the remainders cannot overflow
+                epoch
+                    .cmp(other_epoch)
+                    .then_with(|| a.div_euclid(*b).cmp(&c.div_euclid(*d)))
+                    .then_with(|| (a.rem_euclid(*b) * d).cmp(&(c.rem_euclid(*d) * b)))
+            }
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+// A start or end of a range, ordered by its exact time; the CMTime reports it, and for an end is the sum of the
+// range's start and duration, which CMTime rounds when it cannot represent it
+#[derive(Clone, Copy, Debug)]
+struct Endpoint {
+    time: CMTime,
+    exact: ExactTime,
+}
+
+impl Endpoint {
+    #[inline]
+    fn new(time: CMTime) -> Option<Self> {
+        Some(Endpoint { time, exact: ExactTime::from_time(time)? })
+    }
+}
+
+impl PartialEq for Endpoint {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.exact == other.exact
+    }
+}
+
+impl Eq for Endpoint {}
+
+impl PartialOrd for Endpoint {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Endpoint {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.exact.cmp(&other.exact)
+    }
+}
+
+impl Hash for Endpoint {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.exact.hash(state);
+    }
+}
+
+// Ranges that are invalid, indefinite or empty contribute no times
+#[inline]
+fn bounds_of(range: &CMTimeRange) -> Option<(Endpoint, Endpoint)> {
+    if !range.is_valid() || range.is_indefinite() || range.is_empty() {
+        return None;
+    }
+    let start = Endpoint::new(range.start)?;
+    let end = Endpoint { time: range.start + range.duration, exact: start.exact.checked_add(ExactTime::from_time(range.duration)?)? };
+    if end.time.is_valid() && start < end {
+        Some((start, end))
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn make_range(start: Endpoint, end: Endpoint) -> CMTimeRange {
+    CMTimeRange { start: start.time, duration: end.time - start.time }
+}
+
+impl CMTimeRangeSet {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = CMTimeRange> + '_ {
+        self.ranges.iter().map(|(start, end)| make_range(*start, *end))
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<CMTimeRange> {
+        self.ranges.get(index).map(|(start, end)| make_range(*start, *end))
+    }
+
+    // The smallest range that covers every range in the set
+    pub fn bounds(&self) -> Option<CMTimeRange> {
+        match (self.ranges.first(), self.ranges.last()) {
+            (Some((start, _)), Some((_, end))) => Some(make_range(*start, *end)),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, range: CMTimeRange) {
+        if let Some((start, end)) = bounds_of(&range) {
+            self.insert_bounds(start, end);
+        }
+    }
+
+    // Ranges that overlap or touch the new range are merged into it
+    fn insert_bounds(&mut self, start: Endpoint, end: Endpoint) {
+        let first = self.ranges.partition_point(|(_, range_end)| *range_end < start);
+        let last = self.ranges.partition_point(|(range_start, _)| *range_start <= end);
+        let (mut start, mut end) = (start, end);
+        if first < last {
+            start = start.min(self.ranges[first].0);
+            end = end.max(self.ranges[last - 1].1);
+        }
+        self.ranges.splice(first..last, Some((start, end)));
+    }
+
+    pub fn remove(&mut self, range: CMTimeRange) {
+        if let Some((start, end)) = bounds_of(&range) {
+            self.remove_bounds(start, end);
+        }
+    }
+
+    // Ranges that partially overlap the removed range are trimmed, and a range that covers it is split in two
+    fn remove_bounds(&mut self, start: Endpoint, end: Endpoint) {
+        let first = self.ranges.partition_point(|(_, range_end)| *range_end <= start);
+        let last = self.ranges.partition_point(|(range_start, _)| *range_start < end);
+        if first >= last {
+            return;
+        }
+        let mut pieces = Vec::with_capacity(2);
+        let (first_start, _) = self.ranges[first];
+        let (_, last_end) = self.ranges[last - 1];
+        if first_start < start {
+            pieces.push((first_start, start));
+        }
+        if last_end > end {
+            pieces.push((end, last_end));
+        }
+        self.ranges.splice(first..last, pieces);
+    }
+
+    // Index of the range containing the time, if any
+    fn position(&self, time: CMTime) -> Option<usize> {
+        let time = Endpoint::new(time)?;
+        let index = self.ranges.partition_point(|(_, end)| *end <= time);
+        match self.ranges.get(index) {
+            Some((start, _)) if *start <= time => Some(index),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn contains_time(&self, time: CMTime) -> bool {
+        self.position(time).is_some()
+    }
+
+    #[inline]
+    pub fn range_containing(&self, time: CMTime) -> Option<CMTimeRange> {
+        self.position(time).and_then(|index| self.get(index))
+    }
+
+    pub fn contains_time_range(&self, range: CMTimeRange) -> bool {
+        match bounds_of(&range) {
+            Some((start, end)) => self.position(start.time).map(|index| self.ranges[index].1 >= end).unwrap_or(false),
+            None => false,
+        }
+    }
+
+    // The first uncovered range at or after the time; the gap after the last range has an infinite duration
+    pub fn next_gap_after(&self, time: CMTime) -> Option<CMTimeRange> {
+        let time = Endpoint::new(time)?;
+        let (start, index) = match self.position(time.time) {
+            Some(index) => (self.ranges[index].1, index + 1),
+            None => (time, self.ranges.partition_point(|(_, end)| *end <= time)),
+        };
+        if start.exact == ExactTime::PositiveInfinity {
+            return None;
+        }
+        Some(match self.ranges.get(index) {
+            Some((next_start, _)) => make_range(start, *next_start),
+            None => CMTimeRange { start: start.time, duration: CMTime::POSITIVE_INFINITY },
+        })
+    }
+
+    pub fn union(&self, other: &CMTimeRangeSet) -> CMTimeRangeSet {
+        let mut result = self.clone();
+        for (start, end) in &other.ranges {
+            result.insert_bounds(*start, *end);
+        }
+        result
+    }
+
+    pub fn intersection(&self, other: &CMTimeRangeSet) -> CMTimeRangeSet {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (start1, end1) = self.ranges[i];
+            let (start2, end2) = other.ranges[j];
+            let (start, end) = (start1.max(start2), end1.min(end2));
+            if start < end {
+                ranges.push((start, end));
+            }
+            if end1 < end2 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        CMTimeRangeSet { ranges }
+    }
+
+    pub fn difference(&self, other: &CMTimeRangeSet) -> CMTimeRangeSet {
+        let mut result = self.clone();
+        for (start, end) in &other.ranges {
+            result.remove_bounds(*start, *end);
+        }
+        result
+    }
+
+    // The uncovered parts of the bounding range
+    pub fn complement(&self, bounds: CMTimeRange) -> CMTimeRangeSet {
+        let mut result: CMTimeRangeSet = Some(bounds).into_iter().collect();
+        for (start, end) in &self.ranges {
+            result.remove_bounds(*start, *end);
+        }
+        result
+    }
+}
+
+impl FromIterator<CMTimeRange> for CMTimeRangeSet {
+    fn from_iter<I: IntoIterator<Item = CMTimeRange>>(iter: I) -> Self {
+        let mut set = CMTimeRangeSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<CMTimeRange> for CMTimeRangeSet {
+    fn extend<I: IntoIterator<Item = CMTimeRange>>(&mut self, iter: I) {
+        for range in iter {
+            self.insert(range);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_in_nonzero_epoch() {
+        let range = |start, duration, epoch| CMTimeRange { start: CMTime::make_with_epoch(start, 1, epoch), duration: CMTime::make(duration, 1) };
+        let mut set = CMTimeRangeSet::new();
+        set.insert(range(10, 5, 1));
+        assert_eq!(set.len(), 1);
+        let bounds = set.bounds().unwrap();
+        assert_eq!(bounds.start.epoch, 1);
+        assert_eq!((bounds.start + bounds.duration).epoch, 1);
+        assert!(set.contains_time(CMTime::make_with_epoch(12, 1, 1)));
+        assert!(!set.contains_time(CMTime::make_with_epoch(12, 1, 0)));
+        assert!(set.contains_time_range(range(11, 3, 1)));
+        assert!(!set.contains_time_range(range(11, 3, 2)));
+
+        // Ranges in other epochs are ordered after the earlier epoch and never merge with it
+        set.insert(range(0, 5, 2));
+        set.insert(range(0, 100, 0));
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.get(0).unwrap().start.epoch, 0);
+        assert_eq!(set.get(2).unwrap().start.epoch, 2);
+
+        set.remove(range(11, 2, 1));
+        assert_eq!(set.len(), 4);
+        assert_eq!(set.get(1).unwrap(), range(10, 1, 1));
+        assert_eq!(set.get(2).unwrap(), range(13, 2, 1));
+        assert!(!set.contains_time(CMTime::make_with_epoch(12, 1, 1)));
+        assert_eq!(set.next_gap_after(CMTime::make_with_epoch(10, 1, 1)), Some(range(11, 2, 1)));
+
+        set.remove(range(0, 20, 1));
+        assert_eq!(set.len(), 2);
+        assert!(set.iter().all(|range| range.start.epoch != 1));
+    }
+}
@@ -0,0 +1,356 @@
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use crate::format_description::CMTimeCodeFormatDescription;
+use crate::{
+    format_description::{kCMTimeCodeFlag_24HourMax, kCMTimeCodeFlag_DropFrame, kCMTimeCodeFlag_NegTimesOK},
+    time::{div_round, kCMTimeFlags_Valid, kCMTimeRoundingMethod_RoundTowardNegativeInfinity, CMTime},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimecodeError {
+    InvalidFrameDuration,
+    InvalidFrameQuanta,
+    UnsupportedDropFrameRate,
+    NonNumericTime,
+    NegativeNotAllowed,
+    InvalidComponents,
+    Overflow,
+    InvalidFormat,
+}
+
+impl Display for TimecodeError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TimecodeError::InvalidFrameDuration => write!(f, "frame duration must be a positive numeric time"),
+            TimecodeError::InvalidFrameQuanta => write!(f, "frame quanta must be positive"),
+            TimecodeError::UnsupportedDropFrameRate => write!(f, "drop frame timecode requires a frame quanta that is a multiple of 30"),
+            TimecodeError::NonNumericTime => write!(f, "time is not numeric"),
+            TimecodeError::NegativeNotAllowed => write!(f, "negative timecodes are not allowed"),
+            TimecodeError::InvalidComponents => write!(f, "timecode components are out of range"),
+            TimecodeError::Overflow => write!(f, "timecode is out of range"),
+            TimecodeError::InvalidFormat => write!(f, "invalid timecode string"),
+        }
+    }
+}
+
+impl Error for TimecodeError {}
+
+/// The hours, minutes, seconds and frames of a SMPTE timecode
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TimecodeComponents {
+    pub negative: bool,
+    pub hours: u32,
+    pub minutes: u32,
+    pub seconds: u32,
+    pub frames: u32,
+    pub drop_frame: bool,
+}
+
+impl Display for TimecodeComponents {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let sign = if self.negative { "-" } else { "" };
+        let separator = if self.drop_frame { ';' } else { ':' };
+        write!(f, "{}{:02}:{:02}:{:02}{}{:02}", sign, self.hours, self.minutes, self.seconds, separator, self.frames)
+    }
+}
+
+impl FromStr for TimecodeComponents {
+    type Err = TimecodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let mut fields = [0u32; 4];
+        let mut count = 0;
+        for (index, field) in s.split([':', ';']).enumerate() {
+            if index >= fields.len() || field.is_empty() || !field.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(TimecodeError::InvalidFormat);
+            }
+            if index > 0 && index < 3 && field.len() != 2 {
+                return Err(TimecodeError::InvalidFormat);
+            }
+            fields[index] = field.parse().map_err(|_| TimecodeError::InvalidFormat)?;
+            count += 1;
+        }
+        if count != fields.len() {
+            return Err(TimecodeError::InvalidFormat);
+        }
+        let drop_frame = s.contains(';');
+        Ok(TimecodeComponents { negative, hours: fields[0], minutes: fields[1], seconds: fields[2], frames: fields[3], drop_frame })
+    }
+}
+
+/// Converts between CMTime, frame counts and timecode components for a timecode track
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Timecode {
+    frame_duration: CMTime,
+    frame_quanta: u32,
+    flags: u32,
+}
+
+impl Timecode {
+    // is_multiple_of needs a newer toolchain than this crate supports
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn new(frame_duration: CMTime, frame_quanta: u32, flags: u32) -> Result<Self, TimecodeError> {
+        if !frame_duration.is_numeric() || frame_duration.timescale <= 0 || frame_duration.value <= 0 {
+            return Err(TimecodeError::InvalidFrameDuration);
+        }
+        if frame_quanta == 0 {
+            return Err(TimecodeError::InvalidFrameQuanta);
+        }
+        if flags & kCMTimeCodeFlag_DropFrame != 0 && frame_quanta % 30 != 0 {
+            return Err(TimecodeError::UnsupportedDropFrameRate);
+        }
+        Ok(Timecode { frame_duration, frame_quanta, flags })
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn from_format_description(format_description: &CMTimeCodeFormatDescription) -> Result<Self, TimecodeError> {
+        Self::new(format_description.get_frame_duration(), format_description.get_frame_quanta(), format_description.get_time_code_flags())
+    }
+
+    #[inline]
+    pub fn frame_duration(&self) -> CMTime {
+        self.frame_duration
+    }
+
+    #[inline]
+    pub fn frame_quanta(&self) -> u32 {
+        self.frame_quanta
+    }
+
+    #[inline]
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    #[inline]
+    pub fn is_drop_frame(&self) -> bool {
+        self.flags & kCMTimeCodeFlag_DropFrame != 0
+    }
+
+    #[inline]
+    fn wraps_at_24_hours(&self) -> bool {
+        self.flags & kCMTimeCodeFlag_24HourMax != 0
+    }
+
+    #[inline]
+    fn allows_negative(&self) -> bool {
+        self.flags & kCMTimeCodeFlag_NegTimesOK != 0
+    }
+
+    // Frames skipped at the start of every minute not divisible by ten
+    #[inline]
+    fn dropped_frames_per_minute(&self) -> i64 {
+        if self.is_drop_frame() {
+            self.frame_quanta as i64 / 15
+        } else {
+            0
+        }
+    }
+
+    #[inline]
+    fn frames_per_ten_minutes(&self) -> i64 {
+        self.frame_quanta as i64 * 600 - self.dropped_frames_per_minute() * 9
+    }
+
+    #[inline]
+    pub fn frames_per_day(&self) -> i64 {
+        self.frames_per_ten_minutes() * 6 * 24
+    }
+
+    pub fn frame_number_from_time(&self, time: CMTime) -> Result<i64, TimecodeError> {
+        if !time.is_numeric() || time.timescale <= 0 {
+            return Err(TimecodeError::NonNumericTime);
+        }
+        let num = time.value as i128 * self.frame_duration.timescale as i128;
+        let den = time.timescale as i128 * self.frame_duration.value as i128;
+        let (frame_number, _) = div_round(num, den, kCMTimeRoundingMethod_RoundTowardNegativeInfinity);
+        i64::try_from(frame_number).map_err(|_| TimecodeError::Overflow)
+    }
+
+    pub fn time_from_frame_number(&self, frame_number: i64) -> Result<CMTime, TimecodeError> {
+        let value = frame_number.checked_mul(self.frame_duration.value).ok_or(TimecodeError::Overflow)?;
+        Ok(CMTime { value, timescale: self.frame_duration.timescale, flags: kCMTimeFlags_Valid, epoch: 0 })
+    }
+
+    pub fn components_from_frame_number(&self, frame_number: i64) -> Result<TimecodeComponents, TimecodeError> {
+        let mut negative = frame_number < 0;
+        let mut frame_number = frame_number.checked_abs().ok_or(TimecodeError::Overflow)?;
+        if self.wraps_at_24_hours() {
+            frame_number %= self.frames_per_day();
+            if negative && !self.allows_negative() {
+                frame_number = (self.frames_per_day() - frame_number) % self.frames_per_day();
+                negative = false;
+            }
+        }
+        if negative && !self.allows_negative() {
+            return Err(TimecodeError::NegativeNotAllowed);
+        }
+        let drop = self.dropped_frames_per_minute();
+        if drop > 0 {
+            // Add back the frame numbers skipped by drop frame counting
+            let frames_per_ten_minutes = self.frames_per_ten_minutes();
+            let frames_per_minute = self.frame_quanta as i64 * 60 - drop;
+            let tens = frame_number / frames_per_ten_minutes;
+            let remainder = frame_number % frames_per_ten_minutes;
+            let mut skipped = (drop * 9).checked_mul(tens);
+            if remainder > drop {
+                skipped = skipped.and_then(|skipped| skipped.checked_add(drop * ((remainder - drop) / frames_per_minute)));
+            }
+            frame_number = skipped.and_then(|skipped| frame_number.checked_add(skipped)).ok_or(TimecodeError::Overflow)?;
+        }
+        let quanta = self.frame_quanta as i64;
+        let hours = frame_number / (quanta * 3600);
+        Ok(TimecodeComponents {
+            negative: negative && frame_number != 0,
+            hours: u32::try_from(hours).map_err(|_| TimecodeError::Overflow)?,
+            minutes: (frame_number / (quanta * 60) % 60) as u32,
+            seconds: (frame_number / quanta % 60) as u32,
+            frames: (frame_number % quanta) as u32,
+            drop_frame: drop > 0,
+        })
+    }
+
+    // The separator must match drop frame counting; is_multiple_of needs a newer toolchain than this crate supports
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn frame_number_from_components(&self, components: &TimecodeComponents) -> Result<i64, TimecodeError> {
+        if components.drop_frame != self.is_drop_frame() {
+            return Err(TimecodeError::InvalidFormat);
+        }
+        let drop = self.dropped_frames_per_minute();
+        if components.minutes >= 60 || components.seconds >= 60 || components.frames >= self.frame_quanta {
+            return Err(TimecodeError::InvalidComponents);
+        }
+        if self.wraps_at_24_hours() && components.hours >= 24 {
+            return Err(TimecodeError::InvalidComponents);
+        }
+        if drop > 0 && components.seconds == 0 && components.minutes % 10 != 0 && (components.frames as i64) < drop {
+            return Err(TimecodeError::InvalidComponents);
+        }
+        let quanta = self.frame_quanta as i64;
+        let total_minutes = components.hours as i64 * 60 + components.minutes as i64;
+        let frame_number = (total_minutes * 60 + components.seconds as i64)
+            .checked_mul(quanta)
+            .and_then(|frames| frames.checked_add(components.frames as i64))
+            .and_then(|frames| frames.checked_sub(drop.checked_mul(total_minutes - total_minutes / 10)?))
+            .ok_or(TimecodeError::Overflow)?;
+        if components.negative && frame_number != 0 {
+            if !self.allows_negative() {
+                return Err(TimecodeError::NegativeNotAllowed);
+            }
+            Ok(-frame_number)
+        } else {
+            Ok(frame_number)
+        }
+    }
+
+    #[inline]
+    pub fn components_from_time(&self, time: CMTime) -> Result<TimecodeComponents, TimecodeError> {
+        self.components_from_frame_number(self.frame_number_from_time(time)?)
+    }
+
+    #[inline]
+    pub fn time_from_components(&self, components: &TimecodeComponents) -> Result<CMTime, TimecodeError> {
+        self.time_from_frame_number(self.frame_number_from_components(components)?)
+    }
+
+    #[inline]
+    pub fn format_time(&self, time: CMTime) -> Result<String, TimecodeError> {
+        Ok(self.components_from_time(time)?.to_string())
+    }
+
+    #[inline]
+    pub fn parse_time(&self, s: &str) -> Result<CMTime, TimecodeError> {
+        self.time_from_components(&s.parse()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drop_frame() -> Timecode {
+        Timecode::new(CMTime::make(1001, 30000), 30, kCMTimeCodeFlag_DropFrame).unwrap()
+    }
+
+    fn format(timecode: &Timecode, frame_number: i64) -> String {
+        timecode.components_from_frame_number(frame_number).unwrap().to_string()
+    }
+
+    fn frame_number(timecode: &Timecode, s: &str) -> Result<i64, TimecodeError> {
+        timecode.frame_number_from_components(&s.parse()?)
+    }
+
+    #[test]
+    fn drop_frame_minute_boundaries() {
+        let timecode = drop_frame();
+        assert_eq!(frame_number(&timecode, "00:00:59;29"), Ok(1799));
+        assert_eq!(format(&timecode, 1800), "00:01:00;02");
+        assert_eq!(frame_number(&timecode, "00:01:00;02"), Ok(1800));
+        assert_eq!(frame_number(&timecode, "00:09:59;29"), Ok(17981));
+        assert_eq!(format(&timecode, 17982), "00:10:00;00");
+        assert_eq!(frame_number(&timecode, "00:10:00;00"), Ok(17982));
+        assert_eq!(format(&timecode, 17982 + 1800), "00:11:00;02");
+        // The frame numbers skipped at the start of a minute do not exist
+        assert_eq!(frame_number(&timecode, "00:01:00;00"), Err(TimecodeError::InvalidComponents));
+        assert_eq!(frame_number(&timecode, "00:01:00;01"), Err(TimecodeError::InvalidComponents));
+        for frame_number in (0..timecode.frames_per_ten_minutes() * 2).step_by(7) {
+            let components = timecode.components_from_frame_number(frame_number).unwrap();
+            assert_eq!(timecode.frame_number_from_components(&components), Ok(frame_number));
+        }
+        assert_eq!(timecode.time_from_components(&"00:01:00;02".parse().unwrap()), Ok(CMTime::make(1800 * 1001, 30000)));
+        assert_eq!(timecode.format_time(CMTime::make(1800 * 1001, 30000)).unwrap(), "00:01:00;02");
+    }
+
+    #[test]
+    fn wraps_at_24_hours() {
+        let timecode = Timecode::new(CMTime::make(1, 25), 25, kCMTimeCodeFlag_24HourMax).unwrap();
+        assert_eq!(timecode.frames_per_day(), 25 * 86400);
+        assert_eq!(format(&timecode, 25 * 86400 - 1), "23:59:59:24");
+        assert_eq!(format(&timecode, 25 * 86400), "00:00:00:00");
+        assert_eq!(format(&timecode, 25 * 86400 + 26), "00:00:01:01");
+        assert_eq!(format(&timecode, -1), "23:59:59:24");
+        assert_eq!(frame_number(&timecode, "24:00:00:00"), Err(TimecodeError::InvalidComponents));
+
+        let timecode = Timecode::new(CMTime::make(1001, 30000), 30, kCMTimeCodeFlag_DropFrame | kCMTimeCodeFlag_24HourMax).unwrap();
+        assert_eq!(format(&timecode, timecode.frames_per_day() - 1), "23:59:59;29");
+        assert_eq!(format(&timecode, timecode.frames_per_day()), "00:00:00;00");
+
+        let timecode = Timecode::new(CMTime::make(1, 25), 25, kCMTimeCodeFlag_24HourMax | kCMTimeCodeFlag_NegTimesOK).unwrap();
+        assert_eq!(format(&timecode, -(25 * 86400) - 26), "-00:00:01:01");
+        assert_eq!(frame_number(&timecode, "-00:00:01:01"), Ok(-26));
+    }
+
+    #[test]
+    fn separator_must_match_drop_frame() {
+        let non_drop = Timecode::new(CMTime::make(1, 25), 25, 0).unwrap();
+        assert_eq!(non_drop.parse_time("00:00:01:05"), Ok(CMTime::make(30, 25)));
+        assert_eq!(non_drop.parse_time("00:00:01;05"), Err(TimecodeError::InvalidFormat));
+        let timecode = drop_frame();
+        assert_eq!(timecode.parse_time("00:00:01;05"), Ok(CMTime::make(35 * 1001, 30000)));
+        assert_eq!(timecode.parse_time("00:00:01:05"), Err(TimecodeError::InvalidFormat));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Timecode::new(CMTime::make(1, 25), 25, kCMTimeCodeFlag_DropFrame), Err(TimecodeError::UnsupportedDropFrameRate));
+        assert_eq!(Timecode::new(CMTime::make(0, 25), 25, 0), Err(TimecodeError::InvalidFrameDuration));
+        assert_eq!(Timecode::new(CMTime::make(1, 25), 0, 0), Err(TimecodeError::InvalidFrameQuanta));
+        let timecode = Timecode::new(CMTime::make(1, 25), 25, 0).unwrap();
+        assert_eq!(timecode.components_from_frame_number(-1), Err(TimecodeError::NegativeNotAllowed));
+        assert_eq!(timecode.parse_time("-00:00:00:01"), Err(TimecodeError::NegativeNotAllowed));
+        assert_eq!(timecode.parse_time("00:00:00:25"), Err(TimecodeError::InvalidComponents));
+        assert_eq!(timecode.parse_time("00:00:00"), Err(TimecodeError::InvalidFormat));
+        assert_eq!(timecode.parse_time("00:0:00:00"), Err(TimecodeError::InvalidFormat));
+        assert_eq!(drop_frame().components_from_frame_number(i64::MAX), Err(TimecodeError::Overflow));
+    }
+}
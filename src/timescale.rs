@@ -0,0 +1,94 @@
+use std::{collections::HashMap, convert::TryFrom};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use crate::sample_buffer::CMSampleTimingInfo;
+use crate::time::{gcd, kCMTimeMaxTimescale, CMTime, CMTimeScale, CMTimeValue};
+
+/// The result of resolving a timescale shared by a set of times
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommonTimescale {
+    pub timescale: CMTimeScale,
+    /// Indices of the times that cannot be represented exactly in `timescale`
+    pub rounded: Vec<usize>,
+}
+
+impl CommonTimescale {
+    #[inline]
+    pub fn is_exact(&self) -> bool {
+        self.rounded.is_empty()
+    }
+}
+
+// The smallest timescale in which the time can be represented exactly
+#[inline]
+fn required_timescale(time: &CMTime) -> Option<u128> {
+    if time.is_numeric() && time.timescale > 0 {
+        Some(time.timescale as u128 / gcd(time.value.unsigned_abs() as u128, time.timescale as u128))
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn lcm(a: u128, b: u128) -> u128 {
+    a / gcd(a, b) * b
+}
+
+#[inline]
+fn is_exact_in(time: &CMTime, timescale: CMTimeScale) -> bool {
+    if !time.is_numeric() || time.timescale <= 0 {
+        return true;
+    }
+    let scaled = time.value as i128 * timescale as i128;
+    scaled % time.timescale as i128 == 0 && CMTimeValue::try_from(scaled / time.timescale as i128).is_ok()
+}
+
+// Picks the timescale used when the exact least common multiple does not fit in a CMTimeScale. The most frequently
+// required timescales are kept exact first, then the result is scaled up as far as possible to reduce rounding errors.
+fn best_fit_timescale(times: &[CMTime]) -> CMTimeScale {
+    let mut frequencies: HashMap<u128, usize> = HashMap::new();
+    for timescale in times.iter().filter_map(required_timescale) {
+        *frequencies.entry(timescale).or_insert(0) += 1;
+    }
+    let mut candidates: Vec<(u128, usize)> = frequencies.into_iter().collect();
+    candidates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let mut timescale = 1u128;
+    for (required, _) in candidates {
+        let multiple = lcm(timescale, required);
+        if multiple <= kCMTimeMaxTimescale as u128 {
+            timescale = multiple;
+        }
+    }
+    let max_value = times
+        .iter()
+        .filter(|time| time.is_numeric() && time.timescale > 0)
+        .fold(0u128, |max, time| max.max(time.value.unsigned_abs() as u128 * timescale / time.timescale as u128 + 1));
+    let factor = (kCMTimeMaxTimescale as u128 / timescale).min(i64::MAX as u128 / max_value.max(1)).max(1);
+    (timescale * factor) as CMTimeScale
+}
+
+pub fn common_timescale<I>(times: I) -> CommonTimescale
+where
+    I: IntoIterator<Item = CMTime>,
+{
+    let times: Vec<CMTime> = times.into_iter().collect();
+    let mut timescale = Some(1u128);
+    for required in times.iter().filter_map(required_timescale) {
+        timescale = timescale.map(|timescale| lcm(timescale, required)).filter(|timescale| *timescale <= kCMTimeMaxTimescale as u128);
+    }
+    let timescale = match timescale {
+        Some(timescale) => timescale as CMTimeScale,
+        None => best_fit_timescale(&times),
+    };
+    let rounded = times.iter().enumerate().filter(|(_, time)| !is_exact_in(time, timescale)).map(|(index, _)| index).collect();
+    CommonTimescale { timescale, rounded }
+}
+
+// Resolves the timescale of the durations, presentation and decode timestamps; rounded holds sample indices
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn common_timescale_for_timing_info(timing_info: &[CMSampleTimingInfo]) -> CommonTimescale {
+    let resolved = common_timescale(timing_info.iter().flat_map(|info| [info.duration, info.presentationTimeStamp, info.decodeTimeStamp]));
+    let mut rounded: Vec<usize> = resolved.rounded.iter().map(|index| index / 3).collect();
+    rounded.dedup();
+    CommonTimescale { timescale: resolved.timescale, rounded }
+}
@@ -0,0 +1,731 @@
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display, Formatter, Write},
+};
+
+use crate::time::{
+    div_round, gcd, kCMTimeFlags_Valid, kCMTimeMaxTimescale, kCMTimeRoundingMethod_RoundHalfAwayFromZero,
+    kCMTimeRoundingMethod_RoundTowardNegativeInfinity, reduce, resolve_rounding_method, CMTime, CMTimeRoundingMethod, CMTimeScale,
+};
+
+pub const MAX_TIMESTAMP_PRECISION: u32 = 9;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimestampFormatError {
+    NonNumericTime,
+    NegativeNotAllowed,
+    Overflow,
+}
+
+impl Display for TimestampFormatError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TimestampFormatError::NonNumericTime => write!(f, "time is not numeric"),
+            TimestampFormatError::NegativeNotAllowed => write!(f, "negative times cannot be represented in this format"),
+            TimestampFormatError::Overflow => write!(f, "time is out of range"),
+        }
+    }
+}
+
+impl Error for TimestampFormatError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimestampParseErrorKind {
+    UnexpectedEnd,
+    UnexpectedCharacter,
+    InvalidNumber,
+    OutOfRange,
+    UnsupportedDesignator,
+    Overflow,
+}
+
+/// A timestamp parse failure and the byte offset in the input where it was detected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimestampParseError {
+    pub kind: TimestampParseErrorKind,
+    pub offset: usize,
+}
+
+impl Display for TimestampParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let description = match self.kind {
+            TimestampParseErrorKind::UnexpectedEnd => "unexpected end of timestamp",
+            TimestampParseErrorKind::UnexpectedCharacter => "unexpected character",
+            TimestampParseErrorKind::InvalidNumber => "invalid number",
+            TimestampParseErrorKind::OutOfRange => "field is out of range",
+            TimestampParseErrorKind::UnsupportedDesignator => "unsupported duration designator",
+            TimestampParseErrorKind::Overflow => "timestamp is out of range",
+        };
+        write!(f, "{} at byte {}", description, self.offset)
+    }
+}
+
+impl Error for TimestampParseError {}
+
+/// The TTML frame rate, sub-frame rate and tick rate used to interpret frame and tick based time expressions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TtmlTimeBase {
+    pub frame_rate: u32,
+    pub frame_rate_multiplier: (u32, u32),
+    pub sub_frame_rate: u32,
+    pub tick_rate: u32,
+}
+
+impl Default for TtmlTimeBase {
+    fn default() -> Self {
+        TtmlTimeBase { frame_rate: 30, frame_rate_multiplier: (1, 1), sub_frame_rate: 1, tick_rate: 1 }
+    }
+}
+
+impl TtmlTimeBase {
+    // When ttp:frameRate is specified, ttp:tickRate defaults to the frame rate times the sub-frame rate
+    #[inline]
+    pub fn new(frame_rate: u32, frame_rate_multiplier: (u32, u32)) -> Self {
+        TtmlTimeBase { frame_rate, frame_rate_multiplier, sub_frame_rate: 1, tick_rate: frame_rate }
+    }
+
+    // Frames per second as a fraction
+    #[inline]
+    pub fn effective_frame_rate(&self) -> (u64, u64) {
+        (self.frame_rate as u64 * self.frame_rate_multiplier.0 as u64, self.frame_rate_multiplier.1 as u64)
+    }
+
+    #[inline]
+    pub fn frame_duration(&self) -> CMTime {
+        let (num, den) = self.effective_frame_rate();
+        make_time(den as i128, num as i128).unwrap_or(CMTime::INVALID)
+    }
+
+    #[inline]
+    fn is_valid(&self) -> bool {
+        self.frame_rate > 0 && self.frame_rate_multiplier.0 > 0 && self.frame_rate_multiplier.1 > 0 && self.sub_frame_rate > 0 && self.tick_rate > 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TtmlMetric {
+    Hours,
+    Minutes,
+    Seconds,
+    Milliseconds,
+    Frames,
+    Ticks,
+}
+
+impl TtmlMetric {
+    #[inline]
+    fn suffix(&self) -> &'static str {
+        match self {
+            TtmlMetric::Hours => "h",
+            TtmlMetric::Minutes => "m",
+            TtmlMetric::Seconds => "s",
+            TtmlMetric::Milliseconds => "ms",
+            TtmlMetric::Frames => "f",
+            TtmlMetric::Ticks => "t",
+        }
+    }
+
+    // Units per second as a fraction
+    #[inline]
+    fn rate(&self, time_base: &TtmlTimeBase) -> (i128, i128) {
+        match self {
+            TtmlMetric::Hours => (1, 3600),
+            TtmlMetric::Minutes => (1, 60),
+            TtmlMetric::Seconds => (1, 1),
+            TtmlMetric::Milliseconds => (1000, 1),
+            TtmlMetric::Frames => {
+                let (num, den) = time_base.effective_frame_rate();
+                (num as i128, den as i128)
+            }
+            TtmlMetric::Ticks => (time_base.tick_rate as i128, 1),
+        }
+    }
+}
+
+// Exact rational arithmetic on parsed fields, converted to a CMTime once the whole expression is known
+#[derive(Clone, Copy, Debug)]
+struct Rational {
+    num: i128,
+    den: i128,
+}
+
+impl Rational {
+    #[inline]
+    fn new(num: i128, den: i128) -> Self {
+        Rational { num, den }
+    }
+
+    fn checked_add(self, other: Rational) -> Option<Rational> {
+        let divisor = gcd(self.den as u128, other.den as u128) as i128;
+        let den = self.den.checked_mul(other.den / divisor)?;
+        let num = self.num.checked_mul(den / self.den)?.checked_add(other.num.checked_mul(den / other.den)?)?;
+        Some(Rational { num, den })
+    }
+
+    fn checked_mul(self, num: i128, den: i128) -> Option<Rational> {
+        Some(Rational { num: self.num.checked_mul(num)?, den: self.den.checked_mul(den)? })
+    }
+}
+
+// Keeps the parsed timescale (e.g. milliseconds) when it fits, reducing to lowest terms otherwise
+fn make_time(num: i128, den: i128) -> Option<CMTime> {
+    let (num, den) = if den > kCMTimeMaxTimescale as i128 || i64::try_from(num).is_err() { reduce(num, den) } else { (num, den) };
+    Some(CMTime { value: i64::try_from(num).ok()?, timescale: CMTimeScale::try_from(den).ok()?, flags: kCMTimeFlags_Valid, epoch: 0 })
+}
+
+/// Formats CMTime values as subtitle and manifest timestamps with a fixed number of fractional digits
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TimestampFormatter {
+    precision: u32,
+    rounding_method: CMTimeRoundingMethod,
+}
+
+impl Default for TimestampFormatter {
+    fn default() -> Self {
+        TimestampFormatter { precision: 3, rounding_method: kCMTimeRoundingMethod_RoundHalfAwayFromZero }
+    }
+}
+
+impl TimestampFormatter {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Precision is clamped to MAX_TIMESTAMP_PRECISION fractional digits
+    #[inline]
+    pub fn with_precision(self, precision: u32) -> Self {
+        TimestampFormatter { precision: precision.min(MAX_TIMESTAMP_PRECISION), ..self }
+    }
+
+    #[inline]
+    pub fn with_rounding_method(self, rounding_method: CMTimeRoundingMethod) -> Self {
+        TimestampFormatter { rounding_method, ..self }
+    }
+
+    #[inline]
+    pub fn precision(&self) -> u32 {
+        self.precision
+    }
+
+    #[inline]
+    pub fn rounding_method(&self) -> CMTimeRoundingMethod {
+        self.rounding_method
+    }
+
+    // Rounds the time to a whole number of units, where rate is the number of units per second
+    fn quantize(&self, time: CMTime, rate: (i128, i128), allow_negative: bool) -> Result<i128, TimestampFormatError> {
+        if !time.is_numeric() || time.timescale <= 0 {
+            return Err(TimestampFormatError::NonNumericTime);
+        }
+        if time.value < 0 && !allow_negative {
+            return Err(TimestampFormatError::NegativeNotAllowed);
+        }
+        let new_timescale = CMTimeScale::try_from(rate.0 / rate.1).unwrap_or(kCMTimeMaxTimescale);
+        let method = resolve_rounding_method(self.rounding_method, time.timescale, new_timescale);
+        let num = (time.value as i128).checked_mul(rate.0).ok_or(TimestampFormatError::Overflow)?;
+        let den = (time.timescale as i128).checked_mul(rate.1).ok_or(TimestampFormatError::Overflow)?;
+        Ok(div_round(num, den, method).0)
+    }
+
+    #[inline]
+    fn fraction_scale(&self) -> i128 {
+        10i128.pow(self.precision)
+    }
+
+    fn write_clock(&self, time: CMTime, separator: char) -> Result<String, TimestampFormatError> {
+        let scale = self.fraction_scale();
+        let units = self.quantize(time, (scale, 1), false)?;
+        let seconds = units / scale;
+        let mut s = format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+        if self.precision > 0 {
+            let _ = write!(s, "{}{:0width$}", separator, units % scale, width = self.precision as usize);
+        }
+        Ok(s)
+    }
+
+    // Writes the fractional digits without trailing zeros, or nothing if the fraction is zero
+    fn write_trimmed_fraction(&self, s: &mut String, fraction: i128) {
+        if fraction != 0 {
+            let digits = format!("{:0width$}", fraction, width = self.precision as usize);
+            let _ = write!(s, ".{}", digits.trim_end_matches('0'));
+        }
+    }
+
+    #[inline]
+    pub fn format_webvtt(&self, time: CMTime) -> Result<String, TimestampFormatError> {
+        self.write_clock(time, '.')
+    }
+
+    #[inline]
+    pub fn format_srt(&self, time: CMTime) -> Result<String, TimestampFormatError> {
+        self.write_clock(time, ',')
+    }
+
+    // TTML clock time with a decimal fraction of seconds, e.g. 00:01:02.345
+    #[inline]
+    pub fn format_ttml_clock(&self, time: CMTime) -> Result<String, TimestampFormatError> {
+        self.write_clock(time, '.')
+    }
+
+    // TTML clock time with a frame count, e.g. 00:00:01:12, where the frames count from the start of the whole second
+    pub fn format_ttml_clock_frames(&self, time: CMTime, time_base: &TtmlTimeBase) -> Result<String, TimestampFormatError> {
+        self.quantize(time, (1, 1), false)?;
+        let (num, den) = time_base.effective_frame_rate();
+        let (value, timescale) = (time.value as i128, time.timescale as i128);
+        let mut seconds = div_round(value, timescale, kCMTimeRoundingMethod_RoundTowardNegativeInfinity).0;
+        let remainder = value - seconds * timescale;
+        let method = resolve_rounding_method(self.rounding_method, time.timescale, CMTimeScale::try_from(num / den).unwrap_or(kCMTimeMaxTimescale));
+        let mut frames = div_round(remainder * num as i128, timescale * den as i128, method).0;
+        if frames * den as i128 >= num as i128 {
+            seconds += 1;
+            frames = 0;
+        }
+        Ok(format!("{:02}:{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60, frames))
+    }
+
+    // TTML offset time, e.g. 62.345s or 1500f; frame and tick counts are always whole numbers
+    pub fn format_ttml_offset(&self, time: CMTime, metric: TtmlMetric, time_base: &TtmlTimeBase) -> Result<String, TimestampFormatError> {
+        let rate = metric.rate(time_base);
+        let mut s = match metric {
+            TtmlMetric::Frames | TtmlMetric::Ticks => self.quantize(time, rate, false)?.to_string(),
+            _ => {
+                let scale = self.fraction_scale();
+                let units = self.quantize(time, (rate.0 * scale, rate.1), false)?;
+                let mut s = (units / scale).to_string();
+                self.write_trimmed_fraction(&mut s, units % scale);
+                s
+            }
+        };
+        s.push_str(metric.suffix());
+        Ok(s)
+    }
+
+    // ISO 8601 duration using hours, minutes and seconds, e.g. PT1M2.345S; negative durations are prefixed with '-'
+    pub fn format_iso8601_duration(&self, time: CMTime) -> Result<String, TimestampFormatError> {
+        let scale = self.fraction_scale();
+        let units = self.quantize(time, (scale, 1), true)?;
+        let mut s = String::from(if units < 0 { "-PT" } else { "PT" });
+        let units = units.abs();
+        let seconds = units / scale;
+        let (hours, minutes, fraction) = (seconds / 3600, seconds / 60 % 60, units % scale);
+        if hours > 0 {
+            let _ = write!(s, "{}H", hours);
+        }
+        if minutes > 0 {
+            let _ = write!(s, "{}M", minutes);
+        }
+        if seconds % 60 > 0 || fraction > 0 || seconds == 0 {
+            let _ = write!(s, "{}", seconds % 60);
+            self.write_trimmed_fraction(&mut s, fraction);
+            s.push('S');
+        }
+        Ok(s)
+    }
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Scanner<'a> {
+    #[inline]
+    fn new(s: &'a str) -> Self {
+        Scanner { bytes: s.as_bytes(), position: 0 }
+    }
+
+    #[inline]
+    fn error(&self, kind: TimestampParseErrorKind, offset: usize) -> TimestampParseError {
+        TimestampParseError { kind, offset }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    #[inline]
+    fn accept(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), TimestampParseError> {
+        match self.peek() {
+            Some(b) if b == byte => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(_) => Err(self.error(TimestampParseErrorKind::UnexpectedCharacter, self.position)),
+            None => Err(self.error(TimestampParseErrorKind::UnexpectedEnd, self.position)),
+        }
+    }
+
+    // Reads a run of decimal digits, returning the value and the number of digits
+    fn digits(&mut self) -> Result<(i128, u32), TimestampParseError> {
+        let start = self.position;
+        let mut value: i128 = 0;
+        while let Some(b) = self.peek().filter(u8::is_ascii_digit) {
+            value =
+                value.checked_mul(10).and_then(|v| v.checked_add((b - b'0') as i128)).ok_or(self.error(TimestampParseErrorKind::Overflow, start))?;
+            self.position += 1;
+        }
+        let count = (self.position - start) as u32;
+        if count == 0 {
+            return Err(match self.peek() {
+                Some(_) => self.error(TimestampParseErrorKind::InvalidNumber, start),
+                None => self.error(TimestampParseErrorKind::UnexpectedEnd, start),
+            });
+        }
+        Ok((value, count))
+    }
+
+    fn fixed_digits(&mut self, count: u32) -> Result<i128, TimestampParseError> {
+        let start = self.position;
+        let (value, digits) = self.digits()?;
+        if digits != count {
+            return Err(self.error(TimestampParseErrorKind::InvalidNumber, start));
+        }
+        Ok(value)
+    }
+
+    // Two digit minutes or seconds field below 60
+    fn sexagesimal(&mut self) -> Result<i128, TimestampParseError> {
+        let start = self.position;
+        let value = self.fixed_digits(2)?;
+        if value >= 60 {
+            return Err(self.error(TimestampParseErrorKind::OutOfRange, start));
+        }
+        Ok(value)
+    }
+
+    // Fractions that no CMTime timescale can hold exactly (e.g. ten significant digits) are rejected
+    fn fraction(&mut self) -> Result<Rational, TimestampParseError> {
+        let start = self.position;
+        let (value, digits) = self.digits()?;
+        let den = 10i128.checked_pow(digits).ok_or(self.error(TimestampParseErrorKind::Overflow, start))?;
+        if den <= kCMTimeMaxTimescale as i128 {
+            return Ok(Rational::new(value, den));
+        }
+        let (value, den) = reduce(value, den);
+        if den > kCMTimeMaxTimescale as i128 {
+            return Err(self.error(TimestampParseErrorKind::InvalidNumber, start));
+        }
+        Ok(Rational::new(value, den))
+    }
+
+    // A decimal number with an optional fraction introduced by one of the separators
+    fn decimal(&mut self, separators: &[u8]) -> Result<Rational, TimestampParseError> {
+        let (integer, _) = self.digits()?;
+        let mut value = Rational::new(integer, 1);
+        if let Some(separator) = self.peek().filter(|b| separators.contains(b)) {
+            self.expect(separator)?;
+            let start = self.position;
+            value = value.checked_add(self.fraction()?).ok_or(self.error(TimestampParseErrorKind::Overflow, start))?;
+        }
+        Ok(value)
+    }
+
+    fn finish(&self, value: Rational) -> Result<CMTime, TimestampParseError> {
+        if self.position < self.bytes.len() {
+            return Err(self.error(TimestampParseErrorKind::UnexpectedCharacter, self.position));
+        }
+        make_time(value.num, value.den).ok_or(self.error(TimestampParseErrorKind::Overflow, 0))
+    }
+
+    fn add(&self, total: Rational, value: Rational, start: usize) -> Result<Rational, TimestampParseError> {
+        total.checked_add(value).ok_or(self.error(TimestampParseErrorKind::Overflow, start))
+    }
+
+    // hours ':' minutes ':' seconds, with hours optional when allowed
+    fn clock(&mut self, hours_required: bool) -> Result<Rational, TimestampParseError> {
+        let start = self.position;
+        let (first, first_digits) = self.digits()?;
+        self.expect(b':')?;
+        let second_start = self.position;
+        let second = self.sexagesimal()?;
+        let (hours, minutes, seconds) = if self.peek() == Some(b':') || hours_required {
+            if first_digits < 2 {
+                return Err(self.error(TimestampParseErrorKind::InvalidNumber, start));
+            }
+            self.expect(b':')?;
+            (first, second, self.sexagesimal()?)
+        } else {
+            if first_digits != 2 {
+                return Err(self.error(TimestampParseErrorKind::InvalidNumber, start));
+            }
+            if first >= 60 {
+                return Err(self.error(TimestampParseErrorKind::OutOfRange, start));
+            }
+            (0, first, second)
+        };
+        let total = hours.checked_mul(3600).and_then(|h| h.checked_add(minutes * 60 + seconds));
+        total.map(|total| Rational::new(total, 1)).ok_or(self.error(TimestampParseErrorKind::Overflow, second_start))
+    }
+
+    // WebVTT and SRT timestamps require a fraction after the seconds
+    fn subtitle_timestamp(&mut self, hours_required: bool, separator: u8) -> Result<CMTime, TimestampParseError> {
+        let total = self.clock(hours_required)?;
+        self.expect(separator)?;
+        let start = self.position;
+        let fraction = self.fraction()?;
+        let total = self.add(total, fraction, start)?;
+        self.finish(total)
+    }
+
+    fn ttml(&mut self, time_base: &TtmlTimeBase) -> Result<CMTime, TimestampParseError> {
+        if !time_base.is_valid() {
+            return Err(self.error(TimestampParseErrorKind::OutOfRange, 0));
+        }
+        let (rate_num, rate_den) = time_base.effective_frame_rate();
+        let frame_duration = Rational::new(rate_den as i128, rate_num as i128);
+        // Clock times have a ':' after the leading digits, offset times have a metric
+        let is_clock = self.bytes.iter().position(|b| !b.is_ascii_digit()).map(|index| self.bytes[index] == b':').unwrap_or(false);
+        let total = if is_clock {
+            let mut total = self.clock(true)?;
+            if self.accept(b':') {
+                let start = self.position;
+                let (frames, _) = self.digits()?;
+                if frames as u128 * rate_den as u128 >= rate_num as u128 {
+                    return Err(self.error(TimestampParseErrorKind::OutOfRange, start));
+                }
+                let overflow = self.error(TimestampParseErrorKind::Overflow, start);
+                total = total.checked_add(frame_duration.checked_mul(frames, 1).ok_or(overflow)?).ok_or(overflow)?;
+                if self.accept(b'.') {
+                    let start = self.position;
+                    let (sub_frames, _) = self.digits()?;
+                    if sub_frames >= time_base.sub_frame_rate as i128 {
+                        return Err(self.error(TimestampParseErrorKind::OutOfRange, start));
+                    }
+                    let sub_frame = frame_duration.checked_mul(sub_frames, time_base.sub_frame_rate as i128).ok_or(overflow)?;
+                    total = self.add(total, sub_frame, start)?;
+                }
+            } else if self.accept(b'.') {
+                let start = self.position;
+                let fraction = self.fraction()?;
+                total = self.add(total, fraction, start)?;
+            }
+            total
+        } else {
+            let start = self.position;
+            let count = self.decimal(b".")?;
+            let metric_start = self.position;
+            let metric = match self.bytes.get(self.position..) {
+                Some(b"h") => TtmlMetric::Hours,
+                Some(b"m") => TtmlMetric::Minutes,
+                Some(b"s") => TtmlMetric::Seconds,
+                Some(b"ms") => TtmlMetric::Milliseconds,
+                Some(b"f") => TtmlMetric::Frames,
+                Some(b"t") => TtmlMetric::Ticks,
+                Some([]) | None => return Err(self.error(TimestampParseErrorKind::UnexpectedEnd, metric_start)),
+                Some(_) => return Err(self.error(TimestampParseErrorKind::UnexpectedCharacter, metric_start)),
+            };
+            self.position = self.bytes.len();
+            let (num, den) = metric.rate(time_base);
+            count.checked_mul(den, num).ok_or(self.error(TimestampParseErrorKind::Overflow, start))?
+        };
+        self.finish(total)
+    }
+
+    fn iso8601_duration(&mut self) -> Result<CMTime, TimestampParseError> {
+        let negative = self.accept(b'-');
+        if !negative {
+            self.accept(b'+');
+        }
+        self.expect(b'P')?;
+        let mut total = Rational::new(0, 1);
+        let mut components = 0;
+        let mut in_time = false;
+        // Designators must appear in this order; years and months have no fixed length and are rejected
+        let mut allowed: &[u8] = b"YMWD";
+        while self.position < self.bytes.len() {
+            if !in_time && self.accept(b'T') {
+                in_time = true;
+                allowed = b"HMS";
+                if self.position == self.bytes.len() {
+                    return Err(self.error(TimestampParseErrorKind::UnexpectedEnd, self.position));
+                }
+                continue;
+            }
+            let start = self.position;
+            let value = self.decimal(b".,")?;
+            let designator_offset = self.position;
+            let designator = self.peek().ok_or(self.error(TimestampParseErrorKind::UnexpectedEnd, designator_offset))?;
+            let index =
+                allowed.iter().position(|b| *b == designator).ok_or(self.error(TimestampParseErrorKind::UnexpectedCharacter, designator_offset))?;
+            allowed = &allowed[index + 1..];
+            self.position += 1;
+            let seconds = match (in_time, designator) {
+                (false, b'Y') | (false, b'M') => return Err(self.error(TimestampParseErrorKind::UnsupportedDesignator, designator_offset)),
+                (false, b'W') => 604_800,
+                (false, _) => 86_400,
+                (true, b'H') => 3600,
+                (true, b'M') => 60,
+                (true, _) => 1,
+            };
+            let value = value.checked_mul(seconds, 1).ok_or(self.error(TimestampParseErrorKind::Overflow, start))?;
+            total = self.add(total, value, start)?;
+            components += 1;
+        }
+        if components == 0 {
+            return Err(self.error(TimestampParseErrorKind::UnexpectedEnd, self.position));
+        }
+        if negative {
+            total.num = -total.num;
+        }
+        self.finish(total)
+    }
+}
+
+// WebVTT timestamp, [hh:]mm:ss.ttt
+#[inline]
+pub fn parse_webvtt(s: &str) -> Result<CMTime, TimestampParseError> {
+    Scanner::new(s).subtitle_timestamp(false, b'.')
+}
+
+// SRT timestamp, hh:mm:ss,mmm
+#[inline]
+pub fn parse_srt(s: &str) -> Result<CMTime, TimestampParseError> {
+    Scanner::new(s).subtitle_timestamp(true, b',')
+}
+
+// TTML clock time or offset time; frames, sub-frames and ticks are interpreted with the time base
+#[inline]
+pub fn parse_ttml(s: &str, time_base: &TtmlTimeBase) -> Result<CMTime, TimestampParseError> {
+    Scanner::new(s).ttml(time_base)
+}
+
+#[inline]
+pub fn parse_iso8601_duration(s: &str) -> Result<CMTime, TimestampParseError> {
+    Scanner::new(s).iso8601_duration()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(kind: TimestampParseErrorKind, offset: usize) -> TimestampParseError {
+        TimestampParseError { kind, offset }
+    }
+
+    fn parts(time: CMTime) -> (i64, i32) {
+        (time.value, time.timescale)
+    }
+
+    #[test]
+    fn webvtt_and_srt() {
+        let formatter = TimestampFormatter::new();
+        // The parsed timescale follows the number of fractional digits
+        assert_eq!(parse_webvtt("00:01.500").map(parts), Ok((1500, 1000)));
+        assert_eq!(parse_webvtt("01:02:03.004").map(parts), Ok((3_723_004, 1000)));
+        assert_eq!(parse_webvtt("100:00:00.5").map(parts), Ok((3_600_005, 10)));
+        assert_eq!(parse_srt("01:02:03,004").map(parts), Ok((3_723_004, 1000)));
+        for s in ["00:00:00.000", "00:00:01.500", "01:02:03.004", "123:59:59.999"].iter() {
+            assert_eq!(formatter.format_webvtt(parse_webvtt(s).unwrap()).as_deref(), Ok(*s));
+            let srt = s.replace('.', ",");
+            assert_eq!(formatter.format_srt(parse_srt(&srt).unwrap()), Ok(srt));
+        }
+        assert_eq!(formatter.format_webvtt(CMTime::make(2, 3)).as_deref(), Ok("00:00:00.667"));
+        assert_eq!(formatter.with_precision(0).format_srt(CMTime::make(2, 3)).as_deref(), Ok("00:00:01"));
+        assert_eq!(formatter.with_precision(12).precision(), MAX_TIMESTAMP_PRECISION);
+        assert_eq!(formatter.format_webvtt(CMTime::make(-1, 1)), Err(TimestampFormatError::NegativeNotAllowed));
+        assert_eq!(formatter.format_srt(CMTime::INVALID), Err(TimestampFormatError::NonNumericTime));
+
+        assert_eq!(parse_webvtt("00:60.000"), Err(error(TimestampParseErrorKind::OutOfRange, 3)));
+        assert_eq!(parse_webvtt("60:00.000"), Err(error(TimestampParseErrorKind::OutOfRange, 0)));
+        assert_eq!(parse_webvtt("00:00:60.000"), Err(error(TimestampParseErrorKind::OutOfRange, 6)));
+        assert_eq!(parse_webvtt("0:00.000"), Err(error(TimestampParseErrorKind::InvalidNumber, 0)));
+        assert_eq!(parse_webvtt("00:0.000"), Err(error(TimestampParseErrorKind::InvalidNumber, 3)));
+        assert_eq!(parse_webvtt("00:00"), Err(error(TimestampParseErrorKind::UnexpectedEnd, 5)));
+        assert_eq!(parse_webvtt("00:00."), Err(error(TimestampParseErrorKind::UnexpectedEnd, 6)));
+        assert_eq!(parse_webvtt("00:00.000x"), Err(error(TimestampParseErrorKind::UnexpectedCharacter, 9)));
+        assert_eq!(parse_srt("1:02:03,004"), Err(error(TimestampParseErrorKind::InvalidNumber, 0)));
+        assert_eq!(parse_srt("00:02,004"), Err(error(TimestampParseErrorKind::UnexpectedCharacter, 5)));
+        assert_eq!(parse_srt("01:02:03.004"), Err(error(TimestampParseErrorKind::UnexpectedCharacter, 8)));
+    }
+
+    #[test]
+    fn long_fractions() {
+        // Ten fractional digits only parse when they reduce to a timescale CMTime can hold
+        assert_eq!(parse_webvtt("00:00.5000000000"), Ok(CMTime::make(1, 2)));
+        assert_eq!(parse_webvtt("00:00.0000000001"), Err(error(TimestampParseErrorKind::InvalidNumber, 6)));
+        assert_eq!(parse_iso8601_duration("PT0.0000000001S"), Err(error(TimestampParseErrorKind::InvalidNumber, 4)));
+        assert_eq!(parse_webvtt("00:00.000000000000000000000000000000000000001"), Err(error(TimestampParseErrorKind::Overflow, 6)));
+    }
+
+    #[test]
+    fn ttml() {
+        let formatter = TimestampFormatter::new();
+        let time_base = TtmlTimeBase::default();
+        let ntsc = TtmlTimeBase::new(30, (1000, 1001));
+        assert_eq!(time_base.frame_duration(), CMTime::make(1, 30));
+        assert_eq!(ntsc.frame_duration(), CMTime::make(1001, 30000));
+        assert_eq!(parse_ttml("00:00:01:15", &time_base), Ok(CMTime::make(3, 2)));
+        assert_eq!(parse_ttml("00:00:01:15", &ntsc), Ok(CMTime::make(30000 + 15 * 1001, 30000)));
+        assert_eq!(parse_ttml("00:00:01:15.1", &TtmlTimeBase { sub_frame_rate: 2, ..time_base }), Ok(CMTime::make(31 * 2 + 29, 60)));
+        assert_eq!(parse_ttml("00:01:02.5", &time_base), Ok(CMTime::make(125, 2)));
+        for s in ["1.5h", "90m", "5400s", "5400000ms", "162000f"].iter() {
+            assert_eq!(parse_ttml(s, &time_base), Ok(CMTime::make(5400, 1)), "{}", s);
+        }
+        assert_eq!(parse_ttml("90t", &TtmlTimeBase { tick_rate: 60, ..time_base }), Ok(CMTime::make(3, 2)));
+
+        let time = CMTime::make(3, 2);
+        assert_eq!(formatter.format_ttml_clock(time).as_deref(), Ok("00:00:01.500"));
+        assert_eq!(formatter.format_ttml_clock_frames(time, &time_base).as_deref(), Ok("00:00:01:15"));
+        assert_eq!(formatter.format_ttml_clock_frames(CMTime::make(2999, 2000), &time_base).as_deref(), Ok("00:00:01:15"));
+        assert_eq!(formatter.format_ttml_clock_frames(CMTime::make(1999, 2000), &time_base).as_deref(), Ok("00:00:01:00"));
+        assert_eq!(formatter.format_ttml_offset(time, TtmlMetric::Seconds, &time_base).as_deref(), Ok("1.5s"));
+        assert_eq!(formatter.format_ttml_offset(time, TtmlMetric::Milliseconds, &time_base).as_deref(), Ok("1500ms"));
+        assert_eq!(formatter.format_ttml_offset(time, TtmlMetric::Frames, &time_base).as_deref(), Ok("45f"));
+        assert_eq!(formatter.format_ttml_offset(time, TtmlMetric::Ticks, &time_base).as_deref(), Ok("2t"));
+        assert_eq!(formatter.format_ttml_offset(CMTime::make(5400, 1), TtmlMetric::Hours, &time_base).as_deref(), Ok("1.5h"));
+        for s in ["00:00:01:15", "00:00:01.5", "1.5s", "45f", "1500ms"].iter() {
+            assert_eq!(parse_ttml(s, &time_base), Ok(time), "{}", s);
+        }
+
+        assert_eq!(parse_ttml("00:00:01:30", &time_base), Err(error(TimestampParseErrorKind::OutOfRange, 9)));
+        assert_eq!(parse_ttml("00:00:01:15.1", &time_base), Err(error(TimestampParseErrorKind::OutOfRange, 12)));
+        assert_eq!(parse_ttml("10", &time_base), Err(error(TimestampParseErrorKind::UnexpectedEnd, 2)));
+        assert_eq!(parse_ttml("10x", &time_base), Err(error(TimestampParseErrorKind::UnexpectedCharacter, 2)));
+        assert_eq!(parse_ttml("10s ", &time_base), Err(error(TimestampParseErrorKind::UnexpectedCharacter, 2)));
+        assert_eq!(parse_ttml("10s", &TtmlTimeBase { tick_rate: 0, ..time_base }), Err(error(TimestampParseErrorKind::OutOfRange, 0)));
+    }
+
+    #[test]
+    fn iso8601_duration() {
+        let formatter = TimestampFormatter::new();
+        assert_eq!(parse_iso8601_duration("PT1M2.345S").map(parts), Ok((62_345, 1000)));
+        assert_eq!(parse_iso8601_duration("-PT1.5S"), Ok(CMTime::make(-3, 2)));
+        assert_eq!(parse_iso8601_duration("+PT0,5S"), Ok(CMTime::make(1, 2)));
+        assert_eq!(parse_iso8601_duration("P1DT1H"), Ok(CMTime::make(90_000, 1)));
+        assert_eq!(parse_iso8601_duration("P1W"), Ok(CMTime::make(604_800, 1)));
+        for s in ["PT0S", "PT1M2.345S", "-PT1.5S", "PT1H", "PT25H0.001S", "PT1H1M1S"].iter() {
+            assert_eq!(formatter.format_iso8601_duration(parse_iso8601_duration(s).unwrap()).as_deref(), Ok(*s));
+        }
+        assert_eq!(formatter.format_iso8601_duration(CMTime::POSITIVE_INFINITY), Err(TimestampFormatError::NonNumericTime));
+
+        assert_eq!(parse_iso8601_duration("P"), Err(error(TimestampParseErrorKind::UnexpectedEnd, 1)));
+        assert_eq!(parse_iso8601_duration("PT"), Err(error(TimestampParseErrorKind::UnexpectedEnd, 2)));
+        assert_eq!(parse_iso8601_duration("1S"), Err(error(TimestampParseErrorKind::UnexpectedCharacter, 0)));
+        assert_eq!(parse_iso8601_duration("PT1"), Err(error(TimestampParseErrorKind::UnexpectedEnd, 3)));
+        assert_eq!(parse_iso8601_duration("P1Y"), Err(error(TimestampParseErrorKind::UnsupportedDesignator, 2)));
+        assert_eq!(parse_iso8601_duration("P1M"), Err(error(TimestampParseErrorKind::UnsupportedDesignator, 2)));
+        assert_eq!(parse_iso8601_duration("PT1S1M"), Err(error(TimestampParseErrorKind::UnexpectedCharacter, 5)));
+        assert_eq!(parse_iso8601_duration("P1H"), Err(error(TimestampParseErrorKind::UnexpectedCharacter, 2)));
+        assert_eq!(parse_iso8601_duration("PTxS"), Err(error(TimestampParseErrorKind::InvalidNumber, 2)));
+        assert_eq!(parse_iso8601_duration("P99999999999999999999999999999999999999999D"), Err(error(TimestampParseErrorKind::Overflow, 1)));
+        assert_eq!(parse_iso8601_duration("P999999999999999D"), Err(error(TimestampParseErrorKind::Overflow, 0)));
+    }
+
+    #[test]
+    fn error_display() {
+        assert_eq!(error(TimestampParseErrorKind::OutOfRange, 3).to_string(), "field is out of range at byte 3");
+        assert_eq!(TimestampFormatError::Overflow.to_string(), "time is out of range");
+    }
+}
@@ -0,0 +1,234 @@
+use std::convert::TryFrom;
+
+use crate::time::{
+    div_round, kCMTimeFlags_Valid, kCMTimeRoundingMethod_RoundHalfAwayFromZero, CMTime, CMTimeConversionError, CMTimeScale, CMTimeValue,
+};
+
+pub const TS_SYSTEM_CLOCK_RATE: CMTimeScale = 90_000;
+pub const TS_PROGRAM_CLOCK_RATE: CMTimeScale = 27_000_000;
+pub const TS_PCR_EXTENSIONS_PER_TICK: u64 = 300;
+pub const TS_TIMESTAMP_MASK: u64 = (1 << 33) - 1;
+
+// PTS, DTS and PCR values are tracked in 27 MHz units, where both wrap after 2^33 ticks of the 90 kHz clock
+const WRAP: i64 = (1 << 33) * TS_PCR_EXTENSIONS_PER_TICK as i64;
+const HALF_WRAP: i64 = WRAP / 2;
+
+/// Converts 33-bit PTS/DTS and PCR values into CMTime values on a single timeline that does not wrap
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TsTimestampUnwrapper {
+    discontinuity_threshold: i64,
+    // The last value in 27 MHz units with wraparound undone, before the discontinuity offset is applied
+    last: Option<i64>,
+    // The last positive step between consecutive values, used to keep the timeline increasing across a discontinuity
+    last_delta: i64,
+    offset: i64,
+    pending_reset: bool,
+    discontinuity: bool,
+}
+
+impl Default for TsTimestampUnwrapper {
+    fn default() -> Self {
+        Self::new(CMTime::make(10, 1))
+    }
+}
+
+impl TsTimestampUnwrapper {
+    // Jumps larger than the threshold in either direction are treated as discontinuities; a non-numeric threshold disables detection
+    pub fn new(discontinuity_threshold: CMTime) -> Self {
+        let discontinuity_threshold = if discontinuity_threshold.is_numeric() && discontinuity_threshold.timescale > 0 {
+            let ticks =
+                discontinuity_threshold.value.unsigned_abs() as i128 * TS_PROGRAM_CLOCK_RATE as i128 / discontinuity_threshold.timescale as i128;
+            i64::try_from(ticks).unwrap_or(i64::MAX)
+        } else {
+            i64::MAX
+        };
+        TsTimestampUnwrapper { discontinuity_threshold, last: None, last_delta: 0, offset: 0, pending_reset: false, discontinuity: false }
+    }
+
+    #[inline]
+    pub fn discontinuity_threshold(&self) -> CMTime {
+        CMTime { value: self.discontinuity_threshold, timescale: TS_PROGRAM_CLOCK_RATE, flags: kCMTimeFlags_Valid, epoch: 0 }
+    }
+
+    // Whether the value passed to the last unwrap call started a new timeline
+    #[inline]
+    pub fn discontinuity_detected(&self) -> bool {
+        self.discontinuity
+    }
+
+    // Call when the adaptation field has discontinuity_indicator set; the next value continues one step after the last output time
+    #[inline]
+    pub fn reset(&mut self) {
+        self.pending_reset = true;
+    }
+
+    fn unwrap_ticks(&mut self, ticks: i64) -> i64 {
+        let ticks = ticks.rem_euclid(WRAP);
+        self.discontinuity = false;
+        let unwrapped = match self.last {
+            None => ticks,
+            Some(last) => {
+                // Choose the candidate nearest to the last value, so a wrap in either direction is undone
+                let delta = (ticks - last).rem_euclid(WRAP);
+                let delta = if delta >= HALF_WRAP { delta - WRAP } else { delta };
+                let unwrapped = last + delta;
+                if self.pending_reset || delta.abs() > self.discontinuity_threshold {
+                    // Continue after the last output by the last observed step, or a single 90 kHz tick before any was seen,
+                    // rounding the offset up to whole 90 kHz ticks so PTS and DTS stay exact and the output stays increasing
+                    let step = if self.last_delta > 0 { self.last_delta } else { TS_PCR_EXTENSIONS_PER_TICK as i64 };
+                    let tick = TS_PCR_EXTENSIONS_PER_TICK as i64;
+                    self.offset += (last + step - unwrapped + tick - 1).div_euclid(tick) * tick;
+                    self.discontinuity = true;
+                } else if delta > 0 {
+                    self.last_delta = delta;
+                }
+                unwrapped
+            }
+        };
+        self.pending_reset = false;
+        self.last = Some(unwrapped);
+        unwrapped + self.offset
+    }
+
+    pub fn unwrap_pts(&mut self, pts: u64) -> CMTime {
+        let ticks = self.unwrap_ticks((pts & TS_TIMESTAMP_MASK) as i64 * TS_PCR_EXTENSIONS_PER_TICK as i64);
+        CMTime { value: ticks / TS_PCR_EXTENSIONS_PER_TICK as i64, timescale: TS_SYSTEM_CLOCK_RATE, flags: kCMTimeFlags_Valid, epoch: 0 }
+    }
+
+    #[inline]
+    pub fn unwrap_dts(&mut self, dts: u64) -> CMTime {
+        self.unwrap_pts(dts)
+    }
+
+    pub fn unwrap_pcr(&mut self, pcr_base: u64, pcr_extension: u16) -> CMTime {
+        let ticks = (pcr_base & TS_TIMESTAMP_MASK) as i64 * TS_PCR_EXTENSIONS_PER_TICK as i64 + pcr_extension as i64;
+        CMTime { value: self.unwrap_ticks(ticks), timescale: TS_PROGRAM_CLOCK_RATE, flags: kCMTimeFlags_Valid, epoch: 0 }
+    }
+}
+
+fn ticks_from_time(time: CMTime, rate: CMTimeScale) -> Result<i64, CMTimeConversionError> {
+    time.check_numeric()?;
+    let (ticks, _) = div_round(time.value as i128 * rate as i128, time.timescale as i128, kCMTimeRoundingMethod_RoundHalfAwayFromZero);
+    Ok(ticks.rem_euclid(WRAP as i128) as i64)
+}
+
+// The 33-bit PTS or DTS for the time, wrapped as it is written to a PES header
+#[inline]
+pub fn pts_from_time(time: CMTime) -> Result<u64, CMTimeConversionError> {
+    Ok(ticks_from_time(time, TS_SYSTEM_CLOCK_RATE)? as u64 & TS_TIMESTAMP_MASK)
+}
+
+// The 33-bit PCR base and 9-bit extension for the time
+#[inline]
+pub fn pcr_from_time(time: CMTime) -> Result<(u64, u16), CMTimeConversionError> {
+    let ticks = ticks_from_time(time, TS_PROGRAM_CLOCK_RATE)? as u64;
+    Ok((ticks / TS_PCR_EXTENSIONS_PER_TICK, (ticks % TS_PCR_EXTENSIONS_PER_TICK) as u16))
+}
+
+#[inline]
+pub fn time_from_pts(pts: u64) -> CMTime {
+    CMTime { value: (pts & TS_TIMESTAMP_MASK) as CMTimeValue, timescale: TS_SYSTEM_CLOCK_RATE, flags: kCMTimeFlags_Valid, epoch: 0 }
+}
+
+#[inline]
+pub fn time_from_pcr(pcr_base: u64, pcr_extension: u16) -> CMTime {
+    let value = (pcr_base & TS_TIMESTAMP_MASK) as CMTimeValue * TS_PCR_EXTENSIONS_PER_TICK as CMTimeValue + pcr_extension as CMTimeValue;
+    CMTime { value, timescale: TS_PROGRAM_CLOCK_RATE, flags: kCMTimeFlags_Valid, epoch: 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PTS_WRAP: i64 = 1 << 33;
+
+    fn pts(time: CMTime) -> i64 {
+        assert_eq!(time.timescale, TS_SYSTEM_CLOCK_RATE);
+        time.value
+    }
+
+    #[test]
+    fn wrap_forward() {
+        let mut unwrapper = TsTimestampUnwrapper::default();
+        assert_eq!(pts(unwrapper.unwrap_pts((PTS_WRAP - 3000) as u64)), PTS_WRAP - 3000);
+        assert_eq!(pts(unwrapper.unwrap_pts(0)), PTS_WRAP);
+        assert!(!unwrapper.discontinuity_detected());
+        assert_eq!(pts(unwrapper.unwrap_pts(3000)), PTS_WRAP + 3000);
+        // Values are masked to 33 bits, and a B-frame before the wrap stays before it
+        assert_eq!(pts(unwrapper.unwrap_dts((PTS_WRAP + 6000) as u64)), PTS_WRAP + 6000);
+        assert_eq!(pts(unwrapper.unwrap_pts((PTS_WRAP - 1500) as u64)), PTS_WRAP - 1500);
+        assert!(!unwrapper.discontinuity_detected());
+    }
+
+    #[test]
+    fn wrap_backward() {
+        let mut unwrapper = TsTimestampUnwrapper::default();
+        assert_eq!(pts(unwrapper.unwrap_pts(1500)), 1500);
+        assert_eq!(pts(unwrapper.unwrap_pts((PTS_WRAP - 1500) as u64)), -1500);
+        assert_eq!(pts(unwrapper.unwrap_pts(4500)), 4500);
+        assert!(!unwrapper.discontinuity_detected());
+    }
+
+    #[test]
+    fn pcr_wrap() {
+        let mut unwrapper = TsTimestampUnwrapper::default();
+        let time = unwrapper.unwrap_pcr(TS_TIMESTAMP_MASK, 299);
+        assert_eq!((time.value, time.timescale), (WRAP - 1, TS_PROGRAM_CLOCK_RATE));
+        assert_eq!(unwrapper.unwrap_pcr(0, 1).value, WRAP + 1);
+        assert!(!unwrapper.discontinuity_detected());
+    }
+
+    #[test]
+    fn discontinuity_continues_timeline() {
+        // Before any step has been observed the timeline continues one tick later
+        let mut unwrapper = TsTimestampUnwrapper::default();
+        assert_eq!(pts(unwrapper.unwrap_pts(2000)), 2000);
+        assert_eq!(pts(unwrapper.unwrap_pts(500_000_000)), 2001);
+        assert!(unwrapper.discontinuity_detected());
+        assert_eq!(pts(unwrapper.unwrap_pts(500_003_000)), 5001);
+        assert!(!unwrapper.discontinuity_detected());
+
+        // Afterwards it continues by the last positive step, ignoring reordered values
+        let mut unwrapper = TsTimestampUnwrapper::default();
+        for (input, output) in [(0, 0), (3003, 3003), (9009, 9009), (6006, 6006), (1_000_000_000, 12012), (1_000_003_003, 15015)].iter() {
+            assert_eq!(pts(unwrapper.unwrap_pts(*input)), *output);
+        }
+
+        // A jump backwards beyond the threshold is also a discontinuity
+        let mut unwrapper = TsTimestampUnwrapper::new(CMTime::make(1, 1));
+        assert_eq!(unwrapper.discontinuity_threshold(), CMTime::make(TS_PROGRAM_CLOCK_RATE as i64, TS_PROGRAM_CLOCK_RATE));
+        assert_eq!(pts(unwrapper.unwrap_pts(900_000)), 900_000);
+        assert_eq!(pts(unwrapper.unwrap_pts(903_600)), 903_600);
+        assert_eq!(pts(unwrapper.unwrap_pts(90_000)), 907_200);
+        assert!(unwrapper.discontinuity_detected());
+        assert_eq!(pts(unwrapper.unwrap_pts(93_600)), 910_800);
+    }
+
+    #[test]
+    fn reset_continues_timeline() {
+        let mut unwrapper = TsTimestampUnwrapper::default();
+        assert_eq!(pts(unwrapper.unwrap_pts(90_000)), 90_000);
+        assert_eq!(pts(unwrapper.unwrap_pts(93_600)), 93_600);
+        unwrapper.reset();
+        assert_eq!(pts(unwrapper.unwrap_pts(93_600)), 97_200);
+        assert!(unwrapper.discontinuity_detected());
+        assert_eq!(pts(unwrapper.unwrap_pts(97_200)), 100_800);
+
+        // PCR offsets stay whole 90 kHz ticks so PTS and PCR on the same unwrapper stay aligned
+        let mut unwrapper = TsTimestampUnwrapper::default();
+        assert_eq!(unwrapper.unwrap_pcr(1000, 10).value, 300_010);
+        unwrapper.reset();
+        assert_eq!(unwrapper.unwrap_pcr(0, 20).value, 300_320);
+        assert_eq!(unwrapper.unwrap_pcr(1, 20).value, 300_620);
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(time_from_pts(PTS_WRAP as u64 + 5), CMTime::make(5, TS_SYSTEM_CLOCK_RATE));
+        assert_eq!(time_from_pcr(2, 10), CMTime::make(610, TS_PROGRAM_CLOCK_RATE));
+        assert_eq!(pts_from_time(CMTime::make(-1, 90_000)), Ok(TS_TIMESTAMP_MASK));
+        assert_eq!(pts_from_time(CMTime::make(1, 3)), Ok(30_000));
+        assert_eq!(pcr_from_time(CMTime::make(610, TS_PROGRAM_CLOCK_RATE)), Ok((2, 10)));
+        assert!(pts_from_time(CMTime::INVALID).is_err());
+    }
+}
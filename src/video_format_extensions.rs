@@ -0,0 +1,609 @@
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use std::convert::TryFrom;
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use core_foundation::{
+    array::CFArray,
+    base::{CFType, TCFType},
+    boolean::CFBoolean,
+    data::CFData,
+    dictionary::CFDictionary,
+    number::CFNumber,
+    string::{CFString, CFStringRef},
+    ConcreteCFType,
+};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use crate::format_description::{
+    kCMFormatDescriptionExtension_AlphaChannelMode, kCMFormatDescriptionExtension_AmbientViewingEnvironment,
+    kCMFormatDescriptionExtension_BitsPerComponent, kCMFormatDescriptionExtension_BytesPerRow,
+    kCMFormatDescriptionExtension_ChromaLocationBottomField, kCMFormatDescriptionExtension_ChromaLocationTopField,
+    kCMFormatDescriptionExtension_CleanAperture, kCMFormatDescriptionExtension_ColorPrimaries, kCMFormatDescriptionExtension_ContainsAlphaChannel,
+    kCMFormatDescriptionExtension_ContentColorVolume, kCMFormatDescriptionExtension_ContentLightLevelInfo, kCMFormatDescriptionExtension_Depth,
+    kCMFormatDescriptionExtension_FieldCount, kCMFormatDescriptionExtension_FieldDetail, kCMFormatDescriptionExtension_FormatName,
+    kCMFormatDescriptionExtension_FullRangeVideo, kCMFormatDescriptionExtension_GammaLevel, kCMFormatDescriptionExtension_ICCProfile,
+    kCMFormatDescriptionExtension_MasteringDisplayColorVolume, kCMFormatDescriptionExtension_PixelAspectRatio,
+    kCMFormatDescriptionExtension_SampleDescriptionExtensionAtoms, kCMFormatDescriptionExtension_TransferFunction,
+    kCMFormatDescriptionExtension_YCbCrMatrix, kCMFormatDescriptionKey_CleanApertureHeight, kCMFormatDescriptionKey_CleanApertureHeightRational,
+    kCMFormatDescriptionKey_CleanApertureHorizontalOffset, kCMFormatDescriptionKey_CleanApertureHorizontalOffsetRational,
+    kCMFormatDescriptionKey_CleanApertureVerticalOffset, kCMFormatDescriptionKey_CleanApertureVerticalOffsetRational,
+    kCMFormatDescriptionKey_CleanApertureWidth, kCMFormatDescriptionKey_CleanApertureWidthRational,
+    kCMFormatDescriptionKey_PixelAspectRatioHorizontalSpacing, kCMFormatDescriptionKey_PixelAspectRatioVerticalSpacing, ExtensionsBuilder,
+};
+use crate::{
+    color_description::{ColorDescription, ColorPrimaries, TransferFunction, YCbCrMatrix},
+    hdr_metadata::{AmbientViewingEnvironment, ContentColorVolume, ContentLightLevelInfo, MasteringDisplayColorVolume},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoFormatExtensionsError {
+    InvalidDimensions,
+    InvalidCleanAperture,
+    InvalidFieldCount,
+    InvalidFieldDetail,
+    UnknownFieldDetail,
+    InvalidPixelAspectRatio,
+    InvalidHdrMetadata,
+    UnknownValue,
+    InvalidValue,
+}
+
+impl Display for VideoFormatExtensionsError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            VideoFormatExtensionsError::InvalidDimensions => write!(f, "video dimensions are not positive"),
+            VideoFormatExtensionsError::InvalidCleanAperture => write!(f, "clean aperture is empty or extends beyond the video dimensions"),
+            VideoFormatExtensionsError::InvalidFieldCount => write!(f, "field count is neither 1 nor 2"),
+            VideoFormatExtensionsError::InvalidFieldDetail => write!(f, "field detail requires a field count of 2"),
+            VideoFormatExtensionsError::UnknownFieldDetail => write!(f, "unknown field detail"),
+            VideoFormatExtensionsError::InvalidPixelAspectRatio => write!(f, "pixel aspect ratio spacing is zero"),
+            VideoFormatExtensionsError::InvalidHdrMetadata => write!(f, "HDR metadata cannot be encoded or decoded"),
+            VideoFormatExtensionsError::UnknownValue => write!(f, "unknown value for an extension with a fixed set of values"),
+            VideoFormatExtensionsError::InvalidValue => write!(f, "extension value has the wrong type or is out of range"),
+        }
+    }
+}
+
+impl Error for VideoFormatExtensionsError {}
+
+/// The clean aperture of a video format, in pixels; the offsets are of its centre from the centre of the picture
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CleanAperture {
+    pub width: f64,
+    pub height: f64,
+    pub horizontal_offset: f64,
+    pub vertical_offset: f64,
+}
+
+impl CleanAperture {
+    // The clean aperture that crops the given number of pixels from each edge of a picture of the given dimensions
+    pub fn from_cropping(width: u64, height: u64, left: u64, right: u64, top: u64, bottom: u64) -> Option<Self> {
+        Some(CleanAperture {
+            width: width.checked_sub(left.checked_add(right)?)? as f64,
+            height: height.checked_sub(top.checked_add(bottom)?)? as f64,
+            horizontal_offset: (left as f64 - right as f64) / 2.0,
+            vertical_offset: (top as f64 - bottom as f64) / 2.0,
+        })
+    }
+
+    // Whether the aperture is not empty and lies within a picture of the given dimensions
+    pub fn fits(&self, width: i32, height: i32) -> bool {
+        let fits = |size: f64, offset: f64, dimension: i32| size > 0.0 && size <= dimension as f64 && offset.abs() <= (dimension as f64 - size) / 2.0;
+        fits(self.width, self.horizontal_offset, width) && fits(self.height, self.vertical_offset, height)
+    }
+}
+
+/// The ratio of the horizontal to the vertical spacing of pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PixelAspectRatio {
+    pub horizontal_spacing: u32,
+    pub vertical_spacing: u32,
+}
+
+impl PixelAspectRatio {
+    #[inline]
+    pub fn new(horizontal_spacing: u32, vertical_spacing: u32) -> Self {
+        PixelAspectRatio { horizontal_spacing, vertical_spacing }
+    }
+}
+
+/// The temporal or spatial order of the fields of an interlaced video format
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FieldDetail {
+    TemporalTopFirst,
+    TemporalBottomFirst,
+    SpatialFirstLineEarly,
+    SpatialFirstLineLate,
+}
+
+impl FieldDetail {
+    // The value of the kCMFormatDescriptionExtension_FieldDetail extension
+    pub fn core_media_name(self) -> &'static str {
+        match self {
+            FieldDetail::TemporalTopFirst => "TemporalTopFirst",
+            FieldDetail::TemporalBottomFirst => "TemporalBottomFirst",
+            FieldDetail::SpatialFirstLineEarly => "SpatialFirstLineEarly",
+            FieldDetail::SpatialFirstLineLate => "SpatialFirstLineLate",
+        }
+    }
+
+    pub fn from_core_media_name(name: &str) -> Option<Self> {
+        match name {
+            "TemporalTopFirst" => Some(FieldDetail::TemporalTopFirst),
+            "TemporalBottomFirst" => Some(FieldDetail::TemporalBottomFirst),
+            "SpatialFirstLineEarly" => Some(FieldDetail::SpatialFirstLineEarly),
+            "SpatialFirstLineLate" => Some(FieldDetail::SpatialFirstLineLate),
+            _ => None,
+        }
+    }
+}
+
+/// The position of chroma samples relative to luma samples
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChromaLocation {
+    Left,
+    Center,
+    TopLeft,
+    Top,
+    BottomLeft,
+    Bottom,
+    Dv420,
+}
+
+impl ChromaLocation {
+    // The location signalled by chroma_sample_loc_type in H.264 and HEVC video usability information
+    pub fn from_chroma_sample_loc_type(chroma_sample_loc_type: u32) -> Option<Self> {
+        match chroma_sample_loc_type {
+            0 => Some(ChromaLocation::Left),
+            1 => Some(ChromaLocation::Center),
+            2 => Some(ChromaLocation::TopLeft),
+            3 => Some(ChromaLocation::Top),
+            4 => Some(ChromaLocation::BottomLeft),
+            5 => Some(ChromaLocation::Bottom),
+            _ => None,
+        }
+    }
+
+    // The value of the kCMFormatDescriptionExtension_ChromaLocationTopField and BottomField extensions
+    pub fn core_media_name(self) -> &'static str {
+        match self {
+            ChromaLocation::Left => "Left",
+            ChromaLocation::Center => "Center",
+            ChromaLocation::TopLeft => "TopLeft",
+            ChromaLocation::Top => "Top",
+            ChromaLocation::BottomLeft => "BottomLeft",
+            ChromaLocation::Bottom => "Bottom",
+            ChromaLocation::Dv420 => "DV 4:2:0",
+        }
+    }
+
+    pub fn from_core_media_name(name: &str) -> Option<Self> {
+        match name {
+            "Left" => Some(ChromaLocation::Left),
+            "Center" => Some(ChromaLocation::Center),
+            "TopLeft" => Some(ChromaLocation::TopLeft),
+            "Top" => Some(ChromaLocation::Top),
+            "BottomLeft" => Some(ChromaLocation::BottomLeft),
+            "Bottom" => Some(ChromaLocation::Bottom),
+            "DV 4:2:0" => Some(ChromaLocation::Dv420),
+            _ => None,
+        }
+    }
+}
+
+/// How the colour components of pixels with an alpha channel are stored
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AlphaChannelMode {
+    StraightAlpha,
+    PremultipliedAlpha,
+}
+
+impl AlphaChannelMode {
+    // The value of the kCMFormatDescriptionExtension_AlphaChannelMode extension
+    pub fn core_media_name(self) -> &'static str {
+        match self {
+            AlphaChannelMode::StraightAlpha => "StraightAlpha",
+            AlphaChannelMode::PremultipliedAlpha => "PremultipliedAlpha",
+        }
+    }
+
+    pub fn from_core_media_name(name: &str) -> Option<Self> {
+        match name {
+            "StraightAlpha" => Some(AlphaChannelMode::StraightAlpha),
+            "PremultipliedAlpha" => Some(AlphaChannelMode::PremultipliedAlpha),
+            _ => None,
+        }
+    }
+}
+
+/// The kCMFormatDescriptionExtension_* extensions of a video format description; None leaves an extension unset
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VideoFormatExtensions {
+    pub format_name: Option<String>,
+    pub depth: Option<u32>,
+    pub clean_aperture: Option<CleanAperture>,
+    pub field_count: Option<u32>,
+    pub field_detail: Option<FieldDetail>,
+    pub pixel_aspect_ratio: Option<PixelAspectRatio>,
+    // Values without a CoreMedia name are left unset by to_dictionary
+    pub color_primaries: Option<ColorPrimaries>,
+    pub transfer_function: Option<TransferFunction>,
+    pub ycbcr_matrix: Option<YCbCrMatrix>,
+    pub full_range_video: Option<bool>,
+    pub chroma_location_top_field: Option<ChromaLocation>,
+    pub chroma_location_bottom_field: Option<ChromaLocation>,
+    pub bytes_per_row: Option<u32>,
+    pub bits_per_component: Option<u32>,
+    pub contains_alpha_channel: Option<bool>,
+    pub alpha_channel_mode: Option<AlphaChannelMode>,
+    pub icc_profile: Option<Vec<u8>>,
+    pub mastering_display_color_volume: Option<MasteringDisplayColorVolume>,
+    pub content_light_level_info: Option<ContentLightLevelInfo>,
+    pub content_color_volume: Option<ContentColorVolume>,
+    pub ambient_viewing_environment: Option<AmbientViewingEnvironment>,
+    // The kCMFormatDescriptionExtension_SampleDescriptionExtensionAtoms, such as avcC, in order; an atom may occur
+    // more than once
+    pub sample_description_extension_atoms: Vec<(String, Vec<u8>)>,
+}
+
+impl VideoFormatExtensions {
+    // The colour extensions, with unset ones reported as unspecified and video range
+    pub fn color_description(&self) -> ColorDescription {
+        ColorDescription {
+            primaries: self.color_primaries.unwrap_or_default(),
+            transfer_function: self.transfer_function.unwrap_or_default(),
+            ycbcr_matrix: self.ycbcr_matrix.unwrap_or_default(),
+            full_range: self.full_range_video.unwrap_or(false),
+        }
+    }
+
+    pub fn set_color_description(&mut self, color: &ColorDescription) {
+        self.color_primaries = Some(color.primaries);
+        self.transfer_function = Some(color.transfer_function);
+        self.ycbcr_matrix = Some(color.ycbcr_matrix);
+        self.full_range_video = Some(color.full_range);
+    }
+
+    // The first sample description extension atom with the given name
+    pub fn atom(&self, name: &str) -> Option<&[u8]> {
+        self.sample_description_extension_atoms.iter().find(|(atom, _)| atom == name).map(|(_, data)| data.as_slice())
+    }
+
+    // Replaces every sample description extension atom with the given name
+    pub fn set_atom(&mut self, name: &str, data: &[u8]) {
+        self.sample_description_extension_atoms.retain(|(atom, _)| atom != name);
+        self.sample_description_extension_atoms.push((name.to_string(), data.to_vec()));
+    }
+
+    // Checks the extensions that do not depend on the video dimensions
+    fn check(&self) -> Result<(), VideoFormatExtensionsError> {
+        match self.field_count {
+            None | Some(1) | Some(2) => {}
+            Some(_) => return Err(VideoFormatExtensionsError::InvalidFieldCount),
+        }
+        if self.field_detail.is_some() && self.field_count != Some(2) {
+            return Err(VideoFormatExtensionsError::InvalidFieldDetail);
+        }
+        if self.pixel_aspect_ratio.is_some_and(|ratio| ratio.horizontal_spacing == 0 || ratio.vertical_spacing == 0) {
+            return Err(VideoFormatExtensionsError::InvalidPixelAspectRatio);
+        }
+        Ok(())
+    }
+
+    // Checks that the extensions are consistent with each other and with a video format of the given dimensions
+    pub fn validate(&self, width: i32, height: i32) -> Result<(), VideoFormatExtensionsError> {
+        if width <= 0 || height <= 0 {
+            return Err(VideoFormatExtensionsError::InvalidDimensions);
+        }
+        if self.clean_aperture.is_some_and(|aperture| !aperture.fits(width, height)) {
+            return Err(VideoFormatExtensionsError::InvalidCleanAperture);
+        }
+        self.check()
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl VideoFormatExtensions {
+    // Adds the extensions to a dictionary under construction
+    pub(crate) fn write_to(&self, builder: &mut ExtensionsBuilder) -> Result<(), VideoFormatExtensionsError> {
+        self.check()?;
+        let hdr_error = |_| VideoFormatExtensionsError::InvalidHdrMetadata;
+        unsafe {
+            if let Some(format_name) = &self.format_name {
+                builder.set(kCMFormatDescriptionExtension_FormatName, CFString::new(format_name).as_CFType());
+            }
+            if let Some(depth) = self.depth {
+                builder.set_number(kCMFormatDescriptionExtension_Depth, depth)?;
+            }
+            if let Some(aperture) = self.clean_aperture {
+                let aperture = CFDictionary::from_CFType_pairs(&[
+                    (CFString::wrap_under_get_rule(kCMFormatDescriptionKey_CleanApertureWidth), CFNumber::from(aperture.width)),
+                    (CFString::wrap_under_get_rule(kCMFormatDescriptionKey_CleanApertureHeight), CFNumber::from(aperture.height)),
+                    (
+                        CFString::wrap_under_get_rule(kCMFormatDescriptionKey_CleanApertureHorizontalOffset),
+                        CFNumber::from(aperture.horizontal_offset),
+                    ),
+                    (CFString::wrap_under_get_rule(kCMFormatDescriptionKey_CleanApertureVerticalOffset), CFNumber::from(aperture.vertical_offset)),
+                ]);
+                builder.set(kCMFormatDescriptionExtension_CleanAperture, aperture.as_CFType());
+            }
+            if let Some(field_count) = self.field_count {
+                builder.set_number(kCMFormatDescriptionExtension_FieldCount, field_count)?;
+            }
+            if let Some(field_detail) = self.field_detail {
+                builder.set_name(kCMFormatDescriptionExtension_FieldDetail, field_detail.core_media_name());
+            }
+            if let Some(ratio) = self.pixel_aspect_ratio {
+                let horizontal_spacing = i32::try_from(ratio.horizontal_spacing).map_err(|_| VideoFormatExtensionsError::InvalidValue)?;
+                let vertical_spacing = i32::try_from(ratio.vertical_spacing).map_err(|_| VideoFormatExtensionsError::InvalidValue)?;
+                let ratio = CFDictionary::from_CFType_pairs(&[
+                    (CFString::wrap_under_get_rule(kCMFormatDescriptionKey_PixelAspectRatioHorizontalSpacing), CFNumber::from(horizontal_spacing)),
+                    (CFString::wrap_under_get_rule(kCMFormatDescriptionKey_PixelAspectRatioVerticalSpacing), CFNumber::from(vertical_spacing)),
+                ]);
+                builder.set(kCMFormatDescriptionExtension_PixelAspectRatio, ratio.as_CFType());
+            }
+            if let Some(name) = self.color_primaries.and_then(ColorPrimaries::core_media_name) {
+                builder.set_name(kCMFormatDescriptionExtension_ColorPrimaries, name);
+            }
+            if let Some(transfer_function) = self.transfer_function {
+                if let Some(name) = transfer_function.core_media_name() {
+                    builder.set_name(kCMFormatDescriptionExtension_TransferFunction, name);
+                }
+                if let Some(gamma_level) = transfer_function.gamma_level() {
+                    builder.set(kCMFormatDescriptionExtension_GammaLevel, CFNumber::from(gamma_level).as_CFType());
+                }
+            }
+            if let Some(name) = self.ycbcr_matrix.and_then(YCbCrMatrix::core_media_name) {
+                builder.set_name(kCMFormatDescriptionExtension_YCbCrMatrix, name);
+            }
+            if let Some(full_range_video) = self.full_range_video {
+                builder.set(kCMFormatDescriptionExtension_FullRangeVideo, CFBoolean::from(full_range_video).as_CFType());
+            }
+            if let Some(location) = self.chroma_location_top_field {
+                builder.set_name(kCMFormatDescriptionExtension_ChromaLocationTopField, location.core_media_name());
+            }
+            if let Some(location) = self.chroma_location_bottom_field {
+                builder.set_name(kCMFormatDescriptionExtension_ChromaLocationBottomField, location.core_media_name());
+            }
+            if let Some(bytes_per_row) = self.bytes_per_row {
+                builder.set_number(kCMFormatDescriptionExtension_BytesPerRow, bytes_per_row)?;
+            }
+            if let Some(bits_per_component) = self.bits_per_component {
+                builder.set_number(kCMFormatDescriptionExtension_BitsPerComponent, bits_per_component)?;
+            }
+            if let Some(contains_alpha_channel) = self.contains_alpha_channel {
+                builder.set(kCMFormatDescriptionExtension_ContainsAlphaChannel, CFBoolean::from(contains_alpha_channel).as_CFType());
+            }
+            if let Some(mode) = self.alpha_channel_mode {
+                builder.set_name(kCMFormatDescriptionExtension_AlphaChannelMode, mode.core_media_name());
+            }
+            if let Some(icc_profile) = &self.icc_profile {
+                builder.set_data(kCMFormatDescriptionExtension_ICCProfile, icc_profile);
+            }
+            if let Some(value) = self.mastering_display_color_volume {
+                builder.set_data(kCMFormatDescriptionExtension_MasteringDisplayColorVolume, &value.to_bytes().map_err(hdr_error)?);
+            }
+            if let Some(value) = self.content_light_level_info {
+                builder.set_data(kCMFormatDescriptionExtension_ContentLightLevelInfo, &value.to_bytes());
+            }
+            if let Some(value) = self.content_color_volume {
+                builder.set_data(kCMFormatDescriptionExtension_ContentColorVolume, &value.to_bytes().map_err(hdr_error)?);
+            }
+            if let Some(value) = self.ambient_viewing_environment {
+                builder.set_data(kCMFormatDescriptionExtension_AmbientViewingEnvironment, &value.to_bytes().map_err(hdr_error)?);
+            }
+        }
+        for (atom, data) in &self.sample_description_extension_atoms {
+            builder.atom(atom, data);
+        }
+        Ok(())
+    }
+
+    // The extensions dictionary taken by CMVideoFormatDescription::new
+    pub fn to_dictionary(&self) -> Result<CFDictionary<CFString, CFType>, VideoFormatExtensionsError> {
+        let mut builder = ExtensionsBuilder::default();
+        self.write_to(&mut builder)?;
+        Ok(builder.build())
+    }
+
+    // Reads the extensions this type covers from the dictionary returned by CMFormatDescription::get_extensions,
+    // ignoring any others
+    pub fn from_dictionary(extensions: &CFDictionary<CFString, CFType>) -> Result<Self, VideoFormatExtensionsError> {
+        let reader = ExtensionsReader(extensions);
+        let hdr_error = |_| VideoFormatExtensionsError::InvalidHdrMetadata;
+        unsafe {
+            let transfer_function = match reader.string(kCMFormatDescriptionExtension_TransferFunction)? {
+                Some(name) if name == "UseGamma" => {
+                    let gamma_level = reader.number(kCMFormatDescriptionExtension_GammaLevel)?.and_then(|number| number.to_f64());
+                    Some(gamma_level.and_then(TransferFunction::from_gamma_level).ok_or(VideoFormatExtensionsError::UnknownValue)?)
+                }
+                Some(name) => Some(TransferFunction::from_core_media_name(&name).ok_or(VideoFormatExtensionsError::UnknownValue)?),
+                None => None,
+            };
+            let field_detail = match reader.string(kCMFormatDescriptionExtension_FieldDetail)? {
+                Some(name) => Some(FieldDetail::from_core_media_name(&name).ok_or(VideoFormatExtensionsError::UnknownFieldDetail)?),
+                None => None,
+            };
+            let extensions = VideoFormatExtensions {
+                format_name: reader.string(kCMFormatDescriptionExtension_FormatName)?,
+                depth: reader.u32(kCMFormatDescriptionExtension_Depth)?,
+                clean_aperture: reader.clean_aperture()?,
+                field_count: reader.u32(kCMFormatDescriptionExtension_FieldCount)?,
+                field_detail,
+                pixel_aspect_ratio: reader.pixel_aspect_ratio()?,
+                color_primaries: reader.named(kCMFormatDescriptionExtension_ColorPrimaries, ColorPrimaries::from_core_media_name)?,
+                transfer_function,
+                ycbcr_matrix: reader.named(kCMFormatDescriptionExtension_YCbCrMatrix, YCbCrMatrix::from_core_media_name)?,
+                full_range_video: reader.boolean(kCMFormatDescriptionExtension_FullRangeVideo)?,
+                chroma_location_top_field: reader
+                    .named(kCMFormatDescriptionExtension_ChromaLocationTopField, ChromaLocation::from_core_media_name)?,
+                chroma_location_bottom_field: reader
+                    .named(kCMFormatDescriptionExtension_ChromaLocationBottomField, ChromaLocation::from_core_media_name)?,
+                bytes_per_row: reader.u32(kCMFormatDescriptionExtension_BytesPerRow)?,
+                bits_per_component: reader.u32(kCMFormatDescriptionExtension_BitsPerComponent)?,
+                contains_alpha_channel: reader.boolean(kCMFormatDescriptionExtension_ContainsAlphaChannel)?,
+                alpha_channel_mode: reader.named(kCMFormatDescriptionExtension_AlphaChannelMode, AlphaChannelMode::from_core_media_name)?,
+                icc_profile: reader.data(kCMFormatDescriptionExtension_ICCProfile)?,
+                mastering_display_color_volume: reader
+                    .data(kCMFormatDescriptionExtension_MasteringDisplayColorVolume)?
+                    .map(|data| MasteringDisplayColorVolume::parse(&data))
+                    .transpose()
+                    .map_err(hdr_error)?,
+                content_light_level_info: reader
+                    .data(kCMFormatDescriptionExtension_ContentLightLevelInfo)?
+                    .map(|data| ContentLightLevelInfo::parse(&data))
+                    .transpose()
+                    .map_err(hdr_error)?,
+                content_color_volume: reader
+                    .data(kCMFormatDescriptionExtension_ContentColorVolume)?
+                    .map(|data| ContentColorVolume::parse(&data))
+                    .transpose()
+                    .map_err(hdr_error)?,
+                ambient_viewing_environment: reader
+                    .data(kCMFormatDescriptionExtension_AmbientViewingEnvironment)?
+                    .map(|data| AmbientViewingEnvironment::parse(&data))
+                    .transpose()
+                    .map_err(hdr_error)?,
+                sample_description_extension_atoms: reader.atoms()?,
+            };
+            extensions.check()?;
+            Ok(extensions)
+        }
+    }
+}
+
+// Reads typed values from an extensions dictionary; a value of the wrong type is an error
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+struct ExtensionsReader<'a>(&'a CFDictionary<CFString, CFType>);
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl<'a> ExtensionsReader<'a> {
+    #[inline]
+    fn get(&self, key: CFStringRef) -> Option<CFType> {
+        self.0.find(&unsafe { CFString::wrap_under_get_rule(key) }).map(|value| value.clone())
+    }
+
+    fn typed<T: ConcreteCFType>(&self, key: CFStringRef) -> Result<Option<T>, VideoFormatExtensionsError> {
+        match self.get(key) {
+            Some(value) => value.downcast_into::<T>().map(Some).ok_or(VideoFormatExtensionsError::InvalidValue),
+            None => Ok(None),
+        }
+    }
+
+    #[inline]
+    fn string(&self, key: CFStringRef) -> Result<Option<String>, VideoFormatExtensionsError> {
+        Ok(self.typed::<CFString>(key)?.map(|value| value.to_string()))
+    }
+
+    // A string extension with a fixed set of values
+    fn named<T>(&self, key: CFStringRef, from_name: fn(&str) -> Option<T>) -> Result<Option<T>, VideoFormatExtensionsError> {
+        match self.string(key)? {
+            Some(name) => from_name(&name).map(Some).ok_or(VideoFormatExtensionsError::UnknownValue),
+            None => Ok(None),
+        }
+    }
+
+    #[inline]
+    fn number(&self, key: CFStringRef) -> Result<Option<CFNumber>, VideoFormatExtensionsError> {
+        self.typed::<CFNumber>(key)
+    }
+
+    fn u32(&self, key: CFStringRef) -> Result<Option<u32>, VideoFormatExtensionsError> {
+        match self.number(key)? {
+            Some(number) => number.to_i64().and_then(|value| u32::try_from(value).ok()).map(Some).ok_or(VideoFormatExtensionsError::InvalidValue),
+            None => Ok(None),
+        }
+    }
+
+    #[inline]
+    fn boolean(&self, key: CFStringRef) -> Result<Option<bool>, VideoFormatExtensionsError> {
+        Ok(self.typed::<CFBoolean>(key)?.map(bool::from))
+    }
+
+    #[inline]
+    fn data(&self, key: CFStringRef) -> Result<Option<Vec<u8>>, VideoFormatExtensionsError> {
+        Ok(self.typed::<CFData>(key)?.map(|data| data.bytes().to_vec()))
+    }
+
+    #[inline]
+    fn dictionary(&self, key: CFStringRef) -> Result<Option<CFDictionary<CFString, CFType>>, VideoFormatExtensionsError> {
+        Ok(self.typed::<CFDictionary>(key)?.map(|dictionary| unsafe { CFDictionary::wrap_under_get_rule(dictionary.as_concrete_TypeRef()) }))
+    }
+
+    // A number, or a CFArray of a numerator and denominator for the keys ending in Rational
+    fn real(&self, key: CFStringRef, rational_key: CFStringRef) -> Result<f64, VideoFormatExtensionsError> {
+        if let Some(number) = self.number(key)? {
+            return number.to_f64().ok_or(VideoFormatExtensionsError::InvalidValue);
+        }
+        let rational = self.typed::<CFArray>(rational_key)?.ok_or(VideoFormatExtensionsError::InvalidValue)?;
+        let term = |index| {
+            let value = unsafe { CFType::wrap_under_get_rule(*rational.get(index)? as _) };
+            value.downcast::<CFNumber>()?.to_f64()
+        };
+        match (rational.len(), term(0), term(1)) {
+            (2, Some(numerator), Some(denominator)) if denominator != 0.0 => Ok(numerator / denominator),
+            _ => Err(VideoFormatExtensionsError::InvalidValue),
+        }
+    }
+
+    fn clean_aperture(&self) -> Result<Option<CleanAperture>, VideoFormatExtensionsError> {
+        let aperture = match self.dictionary(unsafe { kCMFormatDescriptionExtension_CleanAperture })? {
+            Some(aperture) => aperture,
+            None => return Ok(None),
+        };
+        let aperture = ExtensionsReader(&aperture);
+        unsafe {
+            Ok(Some(CleanAperture {
+                width: aperture.real(kCMFormatDescriptionKey_CleanApertureWidth, kCMFormatDescriptionKey_CleanApertureWidthRational)?,
+                height: aperture.real(kCMFormatDescriptionKey_CleanApertureHeight, kCMFormatDescriptionKey_CleanApertureHeightRational)?,
+                horizontal_offset: aperture
+                    .real(kCMFormatDescriptionKey_CleanApertureHorizontalOffset, kCMFormatDescriptionKey_CleanApertureHorizontalOffsetRational)?,
+                vertical_offset: aperture
+                    .real(kCMFormatDescriptionKey_CleanApertureVerticalOffset, kCMFormatDescriptionKey_CleanApertureVerticalOffsetRational)?,
+            }))
+        }
+    }
+
+    fn pixel_aspect_ratio(&self) -> Result<Option<PixelAspectRatio>, VideoFormatExtensionsError> {
+        let ratio = match self.dictionary(unsafe { kCMFormatDescriptionExtension_PixelAspectRatio })? {
+            Some(ratio) => ratio,
+            None => return Ok(None),
+        };
+        let ratio = ExtensionsReader(&ratio);
+        let horizontal_spacing = ratio.u32(unsafe { kCMFormatDescriptionKey_PixelAspectRatioHorizontalSpacing })?;
+        let vertical_spacing = ratio.u32(unsafe { kCMFormatDescriptionKey_PixelAspectRatioVerticalSpacing })?;
+        match (horizontal_spacing, vertical_spacing) {
+            (Some(horizontal_spacing), Some(vertical_spacing)) => Ok(Some(PixelAspectRatio { horizontal_spacing, vertical_spacing })),
+            _ => Err(VideoFormatExtensionsError::InvalidValue),
+        }
+    }
+
+    // The sample description extension atoms; an atom stored as a CFArray contributes one entry per element
+    fn atoms(&self) -> Result<Vec<(String, Vec<u8>)>, VideoFormatExtensionsError> {
+        let atoms = match self.dictionary(unsafe { kCMFormatDescriptionExtension_SampleDescriptionExtensionAtoms })? {
+            Some(atoms) => atoms,
+            None => return Ok(Vec::new()),
+        };
+        let (names, values) = atoms.get_keys_and_values();
+        let mut entries = Vec::with_capacity(names.len());
+        for (name, value) in names.into_iter().zip(values) {
+            let name = unsafe { CFType::wrap_under_get_rule(name as _) }.downcast::<CFString>().ok_or(VideoFormatExtensionsError::InvalidValue)?;
+            let value = unsafe { CFType::wrap_under_get_rule(value as _) };
+            if let Some(data) = value.downcast::<CFData>() {
+                entries.push((name.to_string(), data.bytes().to_vec()));
+                continue;
+            }
+            let array = value.downcast::<CFArray>().ok_or(VideoFormatExtensionsError::InvalidValue)?;
+            for element in array.iter() {
+                let element = unsafe { CFType::wrap_under_get_rule(*element as _) };
+                let data = element.downcast::<CFData>().ok_or(VideoFormatExtensionsError::InvalidValue)?;
+                entries.push((name.to_string(), data.bytes().to_vec()));
+            }
+        }
+        Ok(entries)
+    }
+}
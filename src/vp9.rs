@@ -0,0 +1,111 @@
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use core_foundation::{base::CFType, dictionary::CFDictionary, propertylist::CFPropertyList, string::CFString};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use crate::format_description::{extensions_with_atom, find_extension_atom};
+
+pub const VP_CONFIGURATION_ATOM: &str = "vpcC";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VpConfigError {
+    UnexpectedEnd,
+    UnsupportedVersion,
+    ValueOutOfRange,
+    MissingConfigurationAtom,
+}
+
+impl Display for VpConfigError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            VpConfigError::UnexpectedEnd => write!(f, "unexpected end of configuration record"),
+            VpConfigError::UnsupportedVersion => write!(f, "unsupported configuration version"),
+            VpConfigError::ValueOutOfRange => write!(f, "field value does not fit in the configuration record"),
+            VpConfigError::MissingConfigurationAtom => write!(f, "no vpcC atom in the sample description extension atoms"),
+        }
+    }
+}
+
+impl Error for VpConfigError {}
+
+/// The VPCodecConfigurationRecord carried in a version 1 vpcC box, as defined in the VP Codec ISO Media File Format
+/// Binding
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct VpCodecConfigurationRecord {
+    pub profile: u8,
+    pub level: u8,
+    pub bit_depth: u8,
+    pub chroma_subsampling: u8,
+    pub video_full_range_flag: bool,
+    pub colour_primaries: u8,
+    pub transfer_characteristics: u8,
+    pub matrix_coefficients: u8,
+    // Always empty for VP8 and VP9
+    pub codec_initialization_data: Vec<u8>,
+}
+
+impl VpCodecConfigurationRecord {
+    // Parses the body of a vpcC box, starting with its version and flags
+    pub fn parse(data: &[u8]) -> Result<Self, VpConfigError> {
+        if data.len() < 4 {
+            return Err(VpConfigError::UnexpectedEnd);
+        }
+        if data[0] != 1 {
+            return Err(VpConfigError::UnsupportedVersion);
+        }
+        if data.len() < 12 {
+            return Err(VpConfigError::UnexpectedEnd);
+        }
+        let len = u16::from_be_bytes([data[10], data[11]]) as usize;
+        let codec_initialization_data = data.get(12..12 + len).ok_or(VpConfigError::UnexpectedEnd)?.to_vec();
+        Ok(VpCodecConfigurationRecord {
+            profile: data[4],
+            level: data[5],
+            bit_depth: data[6] >> 4,
+            chroma_subsampling: data[6] >> 1 & 0x07,
+            video_full_range_flag: data[6] & 0x01 != 0,
+            colour_primaries: data[7],
+            transfer_characteristics: data[8],
+            matrix_coefficients: data[9],
+            codec_initialization_data,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, VpConfigError> {
+        if self.bit_depth > 15 || self.chroma_subsampling > 7 {
+            return Err(VpConfigError::ValueOutOfRange);
+        }
+        let len = u16::try_from(self.codec_initialization_data.len()).map_err(|_| VpConfigError::ValueOutOfRange)?;
+        let mut data = Vec::with_capacity(12 + self.codec_initialization_data.len());
+        data.extend_from_slice(&[1, 0, 0, 0]);
+        data.push(self.profile);
+        data.push(self.level);
+        data.push(self.bit_depth << 4 | self.chroma_subsampling << 1 | self.video_full_range_flag as u8);
+        data.push(self.colour_primaries);
+        data.push(self.transfer_characteristics);
+        data.push(self.matrix_coefficients);
+        data.extend_from_slice(&len.to_be_bytes());
+        data.extend_from_slice(&self.codec_initialization_data);
+        Ok(data)
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl VpCodecConfigurationRecord {
+    // The extensions dictionary carrying the record as a vpcC sample description extension atom, for
+    // CMVideoFormatDescription::new with kCMVideoCodecType_VP9
+    pub fn to_extensions(&self) -> Result<CFDictionary<CFString, CFType>, VpConfigError> {
+        Ok(extensions_with_atom(VP_CONFIGURATION_ATOM, &self.to_bytes()?))
+    }
+
+    // Reads the record from the value of the kCMFormatDescriptionExtension_SampleDescriptionExtensionAtoms extension
+    pub fn from_extension_atoms(atoms: &CFPropertyList) -> Result<Self, VpConfigError> {
+        let data = find_extension_atom(atoms, VP_CONFIGURATION_ATOM).ok_or(VpConfigError::MissingConfigurationAtom)?;
+        Self::parse(&data)
+    }
+}